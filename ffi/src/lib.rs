@@ -26,6 +26,20 @@ mod ffi {
         pub index: u8,
     }
 
+    pub enum ShapeColor {
+        Green = 0,
+        Red = 1,
+        Yellow = 2,
+        Blue = 3,
+    }
+
+    /// An arrow, or a highlighted square if `from` and `to` are the same
+    pub struct Shape {
+        pub color: ShapeColor,
+        pub from: Square,
+        pub to: Square,
+    }
+
     extern "Rust" {
         fn square_from_coords(file: u8, rank: u8) -> Square;
 
@@ -45,6 +59,9 @@ mod ffi {
 
         fn is_en_passant(&self) -> bool;
         fn is_castle(&self) -> bool;
+        /// Whether a piece from the pocket is dropped, in Crazyhouse
+        fn is_drop(&self) -> bool;
+        fn role(&self) -> Role;
         fn castle_rook_from(&self) -> Square;
         fn castle_rook_to(&self) -> Square;
 
@@ -65,11 +82,18 @@ mod ffi {
 
         fn hints(&self, src: Square) -> Vec<Square>;
         fn captures(&self, src: Square) -> Vec<Square>;
+
+        /// Pieces in hand of the given side, one entry per piece; empty
+        /// outside Crazyhouse
+        fn pocket(&self, color: Color) -> Vec<Piece>;
+        fn legal_drop(&self, role: Role, dest: Square) -> *const Move;
+        fn drop_hints(&self, role: Role) -> Vec<Square>;
     }
 
     extern "Rust" {
         type Node;
 
+        fn id(&self) -> u64;
         fn position(&self) -> Box<CurPosition>;
 
         fn prev_move(&self) -> *const Move;
@@ -80,6 +104,15 @@ mod ffi {
         fn siblings(&self) -> Vec<Node>;
         fn mainline_nodes(&self) -> Vec<Node>;
 
+        fn shapes(&self) -> Vec<Shape>;
+        fn set_shapes(&self, shapes: Vec<Shape>);
+
+        /// Remaining time of the player who just moved, in milliseconds
+        fn has_clock(&self) -> bool;
+        fn clock_millis(&self) -> u64;
+        fn set_clock_millis(&self, millis: u64);
+        fn clear_clock(&self);
+
         fn new_variation(&self, m: &Move) -> *const Node;
     }
 
@@ -89,6 +122,7 @@ mod ffi {
         fn game_from_pgn(pgn_str: String) -> *mut GameTree;
 
         fn root(&self) -> Box<Node>;
+        fn node_by_id(&self, id: u64) -> *const Node;
         fn initial_position(&self) -> Box<CurPosition>;
 
         fn pgn(&self) -> String;
@@ -110,6 +144,8 @@ macro_rules! convert_enum {
 
 convert_enum!(sac::Color, ffi::Color, Black, White,);
 
+convert_enum!(ffi::Color, sac::Color, Black, White,);
+
 convert_enum!(
     sac::Role,
     ffi::Role,
@@ -132,6 +168,26 @@ convert_enum!(
     King,
 );
 
+impl From<sac::game::ShapeColor> for ffi::ShapeColor {
+    fn from(value: sac::game::ShapeColor) -> ffi::ShapeColor {
+        match value {
+            sac::game::ShapeColor::Green => ffi::ShapeColor::Green,
+            sac::game::ShapeColor::Red => ffi::ShapeColor::Red,
+            sac::game::ShapeColor::Yellow => ffi::ShapeColor::Yellow,
+            sac::game::ShapeColor::Blue => ffi::ShapeColor::Blue,
+        }
+    }
+}
+
+convert_enum!(
+    ffi::ShapeColor,
+    sac::game::ShapeColor,
+    Green,
+    Red,
+    Yellow,
+    Blue,
+);
+
 impl From<sac::Piece> for ffi::Piece {
     fn from(value: sac::Piece) -> ffi::Piece {
         ffi::Piece {
@@ -155,6 +211,22 @@ impl From<ffi::Square> for sac::Square {
     }
 }
 
+impl From<sac::game::Shape> for ffi::Shape {
+    fn from(value: sac::game::Shape) -> ffi::Shape {
+        ffi::Shape {
+            color: value.color.into(),
+            from: value.from.into(),
+            to: value.to.into(),
+        }
+    }
+}
+
+impl From<ffi::Shape> for sac::game::Shape {
+    fn from(value: ffi::Shape) -> sac::game::Shape {
+        sac::game::Shape::arrow(value.color.into(), value.from.into(), value.to.into())
+    }
+}
+
 fn square_from_coords(file: u8, rank: u8) -> ffi::Square {
     let sq = sac::Square::from_coords(sac::File::new(file as u32), sac::Rank::new(rank as u32));
     ffi::Square {
@@ -185,10 +257,8 @@ impl Move {
     }
 
     fn from(&self) -> ffi::Square {
-        self.inner
-            .from()
-            .expect("a chess move always comes from somewhere")
-            .into()
+        // A dropped piece appears on its square
+        self.inner.from().unwrap_or(self.inner.to()).into()
     }
 
     fn to(&self) -> ffi::Square {
@@ -227,6 +297,14 @@ impl Move {
         self.inner.is_castle()
     }
 
+    fn is_drop(&self) -> bool {
+        matches!(self.inner, sac::Move::Put { .. })
+    }
+
+    fn role(&self) -> ffi::Role {
+        self.inner.role().into()
+    }
+
     fn castle_rook_from(&self) -> ffi::Square {
         if let sac::Move::Castle { king: _, rook } = self.inner {
             return rook.into();
@@ -252,7 +330,7 @@ impl Move {
     }
 }
 
-struct CurPosition(sac::Chess);
+struct CurPosition(sac::VariantPosition);
 
 impl CurPosition {
     fn to_string(&self) -> String {
@@ -302,7 +380,7 @@ impl CurPosition {
                 .0
                 .legal_moves()
                 .into_iter()
-                .filter(|v| v.from().unwrap() == src_sq)
+                .filter(|v| v.from() == Some(src_sq))
                 .collect::<Vec<sac::Move>>();
 
             for m in move_vec {
@@ -347,6 +425,47 @@ impl CurPosition {
     fn captures(&self, src: ffi::Square) -> Vec<ffi::Square> {
         self.legal_moves(src).1
     }
+
+    fn pocket(&self, color: ffi::Color) -> Vec<ffi::Piece> {
+        let color: sac::Color = color.into();
+        let pockets = if let Some(inner) = self.0.pockets() {
+            inner
+        } else {
+            return Vec::new();
+        };
+
+        let mut ret = Vec::new();
+        for role in sac::Role::ALL {
+            let count = *pockets.get(color).get(role);
+            for _ in 0..count {
+                ret.push(sac::Piece { color, role }.into());
+            }
+        }
+        ret
+    }
+
+    fn legal_drop(&self, role: ffi::Role, dst: ffi::Square) -> *const Move {
+        let role: sac::Role = role.into();
+        let dst: sac::Square = dst.into();
+
+        let ret = if let Some(inner) = self.drops(role).into_iter().find(|m| m.to() == dst) {
+            inner
+        } else {
+            return std::ptr::null();
+        };
+
+        let san = sac::SanPlus::from_move(self.0.clone(), &ret);
+        let ret = Box::new(Move { inner: ret, san });
+
+        Box::into_raw(ret)
+    }
+
+    fn drop_hints(&self, role: ffi::Role) -> Vec<ffi::Square> {
+        self.drops(role.into())
+            .into_iter()
+            .map(|m| m.to().into())
+            .collect::<Vec<ffi::Square>>()
+    }
 }
 
 impl CurPosition {
@@ -356,7 +475,7 @@ impl CurPosition {
             .0
             .legal_moves()
             .into_iter()
-            .filter(|v| v.from().unwrap() == sq)
+            .filter(|v| v.from() == Some(sq))
             .collect::<Vec<sac::Move>>();
         move_vec.dedup_by(|l, r| {
             if !l.is_promotion() || !r.is_promotion() {
@@ -395,29 +514,34 @@ impl CurPosition {
 
         (move_vec, capture_vec)
     }
+
+    fn drops(&self, role: sac::Role) -> Vec<sac::Move> {
+        self.0
+            .legal_moves()
+            .into_iter()
+            .filter(|m| matches!(m, sac::Move::Put { role: r, .. } if *r == role))
+            .collect::<Vec<sac::Move>>()
+    }
 }
 
 struct Node(sac::game::Node);
 
 impl Node {
+    fn id(&self) -> u64 {
+        self.0.id()
+    }
+
     fn position(&self) -> Box<CurPosition> {
         Box::new(CurPosition(self.0.position()))
     }
 
     fn prev_move(&self) -> *const Move {
-        let parent = if let Some(inner) = self.0.parent() {
-            inner
-        } else {
-            return std::ptr::null();
-        };
-        let m = if let Some(inner) = self.0.prev_move() {
-            inner
+        let (m, san) = if let (Some(m), Some(san)) = (self.0.prev_move(), self.0.san()) {
+            (m, san)
         } else {
             return std::ptr::null();
         };
 
-        let pos_prev = parent.position();
-        let san = sac::SanPlus::from_move(pos_prev, &m);
         let ret = Box::new(Move { inner: m, san });
 
         Box::into_raw(ret)
@@ -456,14 +580,35 @@ impl Node {
     }
 
     fn mainline_nodes(&self) -> Vec<Node> {
-        let mut node = self.0.clone();
-        let mut node_vec: Vec<sac::game::Node> = Vec::new();
-        while let Some(node_next) = node.mainline() {
-            node_vec.push(node_next.clone());
-            node = node_next;
-        }
+        self.0.mainline_iter().map(Node).collect::<Vec<_>>()
+    }
 
-        node_vec.into_iter().map(Node).collect::<Vec<_>>()
+    fn shapes(&self) -> Vec<ffi::Shape> {
+        self.0.shapes().into_iter().map(ffi::Shape::from).collect()
+    }
+
+    fn set_shapes(&self, shapes: Vec<ffi::Shape>) {
+        self.0
+            .clone()
+            .set_shapes(shapes.into_iter().map(sac::game::Shape::from).collect());
+    }
+
+    fn has_clock(&self) -> bool {
+        self.0.clock().is_some()
+    }
+
+    fn clock_millis(&self) -> u64 {
+        self.0.clock().map_or(0, |clock| clock.as_millis() as u64)
+    }
+
+    fn set_clock_millis(&self, millis: u64) {
+        self.0
+            .clone()
+            .set_clock(Some(std::time::Duration::from_millis(millis)));
+    }
+
+    fn clear_clock(&self) {
+        self.0.clone().set_clock(None);
     }
 
     fn new_variation(&self, m: &Move) -> *const Node {
@@ -502,6 +647,16 @@ impl GameTree {
         Box::new(Node(self.0.root()))
     }
 
+    fn node_by_id(&self, id: u64) -> *const Node {
+        let ret: Box<Node> = if let Some(inner) = self.0.node_by_id(id) {
+            Box::new(Node(inner))
+        } else {
+            return std::ptr::null();
+        };
+
+        Box::into_raw(ret)
+    }
+
     fn initial_position(&self) -> Box<CurPosition> {
         Box::new(CurPosition(self.0.initial_position()))
     }
@@ -0,0 +1,391 @@
+//! Animated GIF and APNG exports of a game's mainline.
+
+use crate::game::Game;
+use crate::{Color, Move, Position, Role, Square, VariantPosition};
+
+use std::borrow::Cow;
+use std::io::Write;
+use std::time::Duration;
+
+/// Options controlling how an animation is drawn.
+#[derive(Debug, Clone)]
+pub struct AnimationOptions {
+    /// Width and height of a square in pixels
+    pub square_size: u16,
+    /// Draw the board from Black's side
+    pub flipped: bool,
+    /// How long each position is shown
+    pub delay: Duration,
+    /// How long the final position is shown before the animation loops
+    pub final_delay: Duration,
+    /// Highlight the squares of the move that reached each position
+    pub last_move: bool,
+}
+
+impl Default for AnimationOptions {
+    fn default() -> Self {
+        Self {
+            square_size: 40,
+            flipped: false,
+            delay: Duration::from_secs(1),
+            final_delay: Duration::from_secs(3),
+            last_move: true,
+        }
+    }
+}
+
+impl AnimationOptions {
+    fn board_size(&self) -> u16 {
+        self.square_size * 8
+    }
+}
+
+const LIGHT_SQUARE: u8 = 0;
+const DARK_SQUARE: u8 = 1;
+const LAST_MOVE: u8 = 2;
+const WHITE_PIECE: u8 = 3;
+const BLACK_PIECE: u8 = 4;
+const OUTLINE: u8 = 5;
+
+/// Palette entries, indexed by the constants above and padded to a power
+/// of two for GIF.
+const PALETTE: [u8; 24] = [
+    0xf0, 0xd9, 0xb5, // light square
+    0xb5, 0x88, 0x63, // dark square
+    0xcd, 0xd2, 0x6a, // last move
+    0xff, 0xff, 0xff, // white piece
+    0x30, 0x30, 0x30, // black piece
+    0x00, 0x00, 0x00, // outline
+    0x00, 0x00, 0x00, // unused
+    0x00, 0x00, 0x00, // unused
+];
+
+const SPRITE_SIZE: usize = 16;
+
+/// Returns the silhouette of a piece, '#' marking the filled cells.
+fn sprite(role: Role) -> [&'static str; SPRITE_SIZE] {
+    match role {
+        Role::Pawn => [
+            "................",
+            "................",
+            "................",
+            "......####......",
+            ".....######.....",
+            ".....######.....",
+            "......####......",
+            ".....######.....",
+            "......####......",
+            "......####......",
+            ".....######.....",
+            "....########....",
+            "...##########...",
+            "...##########...",
+            "................",
+            "................",
+        ],
+        Role::Knight => [
+            "................",
+            "................",
+            ".......##.......",
+            "......####......",
+            ".....#######....",
+            "....#########...",
+            "...####.######..",
+            "...##########...",
+            "....##..######..",
+            ".......######...",
+            "......######....",
+            ".....#######....",
+            "....#########...",
+            "...###########..",
+            "...###########..",
+            "................",
+        ],
+        Role::Bishop => [
+            "................",
+            ".......##.......",
+            "......####......",
+            ".....######.....",
+            "....####.###....",
+            "....###.####....",
+            "....########....",
+            ".....######.....",
+            "......####......",
+            ".....######.....",
+            "......####......",
+            ".....######.....",
+            "....########....",
+            "...##########...",
+            "...##########...",
+            "................",
+        ],
+        Role::Rook => [
+            "................",
+            "................",
+            "...##.####.##...",
+            "...##.####.##...",
+            "...##########...",
+            "....########....",
+            ".....######.....",
+            ".....######.....",
+            ".....######.....",
+            ".....######.....",
+            ".....######.....",
+            "....########....",
+            "...##########...",
+            "...##########...",
+            "................",
+            "................",
+        ],
+        Role::Queen => [
+            "................",
+            ".#.....##.....#.",
+            ".##...####...##.",
+            ".###.######.###.",
+            "..############..",
+            "..############..",
+            "...##########...",
+            "....########....",
+            "....########....",
+            ".....######.....",
+            ".....######.....",
+            "....########....",
+            "...##########...",
+            "...##########...",
+            "................",
+            "................",
+        ],
+        Role::King => [
+            ".......##.......",
+            "......####......",
+            ".......##.......",
+            "....########....",
+            "...##########...",
+            "..############..",
+            "..############..",
+            "...##########...",
+            "....########....",
+            ".....######.....",
+            ".....######.....",
+            "....########....",
+            "...##########...",
+            "...##########...",
+            "................",
+            "................",
+        ],
+    }
+}
+
+/// Returns the position as palette indices, one byte per pixel, row by row.
+fn rasterize(
+    position: &VariantPosition,
+    last_move: Option<&Move>,
+    options: &AnimationOptions,
+) -> Vec<u8> {
+    let square_size = usize::from(options.square_size);
+    let board_size = square_size * 8;
+    let mut pixels = vec![0u8; board_size * board_size];
+
+    let highlight_vec = last_move
+        .filter(|_| options.last_move)
+        .map_or_else(Vec::new, |m| {
+            [m.from(), Some(m.to())].into_iter().flatten().collect()
+        });
+
+    for square in Square::ALL {
+        let (file, rank) = (usize::from(square.file()), usize::from(square.rank()));
+        let (x0, y0) = if options.flipped {
+            ((7 - file) * square_size, rank * square_size)
+        } else {
+            (file * square_size, (7 - rank) * square_size)
+        };
+        let background = if highlight_vec.contains(&square) {
+            LAST_MOVE
+        } else if square.is_light() {
+            LIGHT_SQUARE
+        } else {
+            DARK_SQUARE
+        };
+        let piece = position.board().piece_at(square);
+        let filled = |x: usize, y: usize| match piece {
+            Some(piece) => {
+                sprite(piece.role)[y * SPRITE_SIZE / square_size].as_bytes()
+                    [x * SPRITE_SIZE / square_size]
+                    == b'#'
+            }
+            None => false,
+        };
+
+        for y in 0..square_size {
+            for x in 0..square_size {
+                let color = match piece {
+                    Some(_) if filled(x, y) => {
+                        let edge = x == 0
+                            || y == 0
+                            || x + 1 == square_size
+                            || y + 1 == square_size
+                            || !filled(x - 1, y)
+                            || !filled(x + 1, y)
+                            || !filled(x, y - 1)
+                            || !filled(x, y + 1);
+                        match piece.map(|p| p.color) {
+                            _ if edge => OUTLINE,
+                            Some(Color::White) => WHITE_PIECE,
+                            _ => BLACK_PIECE,
+                        }
+                    }
+                    _ => background,
+                };
+                pixels[(y0 + y) * board_size + x0 + x] = color;
+            }
+        }
+    }
+
+    pixels
+}
+
+/// Returns every mainline position with the move that reached it.
+fn mainline_frames(game: &Game) -> Vec<(VariantPosition, Option<Move>)> {
+    let mut ret = vec![(game.initial_position(), None)];
+    ret.extend(
+        game.root()
+            .mainline_iter()
+            .map(|node| (node.position(), node.prev_move())),
+    );
+    ret
+}
+
+/// Returns the delay in centiseconds, saturating at the largest
+/// representable value.
+fn centiseconds(delay: Duration) -> u16 {
+    u16::try_from(delay.as_millis() / 10).unwrap_or(u16::MAX)
+}
+
+impl Game {
+    /// Writes the mainline as an animated GIF, one frame per position.
+    ///
+    /// # Arguments
+    ///
+    /// * `sink` - where the image is written to
+    /// * `options` - square size, orientation and frame delays
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sacrifice::animation::AnimationOptions;
+    ///
+    /// let game = sacrifice::read_pgn("1. e4 e5").unwrap();
+    /// let mut gif = Vec::new();
+    /// game.write_gif(&mut gif, &AnimationOptions::default()).unwrap();
+    /// assert!(gif.starts_with(b"GIF89a"));
+    /// ```
+    pub fn write_gif<W: Write>(&self, sink: W, options: &AnimationOptions) -> std::io::Result<()> {
+        let board_size = options.board_size();
+        let mut encoder = gif::Encoder::new(sink, board_size, board_size, &PALETTE)
+            .map_err(std::io::Error::other)?;
+        encoder
+            .set_repeat(gif::Repeat::Infinite)
+            .map_err(std::io::Error::other)?;
+
+        let frame_vec = mainline_frames(self);
+        let frame_count = frame_vec.len();
+        for (i, (position, last_move)) in frame_vec.into_iter().enumerate() {
+            let frame = gif::Frame {
+                width: board_size,
+                height: board_size,
+                delay: centiseconds(if i + 1 == frame_count {
+                    options.final_delay
+                } else {
+                    options.delay
+                }),
+                buffer: Cow::Owned(rasterize(&position, last_move.as_ref(), options)),
+                ..gif::Frame::default()
+            };
+            encoder.write_frame(&frame).map_err(std::io::Error::other)?;
+        }
+        Ok(())
+    }
+
+    /// Returns the mainline as an animated GIF.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sacrifice::animation::AnimationOptions;
+    ///
+    /// let game = sacrifice::read_pgn("1. e4 e5").unwrap();
+    /// let gif = game.to_gif(&AnimationOptions::default());
+    /// assert!(gif.starts_with(b"GIF89a"));
+    /// ```
+    pub fn to_gif(&self, options: &AnimationOptions) -> Vec<u8> {
+        let mut ret = Vec::new();
+        // Writing to a Vec never fails
+        let _ = self.write_gif(&mut ret, options);
+        ret
+    }
+
+    /// Writes the mainline as an animated PNG, one frame per position.
+    ///
+    /// # Arguments
+    ///
+    /// * `sink` - where the image is written to
+    /// * `options` - square size, orientation and frame delays
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sacrifice::animation::AnimationOptions;
+    ///
+    /// let game = sacrifice::read_pgn("1. e4 e5").unwrap();
+    /// let mut apng = Vec::new();
+    /// game.write_apng(&mut apng, &AnimationOptions::default()).unwrap();
+    /// assert!(apng.starts_with(b"\x89PNG"));
+    /// ```
+    pub fn write_apng<W: Write>(&self, sink: W, options: &AnimationOptions) -> std::io::Result<()> {
+        let board_size = u32::from(options.board_size());
+        let frame_vec = mainline_frames(self);
+
+        let mut encoder = png::Encoder::new(sink, board_size, board_size);
+        encoder.set_color(png::ColorType::Indexed);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.set_palette(&PALETTE[..18]);
+        encoder
+            .set_animated(frame_vec.len() as u32, 0)
+            .map_err(std::io::Error::other)?;
+        let mut writer = encoder.write_header().map_err(std::io::Error::other)?;
+
+        let frame_count = frame_vec.len();
+        for (i, (position, last_move)) in frame_vec.into_iter().enumerate() {
+            let delay = if i + 1 == frame_count {
+                options.final_delay
+            } else {
+                options.delay
+            };
+            writer
+                .set_frame_delay(centiseconds(delay), 100)
+                .map_err(std::io::Error::other)?;
+            writer
+                .write_image_data(&rasterize(&position, last_move.as_ref(), options))
+                .map_err(std::io::Error::other)?;
+        }
+        writer.finish().map_err(std::io::Error::other)
+    }
+
+    /// Returns the mainline as an animated PNG.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sacrifice::animation::AnimationOptions;
+    ///
+    /// let game = sacrifice::read_pgn("1. e4 e5").unwrap();
+    /// let apng = game.to_apng(&AnimationOptions::default());
+    /// assert!(apng.starts_with(b"\x89PNG"));
+    /// ```
+    pub fn to_apng(&self, options: &AnimationOptions) -> Vec<u8> {
+        let mut ret = Vec::new();
+        // Writing to a Vec never fails
+        let _ = self.write_apng(&mut ret, options);
+        ret
+    }
+}
@@ -0,0 +1,219 @@
+//! Polyglot opening book building.
+//!
+//! A [`BookBuilder`] collects the moves played in many games and writes
+//! them as a Polyglot `.bin` book, as read by most engines and GUIs.
+
+use crate::game::{Game, GameResult};
+use crate::{CastlingMode, Color, Move, Role, VariantPosition};
+
+use shakmaty::uci::Uci;
+use std::collections::HashMap;
+use std::io::Write;
+
+/// Which games and moves go into a book.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BookOptions {
+    /// Moves after this many plies of a game are left out
+    pub max_plies: u32,
+    /// Games where either player is rated lower, or unrated, are left out
+    pub min_rating: Option<u16>,
+    /// Whether drawn games are left out
+    pub skip_draws: bool,
+    /// Moves played in fewer games are left out
+    pub min_games: u32,
+}
+
+impl Default for BookOptions {
+    fn default() -> Self {
+        Self {
+            max_plies: 20,
+            min_rating: None,
+            skip_draws: false,
+            min_games: 1,
+        }
+    }
+}
+
+/// How often a move was played from a position, and how it scored.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct MoveStats {
+    games: u32,
+    /// Two points per win and one per draw for the side that played it
+    score: u64,
+}
+
+/// Builds a Polyglot opening book from games.
+///
+/// Each mainline move in the first plies of a game gets two points if the
+/// side that played it won and one if the game was drawn, as in Polyglot's
+/// own `make-book`. Moves that never scored are left out, so a book of
+/// decisive games only suggests the winners' moves. Games without a
+/// result, and games of variants other than standard chess and Chess960,
+/// are skipped.
+///
+/// # Examples
+///
+/// ```
+/// use sacrifice::book::{BookBuilder, BookOptions};
+///
+/// let games = [("1. e4 e5", "1-0"), ("1. e4 c5", "0-1"), ("1. d4 d5", "1/2-1/2")]
+///     .map(|(moves, result)| {
+///         sacrifice::read_pgn(&format!("[Result \"{}\"]\n\n{} {}", result, moves, result)).unwrap()
+///     });
+///
+/// let mut builder = BookBuilder::new(BookOptions::default());
+/// assert_eq!(builder.add_games(games), 3);
+///
+/// let mut bin = Vec::new();
+/// builder.write(&mut bin).unwrap();
+/// // e4 and d4 from the start, e5 is left out as it lost, c5 and d5
+/// assert_eq!(bin.len(), 4 * 16);
+/// ```
+#[derive(Debug, Clone)]
+pub struct BookBuilder {
+    options: BookOptions,
+    position_map: HashMap<u64, HashMap<u16, MoveStats>>,
+    game_count: usize,
+}
+
+impl BookBuilder {
+    pub fn new(options: BookOptions) -> Self {
+        Self {
+            options,
+            position_map: HashMap::new(),
+            game_count: 0,
+        }
+    }
+
+    /// Adds the mainline moves of a game, and returns whether the game was
+    /// used.
+    ///
+    /// # Arguments
+    ///
+    /// * `game` - the game to add
+    pub fn add_game(&mut self, game: &Game) -> bool {
+        if !self.accepts(game) {
+            return false;
+        }
+
+        let mut node = game.root();
+        for _ in 0..self.options.max_plies {
+            let next = if let Some(val) = node.mainline() {
+                val
+            } else {
+                break;
+            };
+            let polyglot_move = next.prev_move().as_ref().and_then(polyglot_move);
+            if let Some(polyglot_move) = polyglot_move {
+                let score = match (game.header.result, node.turn()) {
+                    (GameResult::WhiteWins, Color::White) => 2,
+                    (GameResult::BlackWins, Color::Black) => 2,
+                    (GameResult::Draw, _) => 1,
+                    _ => 0,
+                };
+                let stats = self
+                    .position_map
+                    .entry(node.polyglot_key())
+                    .or_default()
+                    .entry(polyglot_move)
+                    .or_default();
+                stats.games += 1;
+                stats.score += score;
+            }
+            node = next;
+        }
+
+        self.game_count += 1;
+        true
+    }
+
+    /// Adds the mainline moves of every game, and returns how many of them
+    /// were used.
+    ///
+    /// # Arguments
+    ///
+    /// * `games` - the games to add, e.g. from a PGN database
+    pub fn add_games(&mut self, games: impl IntoIterator<Item = Game>) -> usize {
+        games.into_iter().filter(|game| self.add_game(game)).count()
+    }
+
+    /// Returns the number of games added so far.
+    pub fn game_count(&self) -> usize {
+        self.game_count
+    }
+
+    /// Writes the book in the Polyglot format: 16-byte entries sorted by
+    /// position key, best move first.
+    ///
+    /// Weights are scaled down per position if they do not fit in 16 bits.
+    ///
+    /// # Arguments
+    ///
+    /// * `writer` - where to write the `.bin` file
+    pub fn write(&self, mut writer: impl Write) -> std::io::Result<()> {
+        let mut key_vec: Vec<&u64> = self.position_map.keys().collect();
+        key_vec.sort_unstable();
+
+        for key in key_vec {
+            let mut entry_vec: Vec<(u16, u64)> = self.position_map[key]
+                .iter()
+                .filter(|(_, stats)| stats.games >= self.options.min_games && stats.score > 0)
+                .map(|(&polyglot_move, stats)| (polyglot_move, stats.score))
+                .collect();
+            entry_vec.sort_unstable_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+
+            let max_score = entry_vec.first().map_or(0, |entry| entry.1);
+            for (polyglot_move, score) in entry_vec {
+                let weight = if max_score > u64::from(u16::MAX) {
+                    (score * u64::from(u16::MAX) / max_score).max(1)
+                } else {
+                    score
+                };
+                writer.write_all(&key.to_be_bytes())?;
+                writer.write_all(&polyglot_move.to_be_bytes())?;
+                writer.write_all(&(weight as u16).to_be_bytes())?;
+                // The learn field is unused
+                writer.write_all(&0u32.to_be_bytes())?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn accepts(&self, game: &Game) -> bool {
+        if game.header.result == GameResult::Ongoing
+            || (self.options.skip_draws && game.header.result == GameResult::Draw)
+            || !matches!(game.initial_position(), VariantPosition::Chess(_))
+        {
+            return false;
+        }
+        match self.options.min_rating {
+            Some(min_rating) => [game.header.white_elo, game.header.black_elo]
+                .iter()
+                .all(|elo| elo.is_some_and(|elo| elo >= min_rating)),
+            None => true,
+        }
+    }
+}
+
+/// Returns a move as encoded in Polyglot books, with castling as the king
+/// taking its own rook, or `None` for a drop.
+fn polyglot_move(m: &Move) -> Option<u16> {
+    match m.to_uci(CastlingMode::Chess960) {
+        Uci::Normal {
+            from,
+            to,
+            promotion,
+        } => {
+            let promotion_bits = match promotion {
+                Some(Role::Knight) => 1,
+                Some(Role::Bishop) => 2,
+                Some(Role::Rook) => 3,
+                Some(Role::Queen) => 4,
+                _ => 0,
+            };
+            Some(u16::from(to) | u16::from(from) << 6 | promotion_bits << 12)
+        }
+        _ => None,
+    }
+}
@@ -0,0 +1,159 @@
+use super::{Analysis, Engine, EngineError, Limits, PvLine};
+use crate::game::{EvalInfo, Game, Node};
+use crate::Position;
+
+impl Analysis {
+    /// Returns the evaluation as stored on a node, with the depth, the
+    /// principal variation and the given engine name, or `None` if the
+    /// engine gave no score.
+    ///
+    /// # Arguments
+    ///
+    /// * `engine` - name of the engine that did the search
+    pub fn eval_info(&self, engine: Option<&str>) -> Option<EvalInfo> {
+        Some(EvalInfo {
+            eval: self.eval?,
+            depth: Some(self.depth),
+            engine: engine.map(str::to_string),
+            pv: self.pv.clone(),
+        })
+    }
+}
+
+impl PvLine {
+    /// Returns the evaluation of the line as stored on the node after its
+    /// first move, with the rest of the line as principal variation, or
+    /// `None` if the engine gave no score.
+    fn eval_info_after_first(&self, engine: Option<&str>) -> Option<EvalInfo> {
+        Some(EvalInfo {
+            eval: self.eval?,
+            depth: Some(self.depth),
+            engine: engine.map(str::to_string),
+            pv: self.pv.iter().skip(1).cloned().collect(),
+        })
+    }
+}
+
+impl Node {
+    /// Adds the best lines of an analysis of this node's position as
+    /// variations, best first, e.g. the top lines of a search with the
+    /// engine's `MultiPV` option set.
+    ///
+    /// A line whose first move is already a child of the node continues
+    /// in that child instead of adding a new one. The node after the first
+    /// move of each line gets the line's evaluation, unless it already has
+    /// one.
+    ///
+    /// Returns the node after the first move of each line.
+    ///
+    /// # Arguments
+    ///
+    /// * `analysis` - a search of this node's position
+    /// * `engine` - name of the engine, stored with the evaluations
+    /// * `max_plies` - the most moves of each line to add
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use sacrifice::engine::{Engine, Limits};
+    ///
+    /// let mut engine = Engine::spawn("stockfish").unwrap();
+    /// engine.set_option("MultiPV", "3").unwrap();
+    ///
+    /// let game = sacrifice::read_pgn("1. e4 e5 2. Nf3 *").unwrap();
+    /// let node = game.root().mainline_iter().last().unwrap();
+    /// let analysis = engine.analyze(&node, &Limits::depth(20)).unwrap();
+    /// node.add_lines(&analysis, engine.name(), 4);
+    /// assert_eq!(node.variation_vec().len(), 3);
+    /// ```
+    pub fn add_lines(
+        &self,
+        analysis: &Analysis,
+        engine: Option<&str>,
+        max_plies: usize,
+    ) -> Vec<Node> {
+        let mut first_vec = Vec::new();
+        for line in &analysis.lines {
+            let mut line_iter = line.pv.iter().take(max_plies.max(1));
+            let mut first = if let Some(val) = line_iter
+                .next()
+                .and_then(|m| self.clone().new_variation(m.clone()))
+            {
+                val
+            } else {
+                continue;
+            };
+            if first.eval_info().is_none() {
+                first.set_eval_info(line.eval_info_after_first(engine));
+            }
+
+            let mut node = first.clone();
+            for m in line_iter {
+                node = if let Some(val) = node.new_variation(m.clone()) {
+                    val
+                } else {
+                    break;
+                };
+            }
+            first_vec.push(first);
+        }
+        first_vec
+    }
+}
+
+impl Game {
+    /// Runs the engine on the root and every mainline node, and on the
+    /// nodes of the other variations if asked, storing the evaluations
+    /// with their lines on the nodes.
+    ///
+    /// Positions where the game is over are not searched. Evaluations
+    /// already on the nodes are replaced. Evaluations are written to PGN
+    /// as `[%eval]` commands.
+    ///
+    /// # Arguments
+    ///
+    /// * `engine` - the engine to search with
+    /// * `limits` - when to stop the search at each node
+    /// * `variations` - whether to also analyze the other variations
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use sacrifice::engine::{Engine, Limits};
+    ///
+    /// let mut engine = Engine::spawn("stockfish").unwrap();
+    /// let game = sacrifice::read_pgn("1. e4 e5 2. Nf3 Nc6 3. Bb5").unwrap();
+    /// game.analyze_all(&mut engine, &Limits::depth(18), false).unwrap();
+    /// println!("{}", game);
+    /// ```
+    pub fn analyze_all(
+        &self,
+        engine: &mut Engine,
+        limits: &Limits,
+        variations: bool,
+    ) -> Result<(), EngineError> {
+        let root = self.root();
+        let node_vec: Vec<Node> = if variations {
+            std::iter::once(root.clone())
+                .chain(root.descendants())
+                .collect()
+        } else {
+            std::iter::once(root.clone())
+                .chain(root.mainline_iter())
+                .collect()
+        };
+
+        engine.new_game()?;
+        for mut node in node_vec {
+            if node.position().is_game_over() {
+                continue;
+            }
+            let analysis = engine.analyze(&node, limits)?;
+            if let Some(info) = analysis.eval_info(engine.name()) {
+                node.set_eval_info(Some(info));
+            }
+        }
+
+        Ok(())
+    }
+}
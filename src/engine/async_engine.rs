@@ -0,0 +1,280 @@
+use super::info::EngineId;
+use super::{fen_position_command, Analysis, EngineError, Info, Limits, UciOption};
+use crate::game::Node;
+use crate::VariantPosition;
+
+use std::ffi::OsStr;
+use std::future::Future;
+use std::process::Stdio;
+
+use futures_util::future::{self, Either};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, Lines};
+use tokio::process::{Child, ChildStdin, ChildStdout, Command};
+
+/// A running UCI engine driven from async code, e.g. by a server that
+/// runs many analyses at once, each on its own engine.
+///
+/// It works like [`Engine`](super::Engine), but searches can report their
+/// `info` lines as they arrive and be cancelled. The process is killed
+/// when the engine is dropped; [`AsyncEngine::quit`] lets it exit cleanly.
+///
+/// # Examples
+///
+/// ```no_run
+/// use sacrifice::engine::{AsyncEngine, Limits};
+/// use std::time::Duration;
+///
+/// # async fn run() {
+/// let mut engine = AsyncEngine::spawn("stockfish").await.unwrap();
+/// let game = sacrifice::read_pgn("1. e4 e5 2. Nf3 *").unwrap();
+///
+/// // Give up after a second, printing the depth reached so far
+/// let cancel = tokio::time::sleep(Duration::from_secs(1));
+/// let analysis = engine
+///     .analyze_with(&game.root(), &Limits::depth(40), cancel, |info| {
+///         println!("depth {:?}", info.depth);
+///     })
+///     .await
+///     .unwrap();
+/// println!("{:?}", analysis.best_move);
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct AsyncEngine {
+    process: Child,
+    stdin: ChildStdin,
+    lines: Lines<BufReader<ChildStdout>>,
+    id: EngineId,
+}
+
+impl AsyncEngine {
+    /// Starts the engine at the given path and does the handshake.
+    ///
+    /// # Arguments
+    ///
+    /// * `program` - path or name of the engine executable
+    pub async fn spawn(program: impl AsRef<OsStr>) -> Result<Self, EngineError> {
+        Self::from_command(Command::new(program)).await
+    }
+
+    /// Starts the engine with the given command and does the handshake.
+    /// Standard input and output are replaced by pipes.
+    ///
+    /// # Arguments
+    ///
+    /// * `command` - the command that starts the engine
+    pub async fn from_command(mut command: Command) -> Result<Self, EngineError> {
+        let mut process = command
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .kill_on_drop(true)
+            .spawn()?;
+        let stdin = process.stdin.take().ok_or(EngineError::Terminated)?;
+        let stdout = process.stdout.take().ok_or(EngineError::Terminated)?;
+
+        let mut engine = Self {
+            process,
+            stdin,
+            lines: BufReader::new(stdout).lines(),
+            id: EngineId::default(),
+        };
+
+        send(&mut engine.stdin, "uci").await?;
+        while !engine.id.read(&next_line(&mut engine.lines).await?) {}
+        engine.sync().await?;
+
+        Ok(engine)
+    }
+
+    /// Returns the name the engine gave in the handshake.
+    pub fn name(&self) -> Option<&str> {
+        self.id.name.as_deref()
+    }
+
+    /// Returns the author the engine gave in the handshake.
+    pub fn author(&self) -> Option<&str> {
+        self.id.author.as_deref()
+    }
+
+    /// Returns the options the engine announced in the handshake.
+    pub fn options(&self) -> &[UciOption] {
+        &self.id.option_vec
+    }
+
+    /// Sets an engine option, and waits until the engine is ready.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - name of an option the engine announced, in any case
+    /// * `value` - the new value; empty for `button` options
+    pub async fn set_option(&mut self, name: &str, value: &str) -> Result<(), EngineError> {
+        let command = self.id.setoption_command(name, value)?;
+        send(&mut self.stdin, &command).await?;
+        self.sync().await
+    }
+
+    /// Tells the engine that the next search is from a different game,
+    /// and waits until it is ready.
+    pub async fn new_game(&mut self) -> Result<(), EngineError> {
+        send(&mut self.stdin, "ucinewgame").await?;
+        self.sync().await
+    }
+
+    /// Searches the position of the given node, with the moves leading to
+    /// it.
+    ///
+    /// The node is only read when this is called, so the search does not
+    /// hold on to the tree.
+    ///
+    /// # Arguments
+    ///
+    /// * `node` - the node to analyze
+    /// * `limits` - when to stop the search
+    pub fn analyze(
+        &mut self,
+        node: &Node,
+        limits: &Limits,
+    ) -> impl Future<Output = Result<Analysis, EngineError>> + '_ {
+        self.analyze_with(node, limits, future::pending(), |_| {})
+    }
+
+    /// Searches the position of the given node, calling `on_info` with
+    /// each `info` line as it arrives.
+    ///
+    /// Once `cancel` completes, the engine is told to stop, and the result
+    /// holds what was found so far.
+    ///
+    /// # Arguments
+    ///
+    /// * `node` - the node to analyze
+    /// * `limits` - when to stop the search
+    /// * `cancel` - a future that completes when the search should stop
+    /// * `on_info` - called with every `info` line of the search
+    pub fn analyze_with<'a>(
+        &'a mut self,
+        node: &Node,
+        limits: &Limits,
+        cancel: impl Future<Output = ()> + 'a,
+        on_info: impl FnMut(&Info) + 'a,
+    ) -> impl Future<Output = Result<Analysis, EngineError>> + 'a {
+        let position_command = node.uci_position_command();
+        self.search(
+            position_command,
+            node.position(),
+            limits.go_command(),
+            cancel,
+            on_info,
+        )
+    }
+
+    /// Searches the given position, without any history.
+    ///
+    /// # Arguments
+    ///
+    /// * `position` - the position to analyze
+    /// * `limits` - when to stop the search
+    pub async fn analyze_position(
+        &mut self,
+        position: &VariantPosition,
+        limits: &Limits,
+    ) -> Result<Analysis, EngineError> {
+        self.analyze_position_with(position, limits, future::pending(), |_| {})
+            .await
+    }
+
+    /// Searches the given position, without any history, calling
+    /// `on_info` with each `info` line as it arrives and stopping early
+    /// once `cancel` completes.
+    ///
+    /// # Arguments
+    ///
+    /// * `position` - the position to analyze
+    /// * `limits` - when to stop the search
+    /// * `cancel` - a future that completes when the search should stop
+    /// * `on_info` - called with every `info` line of the search
+    pub async fn analyze_position_with(
+        &mut self,
+        position: &VariantPosition,
+        limits: &Limits,
+        cancel: impl Future<Output = ()>,
+        on_info: impl FnMut(&Info),
+    ) -> Result<Analysis, EngineError> {
+        self.search(
+            fen_position_command(position),
+            position.clone(),
+            limits.go_command(),
+            cancel,
+            on_info,
+        )
+        .await
+    }
+
+    /// Sends `quit` and waits for the engine to exit.
+    pub async fn quit(mut self) -> Result<(), EngineError> {
+        send(&mut self.stdin, "quit").await?;
+        self.process.wait().await?;
+        Ok(())
+    }
+
+    async fn search(
+        &mut self,
+        position_command: String,
+        position: VariantPosition,
+        go_command: String,
+        cancel: impl Future<Output = ()>,
+        mut on_info: impl FnMut(&Info),
+    ) -> Result<Analysis, EngineError> {
+        send(&mut self.stdin, &position_command).await?;
+        send(&mut self.stdin, &go_command).await?;
+
+        let mut cancel = std::pin::pin!(cancel);
+        let mut stopped = false;
+        let mut analysis = Analysis::default();
+        loop {
+            let line = if stopped {
+                next_line(&mut self.lines).await?
+            } else {
+                // Reading a line is cancel safe, so nothing is lost when
+                // the cancellation wins
+                let line_future = std::pin::pin!(next_line(&mut self.lines));
+                match future::select(line_future, cancel.as_mut()).await {
+                    Either::Left((line, _)) => line?,
+                    Either::Right(_) => {
+                        stopped = true;
+                        send(&mut self.stdin, "stop").await?;
+                        continue;
+                    }
+                }
+            };
+
+            if let Some(info) = Info::parse(&line, &position) {
+                on_info(&info);
+                analysis.update(info);
+            } else if analysis.finish(&line, &position) {
+                return Ok(analysis);
+            }
+        }
+    }
+
+    /// Sends `isready` and waits for `readyok`.
+    async fn sync(&mut self) -> Result<(), EngineError> {
+        send(&mut self.stdin, "isready").await?;
+        while next_line(&mut self.lines).await?.trim() != "readyok" {}
+        Ok(())
+    }
+}
+
+async fn send(stdin: &mut ChildStdin, command: &str) -> Result<(), EngineError> {
+    stdin.write_all(command.as_bytes()).await?;
+    stdin.write_all(b"\n").await?;
+    stdin.flush().await?;
+    Ok(())
+}
+
+async fn next_line(lines: &mut Lines<BufReader<ChildStdout>>) -> Result<String, EngineError> {
+    match lines.next_line().await? {
+        Some(line) => Ok(line.trim_end().to_string()),
+        None => Err(EngineError::Terminated),
+    }
+}
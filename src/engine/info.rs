@@ -0,0 +1,334 @@
+use super::EngineError;
+use crate::game::Eval;
+use crate::{Color, Move, Position, VariantPosition};
+
+use shakmaty::uci::Uci;
+use std::time::Duration;
+
+/// The result of an engine search.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Analysis {
+    /// The move the engine would play, or `None` if the position has no
+    /// legal moves
+    pub best_move: Option<Move>,
+    /// The reply the engine expects to `best_move`
+    pub ponder: Option<Move>,
+    /// Depth of the deepest search reported, in plies
+    pub depth: u32,
+    pub seldepth: Option<u32>,
+    /// Evaluation of the position, from White's point of view
+    pub eval: Option<Eval>,
+    /// Principal variation, starting with the best move
+    pub pv: Vec<Move>,
+    /// Number of nodes searched
+    pub nodes: Option<u64>,
+    /// Time spent on the search
+    pub time: Option<Duration>,
+    /// The best lines found, best first; more than one if the engine's
+    /// `MultiPV` option is set higher
+    pub lines: Vec<PvLine>,
+}
+
+/// One of the best lines of a search.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PvLine {
+    /// Depth of the search that found the line, in plies
+    pub depth: u32,
+    /// Evaluation of the position if the line is played, from White's
+    /// point of view
+    pub eval: Option<Eval>,
+    pub pv: Vec<Move>,
+}
+
+impl Analysis {
+    /// Updates the analysis with an `info` line. Bounds only replace the
+    /// evaluation if there is none yet.
+    pub(crate) fn update(&mut self, info: Info) {
+        let multipv = info.multipv.unwrap_or(1).max(1) as usize;
+        if !info.pv.is_empty() && !info.bound && multipv <= self.lines.len() + 1 {
+            let line = PvLine {
+                depth: info.depth.unwrap_or(self.depth),
+                eval: info.eval,
+                pv: info.pv.clone(),
+            };
+            if multipv > self.lines.len() {
+                self.lines.push(line);
+            } else {
+                self.lines[multipv - 1] = line;
+            }
+        }
+        if multipv != 1 {
+            return;
+        }
+
+        if let Some(depth) = info.depth {
+            self.depth = self.depth.max(depth);
+        }
+        self.seldepth = info.seldepth.or(self.seldepth);
+        self.nodes = info.nodes.or(self.nodes);
+        self.time = info.time.or(self.time);
+        if info.eval.is_some() && (!info.bound || self.eval.is_none()) {
+            self.eval = info.eval;
+        }
+        if !info.pv.is_empty() && !info.bound {
+            self.pv = info.pv;
+        }
+    }
+}
+
+impl Analysis {
+    /// Reads a `bestmove` line ending a search of the given position, or
+    /// returns `false` if the line is something else.
+    pub(crate) fn finish(&mut self, line: &str, position: &VariantPosition) -> bool {
+        let mut tokens = line.split_whitespace();
+        if tokens.next() != Some("bestmove") {
+            return false;
+        }
+
+        self.best_move = tokens.next().and_then(|t| parse_uci_move(t, position));
+        if let Some(best_move) = &self.best_move {
+            let mut after = position.clone();
+            after.play_unchecked(best_move);
+            self.ponder = tokens
+                .skip_while(|&t| t != "ponder")
+                .nth(1)
+                .and_then(|t| parse_uci_move(t, &after));
+        }
+        true
+    }
+}
+
+/// What an engine tells about itself in the handshake.
+#[derive(Debug, Default)]
+pub(crate) struct EngineId {
+    pub(crate) name: Option<String>,
+    pub(crate) author: Option<String>,
+    pub(crate) option_vec: Vec<UciOption>,
+}
+
+impl EngineId {
+    /// Reads a line of the handshake, returning `true` for `uciok`, its
+    /// last line.
+    pub(crate) fn read(&mut self, line: &str) -> bool {
+        let mut tokens = line.split_whitespace();
+        match tokens.next() {
+            Some("id") => {
+                let key = tokens.next();
+                let value = tokens.collect::<Vec<&str>>().join(" ");
+                match key {
+                    Some("name") => self.name = Some(value),
+                    Some("author") => self.author = Some(value),
+                    _ => {}
+                }
+            }
+            Some("option") => self.option_vec.extend(UciOption::parse(line)),
+            Some("uciok") => return true,
+            _ => {}
+        }
+        false
+    }
+
+    /// Returns the `setoption` command for an announced option.
+    pub(crate) fn setoption_command(&self, name: &str, value: &str) -> Result<String, EngineError> {
+        let option = self
+            .option_vec
+            .iter()
+            .find(|o| o.name.eq_ignore_ascii_case(name))
+            .ok_or_else(|| EngineError::UnknownOption(name.to_string()))?;
+
+        Ok(if value.is_empty() {
+            format!("setoption name {}", option.name)
+        } else {
+            format!("setoption name {} value {}", option.name, value)
+        })
+    }
+}
+
+/// An `info` line sent by an engine during a search.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Info {
+    pub depth: Option<u32>,
+    pub seldepth: Option<u32>,
+    /// Rank of the line among the lines searched, if there are several
+    pub multipv: Option<u32>,
+    /// Evaluation from White's point of view
+    pub eval: Option<Eval>,
+    /// Whether `eval` is only a lower or upper bound
+    pub bound: bool,
+    pub nodes: Option<u64>,
+    pub nps: Option<u64>,
+    pub time: Option<Duration>,
+    /// Principal variation; moves after the first illegal one are dropped
+    pub pv: Vec<Move>,
+    /// Free text following `string`
+    pub string: Option<String>,
+}
+
+impl Info {
+    /// Parses an `info` line for a search of the given position, or
+    /// returns `None` if the line is not an `info` line.
+    ///
+    /// # Arguments
+    ///
+    /// * `line` - the line sent by the engine
+    /// * `position` - the position being searched
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sacrifice::engine::Info;
+    /// use sacrifice::game::Eval;
+    /// use sacrifice::{Chess, Square};
+    ///
+    /// let line = "info depth 12 multipv 1 score cp 31 nodes 5120 time 42 pv e2e4 e7e5";
+    /// let info = Info::parse(line, &Chess::default().into()).unwrap();
+    /// assert_eq!(info.depth, Some(12));
+    /// assert_eq!(info.eval, Some(Eval::Centipawns(31)));
+    /// assert_eq!(info.pv[1].to(), Square::E5);
+    /// ```
+    pub fn parse(line: &str, position: &VariantPosition) -> Option<Self> {
+        let mut tokens = line.split_whitespace();
+        if tokens.next() != Some("info") {
+            return None;
+        }
+
+        let mut info = Info::default();
+        while let Some(token) = tokens.next() {
+            match token {
+                "depth" => info.depth = tokens.next().and_then(|t| t.parse().ok()),
+                "seldepth" => info.seldepth = tokens.next().and_then(|t| t.parse().ok()),
+                "multipv" => info.multipv = tokens.next().and_then(|t| t.parse().ok()),
+                "nodes" => info.nodes = tokens.next().and_then(|t| t.parse().ok()),
+                "nps" => info.nps = tokens.next().and_then(|t| t.parse().ok()),
+                "time" => {
+                    info.time = tokens
+                        .next()
+                        .and_then(|t| t.parse().ok())
+                        .map(Duration::from_millis)
+                }
+                "score" => {
+                    let kind = tokens.next();
+                    let value = tokens.next().and_then(|t| t.parse::<i32>().ok());
+                    // Engines score from the side to move
+                    let value = match position.turn() {
+                        Color::White => value,
+                        Color::Black => value.map(|v| -v),
+                    };
+                    info.eval = match kind {
+                        Some("cp") => value.map(Eval::Centipawns),
+                        Some("mate") => value.map(Eval::Mate),
+                        _ => None,
+                    };
+                }
+                "lowerbound" | "upperbound" => info.bound = true,
+                "pv" => {
+                    // The pv runs to the end of the line
+                    let mut pos = position.clone();
+                    for uci_str in tokens.by_ref() {
+                        match parse_uci_move(uci_str, &pos) {
+                            Some(m) => {
+                                pos.play_unchecked(&m);
+                                info.pv.push(m);
+                            }
+                            None => break,
+                        }
+                    }
+                }
+                "string" => {
+                    let text = tokens.by_ref().collect::<Vec<&str>>().join(" ");
+                    info.string = Some(text);
+                }
+                _ => {}
+            }
+        }
+
+        Some(info)
+    }
+}
+
+/// Returns the legal move given in UCI notation, or `None` if it is
+/// malformed or illegal.
+pub(crate) fn parse_uci_move(uci_str: &str, position: &VariantPosition) -> Option<Move> {
+    Uci::from_ascii(uci_str.as_bytes())
+        .ok()?
+        .to_move(position)
+        .ok()
+}
+
+/// Type of an engine option.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OptionKind {
+    Check,
+    Spin,
+    Combo,
+    Button,
+    String,
+}
+
+/// An option announced by an engine during the handshake.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UciOption {
+    pub name: String,
+    pub kind: OptionKind,
+    pub default: Option<String>,
+    /// Bounds of a `spin` option
+    pub min: Option<i64>,
+    pub max: Option<i64>,
+    /// Allowed values of a `combo` option
+    pub vars: Vec<String>,
+}
+
+impl UciOption {
+    /// Parses an `option` line, or returns `None` if it is malformed.
+    pub(crate) fn parse(line: &str) -> Option<Self> {
+        let mut tokens = line.split_whitespace();
+        if tokens.next() != Some("option") {
+            return None;
+        }
+
+        // Names and values may contain spaces, so collect the words after
+        // each keyword
+        let mut field_vec: Vec<(&str, Vec<&str>)> = Vec::new();
+        for token in tokens {
+            match token {
+                "name" | "type" | "default" | "min" | "max" | "var" => {
+                    field_vec.push((token, Vec::new()))
+                }
+                _ => field_vec.last_mut()?.1.push(token),
+            }
+        }
+
+        let mut option = UciOption {
+            name: String::new(),
+            kind: OptionKind::Button,
+            default: None,
+            min: None,
+            max: None,
+            vars: Vec::new(),
+        };
+        let mut has_kind = false;
+        for (key, words) in field_vec {
+            let value = words.join(" ");
+            match key {
+                "name" => option.name = value,
+                "type" => {
+                    has_kind = true;
+                    option.kind = match value.as_str() {
+                        "check" => OptionKind::Check,
+                        "spin" => OptionKind::Spin,
+                        "combo" => OptionKind::Combo,
+                        "button" => OptionKind::Button,
+                        "string" => OptionKind::String,
+                        _ => return None,
+                    };
+                }
+                "default" => option.default = Some(value),
+                "min" => option.min = value.parse().ok(),
+                "max" => option.max = value.parse().ok(),
+                _ => option.vars.push(value),
+            }
+        }
+
+        (has_kind && !option.name.is_empty()).then_some(option)
+    }
+}
@@ -0,0 +1,334 @@
+//! UCI chess engine client.
+//!
+//! An [`Engine`] runs a program such as Stockfish as a child process and
+//! talks to it over the Universal Chess Interface.
+
+mod analyze;
+#[cfg(feature = "tokio")]
+mod async_engine;
+#[cfg(feature = "tokio")]
+pub use async_engine::AsyncEngine;
+mod info;
+mod selfplay;
+use info::EngineId;
+pub use info::{Analysis, Info, OptionKind, PvLine, UciOption};
+pub use selfplay::SelfPlayOptions;
+
+use crate::game::Node;
+use crate::{Fen, VariantPosition};
+
+use shakmaty::EnPassantMode;
+use std::ffi::OsStr;
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+use std::time::{Duration, Instant};
+
+/// Error from talking to an engine.
+#[derive(Debug)]
+pub enum EngineError {
+    /// Starting the engine, or reading from or writing to it, failed
+    Io(std::io::Error),
+    /// The engine exited or closed its output
+    Terminated,
+    /// The engine did not announce an option of this name
+    UnknownOption(String),
+}
+
+impl std::fmt::Display for EngineError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EngineError::Io(err) => write!(f, "engine i/o error: {}", err),
+            EngineError::Terminated => write!(f, "engine terminated"),
+            EngineError::UnknownOption(name) => write!(f, "unknown engine option {}", name),
+        }
+    }
+}
+
+impl std::error::Error for EngineError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            EngineError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for EngineError {
+    fn from(err: std::io::Error) -> Self {
+        EngineError::Io(err)
+    }
+}
+
+/// When a search stops. A search stops at the first limit reached; one
+/// without any limit is sent as `go infinite` and never returns, so set
+/// at least one.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Limits {
+    /// Maximum depth in plies
+    pub depth: Option<u32>,
+    /// Maximum number of nodes
+    pub nodes: Option<u64>,
+    /// Time to search for
+    pub movetime: Option<Duration>,
+    /// Search for a mate in this many moves
+    pub mate: Option<u32>,
+}
+
+impl Limits {
+    /// Returns limits that stop the search at the given depth.
+    pub fn depth(depth: u32) -> Self {
+        Self {
+            depth: Some(depth),
+            ..Self::default()
+        }
+    }
+
+    /// Returns limits that stop the search after the given number of
+    /// nodes.
+    pub fn nodes(nodes: u64) -> Self {
+        Self {
+            nodes: Some(nodes),
+            ..Self::default()
+        }
+    }
+
+    /// Returns limits that stop the search after the given time.
+    pub fn movetime(movetime: Duration) -> Self {
+        Self {
+            movetime: Some(movetime),
+            ..Self::default()
+        }
+    }
+
+    /// Returns the `go` command for these limits.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sacrifice::engine::Limits;
+    /// use std::time::Duration;
+    ///
+    /// let limits = Limits {
+    ///     depth: Some(20),
+    ///     movetime: Some(Duration::from_secs(2)),
+    ///     ..Limits::default()
+    /// };
+    /// assert_eq!(limits.go_command(), "go depth 20 movetime 2000");
+    /// assert_eq!(Limits::default().go_command(), "go infinite");
+    /// ```
+    pub fn go_command(&self) -> String {
+        let mut ret = "go".to_string();
+        if let Some(depth) = self.depth {
+            ret.push_str(&format!(" depth {}", depth));
+        }
+        if let Some(nodes) = self.nodes {
+            ret.push_str(&format!(" nodes {}", nodes));
+        }
+        if let Some(movetime) = self.movetime {
+            ret.push_str(&format!(" movetime {}", movetime.as_millis()));
+        }
+        if let Some(mate) = self.mate {
+            ret.push_str(&format!(" mate {}", mate));
+        }
+        if ret == "go" {
+            ret.push_str(" infinite");
+        }
+        ret
+    }
+}
+
+/// Returns the `position` command for a position without history.
+fn fen_position_command(position: &VariantPosition) -> String {
+    let fen = Fen::from_position(position.clone(), EnPassantMode::Legal);
+    format!("position fen {}", fen)
+}
+
+/// A running UCI engine.
+///
+/// The handshake is done when the engine is started. Dropping the engine
+/// sends `quit`, and kills the process if it does not exit soon after.
+///
+/// # Examples
+///
+/// ```no_run
+/// use sacrifice::engine::{Engine, Limits};
+///
+/// let mut engine = Engine::spawn("stockfish").unwrap();
+/// engine.set_option("Threads", "4").unwrap();
+///
+/// let game = sacrifice::read_pgn("1. e4 e5 2. Nf3 Nc6 3. Bb5").unwrap();
+/// let node = game.root().mainline_iter().last().unwrap();
+/// let analysis = engine.analyze(&node, &Limits::depth(20)).unwrap();
+/// println!("{:?} {:?}", analysis.best_move, analysis.eval);
+/// ```
+#[derive(Debug)]
+pub struct Engine {
+    process: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+    id: EngineId,
+}
+
+impl Engine {
+    /// Starts the engine at the given path and does the handshake.
+    ///
+    /// # Arguments
+    ///
+    /// * `program` - path or name of the engine executable
+    pub fn spawn(program: impl AsRef<OsStr>) -> Result<Self, EngineError> {
+        Self::from_command(Command::new(program))
+    }
+
+    /// Starts the engine with the given command, e.g. to pass arguments
+    /// or set the working directory, and does the handshake. Standard
+    /// input and output are replaced by pipes.
+    ///
+    /// # Arguments
+    ///
+    /// * `command` - the command that starts the engine
+    pub fn from_command(mut command: Command) -> Result<Self, EngineError> {
+        let mut process = command
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+        let stdin = process.stdin.take().ok_or(EngineError::Terminated)?;
+        let stdout = process.stdout.take().ok_or(EngineError::Terminated)?;
+
+        let mut engine = Self {
+            process,
+            stdin,
+            stdout: BufReader::new(stdout),
+            id: EngineId::default(),
+        };
+
+        engine.send("uci")?;
+        loop {
+            let line = engine.read_line()?;
+            if engine.id.read(&line) {
+                break;
+            }
+        }
+        engine.sync()?;
+
+        Ok(engine)
+    }
+
+    /// Returns the name the engine gave in the handshake.
+    pub fn name(&self) -> Option<&str> {
+        self.id.name.as_deref()
+    }
+
+    /// Returns the author the engine gave in the handshake.
+    pub fn author(&self) -> Option<&str> {
+        self.id.author.as_deref()
+    }
+
+    /// Returns the options the engine announced in the handshake.
+    pub fn options(&self) -> &[UciOption] {
+        &self.id.option_vec
+    }
+
+    /// Sets an engine option, and waits until the engine is ready.
+    ///
+    /// Option names are matched ignoring case, as in the protocol.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - name of an option the engine announced
+    /// * `value` - the new value; empty for `button` options
+    pub fn set_option(&mut self, name: &str, value: &str) -> Result<(), EngineError> {
+        let command = self.id.setoption_command(name, value)?;
+        self.send(&command)?;
+        self.sync()
+    }
+
+    /// Tells the engine that the next search is from a different game,
+    /// and waits until it is ready.
+    pub fn new_game(&mut self) -> Result<(), EngineError> {
+        self.send("ucinewgame")?;
+        self.sync()
+    }
+
+    /// Searches the position of the given node, with the moves leading to
+    /// it so that the engine knows about repetitions.
+    ///
+    /// # Arguments
+    ///
+    /// * `node` - the node to analyze
+    /// * `limits` - when to stop the search
+    pub fn analyze(&mut self, node: &Node, limits: &Limits) -> Result<Analysis, EngineError> {
+        self.search(&node.uci_position_command(), node.position(), limits)
+    }
+
+    /// Searches the given position, without any history.
+    ///
+    /// # Arguments
+    ///
+    /// * `position` - the position to analyze
+    /// * `limits` - when to stop the search
+    pub fn analyze_position(
+        &mut self,
+        position: &VariantPosition,
+        limits: &Limits,
+    ) -> Result<Analysis, EngineError> {
+        self.search(&fen_position_command(position), position.clone(), limits)
+    }
+
+    fn search(
+        &mut self,
+        position_command: &str,
+        position: VariantPosition,
+        limits: &Limits,
+    ) -> Result<Analysis, EngineError> {
+        self.send(position_command)?;
+        self.send(&limits.go_command())?;
+
+        let mut analysis = Analysis::default();
+        loop {
+            let line = self.read_line()?;
+            if let Some(info) = Info::parse(&line, &position) {
+                analysis.update(info);
+            } else if analysis.finish(&line, &position) {
+                return Ok(analysis);
+            }
+        }
+    }
+
+    /// Sends `isready` and waits for `readyok`.
+    fn sync(&mut self) -> Result<(), EngineError> {
+        self.send("isready")?;
+        while self.read_line()?.trim() != "readyok" {}
+        Ok(())
+    }
+
+    fn send(&mut self, command: &str) -> Result<(), EngineError> {
+        writeln!(self.stdin, "{}", command)?;
+        self.stdin.flush()?;
+        Ok(())
+    }
+
+    fn read_line(&mut self) -> Result<String, EngineError> {
+        let mut line = String::new();
+        if self.stdout.read_line(&mut line)? == 0 {
+            return Err(EngineError::Terminated);
+        }
+        Ok(line.trim_end().to_string())
+    }
+}
+
+impl Drop for Engine {
+    fn drop(&mut self) {
+        if self.send("quit").is_ok() {
+            let deadline = Instant::now() + Duration::from_secs(1);
+            while Instant::now() < deadline {
+                if let Ok(Some(_)) = self.process.try_wait() {
+                    return;
+                }
+                std::thread::sleep(Duration::from_millis(10));
+            }
+        }
+        let _ = self.process.kill();
+        let _ = self.process.wait();
+    }
+}
@@ -0,0 +1,134 @@
+use super::{Engine, EngineError, Limits};
+use crate::game::{Game, GameResult, Node};
+use crate::{Color, Outcome, Position};
+
+/// How the engines play a game against each other, see
+/// [`Game::selfplay`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SelfPlayOptions {
+    /// When White's searches stop
+    pub white_limits: Limits,
+    /// When Black's searches stop
+    pub black_limits: Limits,
+    /// The most moves to play; the game is left unfinished after them
+    pub max_plies: Option<usize>,
+}
+
+impl SelfPlayOptions {
+    /// Returns options with the same limits for both sides and no cap on
+    /// the number of moves.
+    pub fn new(limits: Limits) -> Self {
+        Self {
+            white_limits: limits.clone(),
+            black_limits: limits,
+            max_plies: None,
+        }
+    }
+}
+
+impl Game {
+    /// Lets engines play on from a node of this game, adding their moves
+    /// to the tree, until the game is over.
+    ///
+    /// A move the node already has a child for continues in that child;
+    /// other moves start a new variation. Each new node gets the
+    /// evaluation the engine gave for its move. The game ends with the
+    /// position, including draws that could only be claimed, or when the
+    /// engine gives no legal move.
+    ///
+    /// The `White` and `Black` headers are set to the engine names. If the
+    /// last node is on the mainline, the `Result` header is set to how the
+    /// game ended, or to `*` if it did not.
+    ///
+    /// Returns the last node played.
+    ///
+    /// # Arguments
+    ///
+    /// * `node` - a node of this game to play on from
+    /// * `white` - the engine playing White
+    /// * `black` - the engine playing Black, or `None` for `white` to play
+    ///   against itself
+    /// * `options` - limits for each side and a cap on the moves
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use sacrifice::engine::{Engine, Limits, SelfPlayOptions};
+    ///
+    /// let mut stockfish = Engine::spawn("stockfish").unwrap();
+    /// let mut lc0 = Engine::spawn("lc0").unwrap();
+    ///
+    /// let mut game = sacrifice::read_pgn("1. e4 c5 2. Nf3 d6 *").unwrap();
+    /// let node = game.root().mainline_iter().last().unwrap();
+    /// let options = SelfPlayOptions::new(Limits::nodes(100_000));
+    /// game.selfplay(&node, &mut stockfish, Some(&mut lc0), &options).unwrap();
+    /// println!("{}", game);
+    /// ```
+    pub fn selfplay(
+        &mut self,
+        node: &Node,
+        white: &mut Engine,
+        mut black: Option<&mut Engine>,
+        options: &SelfPlayOptions,
+    ) -> Result<Node, EngineError> {
+        white.new_game()?;
+        if let Some(engine) = black.as_deref_mut() {
+            engine.new_game()?;
+        }
+        let white_name = white.name().map(str::to_string);
+        let black_name = match black.as_deref() {
+            Some(engine) => engine.name().map(str::to_string),
+            None => white_name.clone(),
+        };
+
+        let mut node = node.clone();
+        let mut plies = 0;
+        while options.max_plies.is_none_or(|max| plies < max)
+            && !node.position().is_game_over()
+            && node.draw_reason().is_none()
+        {
+            let (engine, limits) = match (node.turn(), black.as_deref_mut()) {
+                (Color::Black, Some(engine)) => (engine, &options.black_limits),
+                (Color::Black, None) => (&mut *white, &options.black_limits),
+                (Color::White, _) => (&mut *white, &options.white_limits),
+            };
+            let analysis = engine.analyze(&node, limits)?;
+            let best_move = if let Some(val) = analysis.best_move.clone() {
+                val
+            } else {
+                break;
+            };
+            let mut next = if let Some(val) = node.new_variation(best_move) {
+                val
+            } else {
+                break;
+            };
+            if let Some(info) = analysis.eval_info(engine.name()) {
+                next.set_eval_info(Some(info));
+            }
+            node = next;
+            plies += 1;
+        }
+
+        if white_name.is_some() {
+            self.header.white = white_name;
+        }
+        if black_name.is_some() {
+            self.header.black = black_name;
+        }
+        if node.is_mainline() {
+            self.header.result = match node.outcome() {
+                Some(Outcome::Decisive {
+                    winner: Color::White,
+                }) => GameResult::WhiteWins,
+                Some(Outcome::Decisive {
+                    winner: Color::Black,
+                }) => GameResult::BlackWins,
+                _ if node.draw_reason().is_some() => GameResult::Draw,
+                _ => GameResult::Ongoing,
+            };
+        }
+
+        Ok(node)
+    }
+}
@@ -0,0 +1,380 @@
+//! Extended Position Description (EPD) support.
+//!
+//! An EPD line is the first four fields of a FEN followed by opcodes, such
+//! as `bm` (best move) or `id`, as found in test suites like WAC and STS.
+
+use crate::game::{CommentPlacement, Eval, Game, Node};
+use crate::{CastlingMode, Chess, Color, Fen, Move, Position, San, VariantPosition};
+
+use shakmaty::EnPassantMode;
+use std::io::Write;
+
+/// Error from parsing an EPD line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EpdError {
+    /// The position fields are missing or do not form a legal position
+    InvalidPosition(String),
+    /// An operand of the given opcode could not be parsed
+    InvalidOperand { opcode: String, operand: String },
+    /// A quoted operand is not terminated
+    UnterminatedString,
+}
+
+impl std::fmt::Display for EpdError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EpdError::InvalidPosition(fen) => write!(f, "invalid position {}", fen),
+            EpdError::InvalidOperand { opcode, operand } => {
+                write!(f, "invalid operand {} for opcode {}", operand, opcode)
+            }
+            EpdError::UnterminatedString => write!(f, "unterminated string operand"),
+        }
+    }
+}
+
+impl std::error::Error for EpdError {}
+
+/// A single EPD operation, e.g. `bm Nf3 Nc3;`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Operation {
+    pub opcode: String,
+    pub operands: Vec<String>,
+}
+
+/// A parsed EPD line.
+#[derive(Debug, Clone)]
+pub struct Epd {
+    /// A game with no moves, starting from the described position
+    pub game: Game,
+
+    /// `id`: identifier of the position
+    pub id: Option<String>,
+    /// `bm`: best moves
+    pub best_moves: Vec<Move>,
+    /// `am`: moves to avoid
+    pub avoid_moves: Vec<Move>,
+    /// `ce`: evaluation in centipawns, from the side to move
+    pub centipawns: Option<i32>,
+    /// `dm`: number of moves to a forced mate
+    pub direct_mate: Option<u32>,
+    /// `pv`: predicted variation
+    pub predicted_variation: Vec<Move>,
+
+    /// All operations in order of appearance, including the ones above
+    pub operations: Vec<Operation>,
+}
+
+impl Epd {
+    /// Returns the position the EPD line describes.
+    pub fn position(&self) -> VariantPosition {
+        self.game.initial_position()
+    }
+
+    /// Returns the operands of the first operation with the given opcode.
+    pub fn operands(&self, opcode: &str) -> Option<&[String]> {
+        self.operations
+            .iter()
+            .find(|op| op.opcode == opcode)
+            .map(|op| op.operands.as_slice())
+    }
+}
+
+/// Parse one EPD line.
+///
+/// # Arguments
+///
+/// * `line` - the EPD record
+///
+/// # Examples
+///
+/// ```
+/// let epd = sacrifice::epd::read_epd(
+///     r#"2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1 w - - bm Qg6; id "WAC.001";"#
+/// ).unwrap();
+/// assert_eq!(epd.id, Some("WAC.001".to_string()));
+/// assert_eq!(epd.best_moves[0].to(), sacrifice::Square::G6);
+/// ```
+pub fn read_epd(line: &str) -> Result<Epd, EpdError> {
+    let line = line.trim();
+
+    let mut fields = line.splitn(5, char::is_whitespace);
+    let position_fields = fields.by_ref().take(4).collect::<Vec<&str>>();
+    if position_fields.len() < 4 {
+        return Err(EpdError::InvalidPosition(line.to_string()));
+    }
+    let operations = parse_operations(fields.next().unwrap_or(""))?;
+
+    let find_operand = |opcode: &str| {
+        operations
+            .iter()
+            .find(|op| op.opcode == opcode)
+            .and_then(|op| op.operands.first())
+    };
+    let halfmoves = find_operand("hmvc").map_or("0", |s| s.as_str());
+    let fullmoves = find_operand("fmvn").map_or("1", |s| s.as_str());
+
+    let fen_str = format!("{} {} {}", position_fields.join(" "), halfmoves, fullmoves);
+    let position: Chess = fen_str
+        .parse::<Fen>()
+        .ok()
+        .and_then(|f| f.into_position(CastlingMode::Standard).ok())
+        .ok_or_else(|| EpdError::InvalidPosition(fen_str.clone()))?;
+
+    let mut game = Game {
+        root: Node::from_position(position.clone().into()),
+        ..Game::default()
+    };
+    game.opt_headers
+        .insert("SetUp".to_string(), "1".to_string());
+    game.opt_headers.insert("FEN".to_string(), fen_str);
+
+    let mut epd = Epd {
+        game,
+
+        id: None,
+        best_moves: Vec::new(),
+        avoid_moves: Vec::new(),
+        centipawns: None,
+        direct_mate: None,
+        predicted_variation: Vec::new(),
+
+        operations: Vec::new(),
+    };
+
+    for op in &operations {
+        let invalid_operand = |operand: &str| EpdError::InvalidOperand {
+            opcode: op.opcode.clone(),
+            operand: operand.to_string(),
+        };
+        let first_operand = op.operands.first().map_or("", |s| s.as_str());
+
+        match op.opcode.as_str() {
+            "id" => epd.id = Some(first_operand.to_string()),
+            "bm" => epd.best_moves = parse_moves(&position, &op.operands, false, invalid_operand)?,
+            "am" => epd.avoid_moves = parse_moves(&position, &op.operands, false, invalid_operand)?,
+            "pv" => {
+                epd.predicted_variation =
+                    parse_moves(&position, &op.operands, true, invalid_operand)?
+            }
+            "ce" => {
+                let val = first_operand
+                    .parse()
+                    .map_err(|_| invalid_operand(first_operand))?;
+                epd.centipawns = Some(val);
+            }
+            "dm" => {
+                let val = first_operand
+                    .parse()
+                    .map_err(|_| invalid_operand(first_operand))?;
+                epd.direct_mate = Some(val);
+            }
+            _ => {}
+        }
+    }
+    epd.operations = operations;
+
+    Ok(epd)
+}
+
+/// Which nodes of a game are written as EPD lines.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EpdSelection {
+    /// The starting position and every main line position
+    #[default]
+    Mainline,
+    /// Every position in the tree, variations included, in PGN order
+    All,
+}
+
+impl Game {
+    /// Writes one EPD line per selected node.
+    ///
+    /// Every line carries `hmvc` and `fmvn`, then the annotations of the
+    /// move that reached the position: `ce` or `dm` for its evaluation
+    /// (from the side to move), `c0` for its comments and `nag` for its
+    /// NAGs.
+    ///
+    /// # Arguments
+    ///
+    /// * `sink` - where to write the EPD lines
+    /// * `selection` - which nodes to write
+    pub fn write_epd<W: Write>(&self, mut sink: W, selection: EpdSelection) -> std::io::Result<()> {
+        let root = self.root();
+        let node_iter: Box<dyn Iterator<Item = Node>> = match selection {
+            EpdSelection::Mainline => Box::new(root.mainline_iter()),
+            EpdSelection::All => Box::new(root.descendants()),
+        };
+        for node in std::iter::once(root.clone()).chain(node_iter) {
+            writeln!(sink, "{}", epd_line(&node))?;
+        }
+
+        sink.flush()
+    }
+
+    /// Returns the selected nodes as EPD lines.
+    ///
+    /// # Arguments
+    ///
+    /// * `selection` - which nodes to write
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sacrifice::epd::EpdSelection;
+    ///
+    /// let game = sacrifice::read_pgn("1. e4 $1 { Best by test } (1. d4) 1... c5 { [%eval 0.3] } *").unwrap();
+    /// let epd = game.to_epd(EpdSelection::Mainline);
+    /// let lines: Vec<&str> = epd.lines().collect();
+    /// assert_eq!(lines.len(), 3);
+    /// assert_eq!(
+    ///     lines[1],
+    ///     r#"rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - hmvc 0; fmvn 1; c0 "Best by test"; nag $1;"#
+    /// );
+    /// assert!(lines[2].ends_with("w KQkq - hmvc 0; fmvn 2; ce 30;"));
+    /// assert_eq!(game.to_epd(EpdSelection::All).lines().count(), 4);
+    /// ```
+    pub fn to_epd(&self, selection: EpdSelection) -> String {
+        let mut buffer: Vec<u8> = Vec::new();
+        self.write_epd(&mut buffer, selection)
+            .expect("writing to a Vec never fails");
+
+        // Everything written is valid UTF-8
+        String::from_utf8(buffer).unwrap()
+    }
+}
+
+/// Returns the EPD line of a node's position and annotations.
+fn epd_line(node: &Node) -> String {
+    let position = node.position();
+    let fen = Fen::from_position(position.clone(), EnPassantMode::Legal).to_string();
+    let position_fields = fen.split(' ').take(4).collect::<Vec<&str>>().join(" ");
+
+    let mut line = format!(
+        "{} hmvc {}; fmvn {};",
+        position_fields,
+        position.halfmoves(),
+        position.fullmoves()
+    );
+
+    let perspective = if position.turn() == Color::White {
+        1
+    } else {
+        -1
+    };
+    match node.eval() {
+        Some(Eval::Centipawns(cp)) => line.push_str(&format!(" ce {};", cp * perspective)),
+        Some(Eval::Mate(moves)) if moves * perspective > 0 => {
+            line.push_str(&format!(" dm {};", moves * perspective))
+        }
+        _ => {}
+    }
+
+    let comment_vec = node
+        .comments()
+        .into_iter()
+        .filter(|c| c.placement == CommentPlacement::AfterMove)
+        .map(|c| c.text)
+        .collect::<Vec<String>>();
+    if !comment_vec.is_empty() {
+        let comment = comment_vec
+            .join(" ")
+            .replace('\\', "\\\\")
+            .replace('"', "\\\"");
+        line.push_str(&format!(" c0 \"{}\";", comment));
+    }
+
+    if let Some(nags) = node.nags() {
+        let mut nag_vec: Vec<u8> = nags.into_iter().collect();
+        if !nag_vec.is_empty() {
+            nag_vec.sort_unstable();
+            let nags = nag_vec
+                .iter()
+                .map(|nag| format!("${}", nag))
+                .collect::<Vec<String>>()
+                .join(" ");
+            line.push_str(&format!(" nag {};", nags));
+        }
+    }
+
+    line
+}
+
+/// Parses SAN operands, either all from the same position or as a line.
+fn parse_moves(
+    position: &Chess,
+    operands: &[String],
+    as_line: bool,
+    invalid_operand: impl Fn(&str) -> EpdError,
+) -> Result<Vec<Move>, EpdError> {
+    let mut position = position.clone();
+    let mut move_vec = Vec::new();
+    for operand in operands {
+        let m = operand
+            .parse::<San>()
+            .ok()
+            .and_then(|san| san.to_move(&position).ok())
+            .ok_or_else(|| invalid_operand(operand))?;
+        if as_line {
+            position.play_unchecked(&m);
+        }
+        move_vec.push(m);
+    }
+
+    Ok(move_vec)
+}
+
+/// Splits the operation part of an EPD line into opcodes and operands.
+fn parse_operations(text: &str) -> Result<Vec<Operation>, EpdError> {
+    let mut operations = Vec::new();
+    let mut tokens: Vec<String> = Vec::new();
+    let mut chars = text.chars().peekable();
+
+    let mut finish = |tokens: &mut Vec<String>| {
+        if tokens.is_empty() {
+            return;
+        }
+        let opcode = tokens.remove(0);
+        operations.push(Operation {
+            opcode,
+            operands: std::mem::take(tokens),
+        });
+    };
+
+    while let Some(c) = chars.next() {
+        match c {
+            ';' => finish(&mut tokens),
+            '"' => {
+                let mut token = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => {
+                            if let Some(escaped) = chars.next() {
+                                token.push(escaped);
+                            }
+                        }
+                        Some(c) => token.push(c),
+                        None => return Err(EpdError::UnterminatedString),
+                    }
+                }
+                tokens.push(token);
+            }
+            c if c.is_whitespace() => {}
+            c => {
+                let mut token = c.to_string();
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || c == ';' {
+                        break;
+                    }
+                    token.push(c);
+                    chars.next();
+                }
+                tokens.push(token);
+            }
+        }
+    }
+    // The last operation may lack its semicolon
+    finish(&mut tokens);
+
+    Ok(operations)
+}
@@ -0,0 +1,211 @@
+//! Opening explorer built from a game collection.
+//!
+//! An [`Explorer`] counts, for every position reached in the opening of
+//! many games, which moves were played from it and how the games ended,
+//! like the lichess opening explorer over a local database.
+
+use crate::game::{Game, GameResult, Node};
+use crate::{Move, Position, San};
+
+use std::collections::HashMap;
+
+/// Games that went through a position or move, by result.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MoveStats {
+    pub games: u32,
+    pub white_wins: u32,
+    pub draws: u32,
+    pub black_wins: u32,
+    /// Sum of the average ratings of the games where both players are
+    /// rated
+    rating_sum: u64,
+    rated_games: u32,
+}
+
+impl MoveStats {
+    /// Returns the average rating of the players, counting only games
+    /// where both are rated, or `None` if there are none.
+    pub fn average_rating(&self) -> Option<u16> {
+        if self.rated_games == 0 {
+            return None;
+        }
+        Some((self.rating_sum / u64::from(self.rated_games)) as u16)
+    }
+
+    fn add(&mut self, result: GameResult, rating: Option<u16>) {
+        self.games += 1;
+        match result {
+            GameResult::WhiteWins => self.white_wins += 1,
+            GameResult::BlackWins => self.black_wins += 1,
+            GameResult::Draw => self.draws += 1,
+            GameResult::Ongoing => {}
+        }
+        if let Some(rating) = rating {
+            self.rating_sum += u64::from(rating);
+            self.rated_games += 1;
+        }
+    }
+}
+
+/// A move played from a position, with the games that went on with it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExplorerMove {
+    pub move_next: Move,
+    /// The move in SAN, e.g. `Nf3`
+    pub san: String,
+    pub stats: MoveStats,
+}
+
+/// Move statistics per position, collected from the mainlines of many
+/// games.
+///
+/// Positions are looked up by their Zobrist hash, so a position reached
+/// by different move orders has the statistics of all of them. Games
+/// without a result are skipped.
+///
+/// # Examples
+///
+/// ```
+/// use sacrifice::explorer::Explorer;
+///
+/// let games = [
+///     "[Result \"1-0\"]\n\n1. e4 e5 2. Nf3 1-0",
+///     "[Result \"0-1\"]\n\n1. Nf3 e5 2. e4 0-1",
+///     "[Result \"1/2-1/2\"]\n\n1. e4 c5 1/2-1/2",
+/// ]
+/// .map(|pgn| sacrifice::read_pgn(pgn).unwrap());
+///
+/// let mut explorer = Explorer::new(20);
+/// assert_eq!(explorer.add_games(games), 3);
+///
+/// let game = sacrifice::read_pgn("1. e4 e5 2. Nf3 *").unwrap();
+/// let moves = explorer.moves(&game.root());
+/// assert_eq!(moves[0].san, "e4");
+/// assert_eq!(moves[0].stats.games, 2);
+/// assert_eq!(moves[1].san, "Nf3");
+///
+/// // 2. Nf3 and 2. e4 reach the same position
+/// let stats = explorer.position_stats(&game.root().mainline_iter().last().unwrap());
+/// assert_eq!((stats.white_wins, stats.draws, stats.black_wins), (1, 0, 1));
+/// ```
+#[derive(Debug, Clone)]
+pub struct Explorer {
+    max_plies: u32,
+    position_map: HashMap<u64, PositionEntry>,
+    game_count: usize,
+}
+
+#[derive(Debug, Clone, Default)]
+struct PositionEntry {
+    stats: MoveStats,
+    move_map: HashMap<Move, MoveStats>,
+}
+
+impl Explorer {
+    /// Returns an empty explorer.
+    ///
+    /// # Arguments
+    ///
+    /// * `max_plies` - how many moves of each game to count
+    pub fn new(max_plies: u32) -> Self {
+        Self {
+            max_plies,
+            position_map: HashMap::new(),
+            game_count: 0,
+        }
+    }
+
+    /// Counts the mainline moves of a game, and returns whether the game
+    /// was used.
+    ///
+    /// # Arguments
+    ///
+    /// * `game` - the game to add
+    pub fn add_game(&mut self, game: &Game) -> bool {
+        let result = game.header.result;
+        if result == GameResult::Ongoing {
+            return false;
+        }
+        let rating = match (game.header.white_elo, game.header.black_elo) {
+            (Some(white), Some(black)) => Some(((u32::from(white) + u32::from(black)) / 2) as u16),
+            _ => None,
+        };
+
+        let mut node = game.root();
+        for ply in 0..=self.max_plies {
+            let entry = self.position_map.entry(node.zobrist()).or_default();
+            entry.stats.add(result, rating);
+            if ply == self.max_plies {
+                break;
+            }
+
+            let next = if let Some(val) = node.mainline() {
+                val
+            } else {
+                break;
+            };
+            if let Some(m) = next.prev_move() {
+                entry.move_map.entry(m).or_default().add(result, rating);
+            }
+            node = next;
+        }
+
+        self.game_count += 1;
+        true
+    }
+
+    /// Counts the mainline moves of every game, and returns how many of
+    /// them were used.
+    ///
+    /// # Arguments
+    ///
+    /// * `games` - the games to add, e.g. from a PGN database
+    pub fn add_games(&mut self, games: impl IntoIterator<Item = Game>) -> usize {
+        games.into_iter().filter(|game| self.add_game(game)).count()
+    }
+
+    /// Returns the number of games added so far.
+    pub fn game_count(&self) -> usize {
+        self.game_count
+    }
+
+    /// Returns the games that reached the position of the given node.
+    ///
+    /// # Arguments
+    ///
+    /// * `node` - a node of any game
+    pub fn position_stats(&self, node: &Node) -> MoveStats {
+        self.position_map
+            .get(&node.zobrist())
+            .map(|entry| entry.stats)
+            .unwrap_or_default()
+    }
+
+    /// Returns the moves played from the position of the given node, most
+    /// played first.
+    ///
+    /// # Arguments
+    ///
+    /// * `node` - a node of any game
+    pub fn moves(&self, node: &Node) -> Vec<ExplorerMove> {
+        let entry = if let Some(val) = self.position_map.get(&node.zobrist()) {
+            val
+        } else {
+            return Vec::new();
+        };
+        let position = node.position();
+
+        let mut move_vec: Vec<ExplorerMove> = entry
+            .move_map
+            .iter()
+            .filter(|(m, _)| position.is_legal(m))
+            .map(|(m, stats)| ExplorerMove {
+                move_next: m.clone(),
+                san: San::from_move(&position, m).to_string(),
+                stats: *stats,
+            })
+            .collect();
+        move_vec.sort_by(|a, b| b.stats.games.cmp(&a.stats.games).then(a.san.cmp(&b.san)));
+        move_vec
+    }
+}
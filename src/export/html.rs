@@ -0,0 +1,154 @@
+use super::escape_html;
+use crate::game::{Eval, Game};
+use crate::pgn::command;
+use crate::pgn::writer::{nag_suffix, Skip, Visitor};
+use crate::{Color, Fen, Move, Position, VariantPosition};
+
+use shakmaty::san::SanPlus;
+use shakmaty::EnPassantMode;
+use std::time::Duration;
+
+/// A visitor producing semantic HTML.
+///
+/// Tag pairs become a `<table class="headers">`, and every move a
+/// `<span class="move">` whose `data-fen` holds the position after it.
+/// Comments, NAGs, evaluations and clocks are spans as well, and
+/// variations are nested `<ul class="variations">` lists.
+#[derive(Debug, Default)]
+pub struct HtmlWriter {
+    html: String,
+
+    force_move_number: bool,
+}
+
+impl HtmlWriter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn push_span(&mut self, class: &str, text: &str) {
+        self.html.push_str(&format!(
+            "<span class=\"{}\">{}</span> ",
+            class,
+            escape_html(text)
+        ));
+    }
+}
+
+impl Visitor for HtmlWriter {
+    type Result = String;
+
+    fn begin_game(&mut self) {
+        self.html = String::from("<div class=\"game\">\n");
+        self.force_move_number = true;
+    }
+
+    fn begin_headers(&mut self) {
+        self.html.push_str("<table class=\"headers\">\n");
+    }
+
+    fn visit_header(&mut self, tag_name: &str, tag_value: &str) {
+        self.html.push_str(&format!(
+            "<tr><th>{}</th><td>{}</td></tr>\n",
+            escape_html(tag_name),
+            escape_html(tag_value)
+        ));
+    }
+
+    fn end_headers(&mut self) {
+        self.html.push_str("</table>\n<div class=\"movetext\">\n");
+    }
+
+    fn visit_move(&mut self, board: VariantPosition, next_move: Move) {
+        let move_number = match board.turn() {
+            Color::White => format!("{}. ", board.fullmoves()),
+            Color::Black if self.force_move_number => format!("{}... ", board.fullmoves()),
+            Color::Black => String::new(),
+        };
+        let san = SanPlus::from_move(board.clone(), &next_move);
+
+        let mut position = board;
+        position.play_unchecked(&next_move);
+        let fen = Fen::from_position(position, EnPassantMode::Legal);
+
+        self.html.push_str(&format!(
+            "<span class=\"move\" data-fen=\"{}\">{}{}</span> ",
+            fen, move_number, san
+        ));
+        self.force_move_number = false;
+    }
+
+    fn visit_comment(&mut self, comment: String) {
+        self.push_span("comment", &comment);
+        self.force_move_number = true;
+    }
+
+    fn visit_nag(&mut self, nag: u8) {
+        let text = nag_suffix(nag).map_or_else(|| format!("${}", nag), str::to_string);
+        self.html.push_str(&format!(
+            "<span class=\"nag\" data-nag=\"{}\">{}</span> ",
+            nag, text
+        ));
+    }
+
+    fn visit_eval(&mut self, eval: Eval) {
+        self.push_span("eval", &command::format_eval(eval, 2));
+    }
+
+    fn visit_clock(&mut self, clock: Duration) {
+        self.push_span("clock", &command::format_clock(clock, 0));
+    }
+
+    fn begin_variation(&mut self) -> Skip {
+        // Sibling variations share one list
+        if self.html.ends_with("</li></ul>\n") {
+            self.html.truncate(self.html.len() - "</ul>\n".len());
+            self.html.push_str("\n<li>");
+        } else {
+            self.html.push_str("\n<ul class=\"variations\"><li>");
+        }
+        self.force_move_number = true;
+
+        Skip(false)
+    }
+
+    fn end_variation(&mut self) {
+        if self.html.ends_with(' ') {
+            self.html.pop();
+        }
+        self.html.push_str("</li></ul>\n");
+        self.force_move_number = true;
+    }
+
+    fn visit_result(&mut self, result: &str) {
+        self.push_span("result", result);
+    }
+
+    fn end_game(&mut self) -> Self::Result {
+        if self.html.ends_with(' ') {
+            self.html.pop();
+        }
+        self.html.push_str("\n</div>\n</div>\n");
+        std::mem::take(&mut self.html)
+    }
+}
+
+impl Game {
+    /// Returns the game as semantic HTML, see [`HtmlWriter`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let game = sacrifice::read_pgn("1. e4 $1 { <b>Best</b> } (1. d4) 1... e5 *").unwrap();
+    /// let html = game.to_html();
+    /// assert!(html.contains("<tr><th>Result</th><td>*</td></tr>"));
+    /// assert!(html.contains(
+    ///     r#"<span class="move" data-fen="rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1">1. e4</span>"#
+    /// ));
+    /// assert!(html.contains(r#"<span class="comment">&lt;b&gt;Best&lt;/b&gt;</span>"#));
+    /// assert!(html.contains(r#"<ul class="variations"><li><span class="move""#));
+    /// ```
+    pub fn to_html(&self) -> String {
+        self.visit(&mut HtmlWriter::new())
+    }
+}
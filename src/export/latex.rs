@@ -0,0 +1,237 @@
+use crate::game::{Eval, Game};
+use crate::pgn::writer::{nag_suffix, Skip, Visitor};
+use crate::{Color, Move, Position, VariantPosition};
+
+use shakmaty::san::SanPlus;
+use std::time::Duration;
+
+/// NAG requesting a diagram after the move
+pub const DIAGRAM_NAG: u8 = 220;
+/// NAG requesting a diagram from Black's side after the move
+pub const DIAGRAM_FLIPPED_NAG: u8 = 221;
+/// Comment marker requesting a diagram after the move, as used by Scid
+const DIAGRAM_MARKER: &str = "[#]";
+
+/// A visitor producing LaTeX for the `xskak` package.
+///
+/// The main line is set with `\mainline`, so the board follows it, and
+/// variations with `\variation`. A `\chessboard` diagram is placed after
+/// main line moves marked with [`DIAGRAM_NAG`], [`DIAGRAM_FLIPPED_NAG`] or
+/// a `[#]` comment.
+#[derive(Debug, Default)]
+pub struct LatexWriter {
+    latex: String,
+
+    game_keys: Vec<String>,
+    depth: usize,
+    /// Whether a `\mainline{` or `\variation{` group is open
+    group_open: bool,
+
+    force_move_number: bool,
+}
+
+impl LatexWriter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn open_group(&mut self) {
+        if self.group_open {
+            return;
+        }
+
+        self.latex.push_str(if self.depth == 0 {
+            "\\mainline{"
+        } else {
+            "\\variation{"
+        });
+        self.group_open = true;
+        self.force_move_number = true;
+    }
+
+    fn close_group(&mut self) {
+        if !self.group_open {
+            return;
+        }
+
+        if self.latex.ends_with(' ') {
+            self.latex.pop();
+        }
+        self.latex.push_str("} ");
+        self.group_open = false;
+    }
+
+    fn diagram(&mut self, flipped: bool) {
+        if self.depth > 0 {
+            return; // The board only follows the main line
+        }
+
+        self.close_group();
+        let len = self.latex.trim_end().len();
+        self.latex.truncate(len);
+        self.latex.push_str(if flipped {
+            "\n\n\\chessboard[inverse]\n\n"
+        } else {
+            "\n\n\\chessboard\n\n"
+        });
+    }
+}
+
+/// Escapes LaTeX special characters in text.
+fn escape_latex(text: &str) -> String {
+    let mut ret = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            '\\' => ret.push_str("\\textbackslash{}"),
+            '~' => ret.push_str("\\textasciitilde{}"),
+            '^' => ret.push_str("\\textasciicircum{}"),
+            '{' | '}' | '$' | '&' | '#' | '_' | '%' => {
+                ret.push('\\');
+                ret.push(ch);
+            }
+            _ => ret.push(ch),
+        }
+    }
+    ret
+}
+
+impl Visitor for LatexWriter {
+    type Result = String;
+
+    fn begin_game(&mut self) {
+        self.latex = String::new();
+        self.game_keys = Vec::new();
+        self.depth = 0;
+        self.group_open = false;
+    }
+
+    fn begin_headers(&mut self) {
+        // Nothing to do
+    }
+
+    fn visit_header(&mut self, tag_name: &str, tag_value: &str) {
+        let key = match tag_name {
+            "Event" | "Site" | "Date" | "Round" | "White" | "Black" | "Result" | "WhiteElo"
+            | "BlackElo" => tag_name.to_lowercase(),
+            "FEN" => "setfen".to_string(),
+            _ => return,
+        };
+        if tag_value.contains('?') && key != "result" {
+            return; // Unknown
+        }
+
+        self.game_keys
+            .push(format!("{}={{{}}}", key, escape_latex(tag_value)));
+    }
+
+    fn end_headers(&mut self) {
+        self.latex
+            .push_str(&format!("\\newchessgame[{}]\n", self.game_keys.join(",")));
+    }
+
+    fn visit_move(&mut self, board: VariantPosition, next_move: Move) {
+        self.open_group();
+
+        let move_number = match board.turn() {
+            Color::White => format!("{}.", board.fullmoves()),
+            Color::Black if self.force_move_number => format!("{}...", board.fullmoves()),
+            Color::Black => String::new(),
+        };
+        let san = SanPlus::from_move(board, &next_move);
+        self.latex.push_str(&format!("{}{} ", move_number, san));
+
+        self.force_move_number = false;
+    }
+
+    fn visit_comment(&mut self, comment: String) {
+        let diagram = comment.contains(DIAGRAM_MARKER);
+        let comment = comment.replace(DIAGRAM_MARKER, "");
+
+        self.close_group();
+        if !comment.trim().is_empty() {
+            self.latex
+                .push_str(&format!("{} ", escape_latex(comment.trim())));
+        }
+        if diagram {
+            self.diagram(false);
+        }
+    }
+
+    fn visit_nag(&mut self, nag: u8) {
+        match nag {
+            DIAGRAM_NAG => return self.diagram(false),
+            DIAGRAM_FLIPPED_NAG => return self.diagram(true),
+            _ => {}
+        }
+
+        if !self.group_open {
+            return; // Only moves carry NAGs
+        }
+
+        if self.latex.ends_with(' ') {
+            self.latex.pop();
+        }
+        match nag_suffix(nag) {
+            Some(suffix) => self.latex.push_str(&format!("{} ", suffix)),
+            None => self.latex.push_str(&format!(" ${} ", nag)),
+        }
+    }
+
+    fn visit_eval(&mut self, _eval: Eval) {
+        // Not typeset
+    }
+
+    fn visit_clock(&mut self, _clock: Duration) {
+        // Not typeset
+    }
+
+    fn begin_variation(&mut self) -> Skip {
+        self.close_group();
+        self.latex.push('(');
+        self.depth += 1;
+
+        Skip(false)
+    }
+
+    fn end_variation(&mut self) {
+        self.close_group();
+        if self.latex.ends_with(' ') {
+            self.latex.pop();
+        }
+        self.latex.push_str(") ");
+        self.depth -= 1;
+        self.force_move_number = true;
+    }
+
+    fn visit_result(&mut self, result: &str) {
+        self.close_group();
+        self.latex.push_str(&escape_latex(result));
+    }
+
+    fn end_game(&mut self) -> Self::Result {
+        let mut latex = std::mem::take(&mut self.latex).trim_end().to_string();
+        latex.push('\n');
+        latex
+    }
+}
+
+impl Game {
+    /// Returns the game as LaTeX for the `xskak` package, see
+    /// [`LatexWriter`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let game = sacrifice::read_pgn(
+    ///     "[White \"Morphy\"]\n\n1. e4 e5 2. Nf3 $1 { The most popular } (2. f4 $220) 2... d6 *"
+    /// ).unwrap();
+    /// assert_eq!(
+    ///     game.to_latex(),
+    ///     "\\newchessgame[white={Morphy},result={*}]\n\
+    ///      \\mainline{1.e4 e5 2.Nf3!} The most popular (\\variation{2.f4}) \\mainline{2...d6} *\n"
+    /// );
+    /// ```
+    pub fn to_latex(&self) -> String {
+        self.visit(&mut LatexWriter::new())
+    }
+}
@@ -0,0 +1,192 @@
+use crate::game::{Eval, Game};
+use crate::pgn::writer::{nag_suffix, Skip, Visitor};
+use crate::{Color, Move, Position, VariantPosition};
+
+use shakmaty::san::SanPlus;
+use std::time::Duration;
+
+/// A visitor producing Markdown.
+///
+/// Tag pairs become a YAML front matter block, the main line numbered
+/// text paragraphs, and variations nested bullet lists. Comments are set
+/// inline in italics.
+#[derive(Debug, Default)]
+pub struct MarkdownWriter {
+    line_vec: Vec<String>,
+    cur_line: String,
+
+    depth: usize,
+    /// Whether the next main line paragraph needs a blank line first
+    pending_blank: bool,
+
+    force_move_number: bool,
+}
+
+impl MarkdownWriter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn flush(&mut self) {
+        let cur_line = std::mem::take(&mut self.cur_line);
+        if cur_line.trim().is_empty() {
+            return;
+        }
+
+        if self.pending_blank {
+            self.line_vec.push(String::new());
+            self.pending_blank = false;
+        }
+        self.line_vec.push(cur_line.trim_end().to_string());
+    }
+
+    fn push_word(&mut self, word: &str) {
+        self.cur_line.push_str(word);
+        self.cur_line.push(' ');
+    }
+}
+
+/// Escapes characters with a meaning in Markdown.
+fn escape_markdown(text: &str) -> String {
+    let mut ret = String::with_capacity(text.len());
+    for ch in text.chars() {
+        if matches!(ch, '\\' | '*' | '_' | '`' | '[' | ']' | '<' | '>') {
+            ret.push('\\');
+        }
+        ret.push(ch);
+    }
+    ret
+}
+
+impl Visitor for MarkdownWriter {
+    type Result = String;
+
+    fn begin_game(&mut self) {
+        self.line_vec = Vec::new();
+        self.cur_line = String::new();
+        self.depth = 0;
+        self.pending_blank = false;
+        self.force_move_number = true;
+    }
+
+    fn begin_headers(&mut self) {
+        self.line_vec.push("---".to_string());
+    }
+
+    fn visit_header(&mut self, tag_name: &str, tag_value: &str) {
+        let tag_value = tag_value.replace('\\', "\\\\").replace('"', "\\\"");
+        self.line_vec
+            .push(format!("{}: \"{}\"", tag_name, tag_value));
+    }
+
+    fn end_headers(&mut self) {
+        if self.line_vec.len() == 1 {
+            self.line_vec.clear(); // No tags
+            return;
+        }
+
+        self.line_vec.push("---".to_string());
+        self.pending_blank = true;
+    }
+
+    fn visit_move(&mut self, board: VariantPosition, next_move: Move) {
+        let move_number = match board.turn() {
+            Color::White => format!("{}. ", board.fullmoves()),
+            Color::Black if self.force_move_number => format!("{}... ", board.fullmoves()),
+            Color::Black => String::new(),
+        };
+        let san = SanPlus::from_move(board, &next_move);
+        self.push_word(&format!(
+            "{}{}",
+            move_number,
+            escape_markdown(&san.to_string())
+        ));
+
+        self.force_move_number = false;
+    }
+
+    fn visit_comment(&mut self, comment: String) {
+        let comment = comment.trim();
+        if comment.is_empty() {
+            return;
+        }
+
+        self.push_word(&format!("*{}*", escape_markdown(comment)));
+        self.force_move_number = true;
+    }
+
+    fn visit_nag(&mut self, nag: u8) {
+        if self.cur_line.ends_with(' ') {
+            self.cur_line.pop();
+        }
+        match nag_suffix(nag) {
+            Some(suffix) => self.push_word(suffix),
+            None => self.push_word(&format!(" ${}", nag)),
+        }
+    }
+
+    fn visit_eval(&mut self, _eval: Eval) {
+        // Not shown
+    }
+
+    fn visit_clock(&mut self, _clock: Duration) {
+        // Not shown
+    }
+
+    fn begin_variation(&mut self) -> Skip {
+        self.flush();
+        self.depth += 1;
+        self.cur_line = format!("{}- ", "  ".repeat(self.depth - 1));
+        self.force_move_number = true;
+
+        Skip(false)
+    }
+
+    fn end_variation(&mut self) {
+        self.flush();
+        self.depth -= 1;
+        if self.depth == 0 {
+            self.pending_blank = true;
+        } else {
+            // Continue the enclosing list item
+            self.cur_line = "  ".repeat(self.depth);
+        }
+        self.force_move_number = true;
+    }
+
+    fn visit_result(&mut self, result: &str) {
+        self.push_word(&format!("**{}**", escape_markdown(result)));
+    }
+
+    fn end_game(&mut self) -> Self::Result {
+        self.flush();
+
+        let mut markdown = std::mem::take(&mut self.line_vec).join("\n");
+        markdown.push('\n');
+        markdown
+    }
+}
+
+impl Game {
+    /// Returns the game as Markdown, see [`MarkdownWriter`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let game = sacrifice::read_pgn(
+    ///     "[White \"Tal\"]\n\n1. e4 e5 { Classical } (1... c5 2. Nf3 (2. c3)) 2. Nf3 *"
+    /// ).unwrap();
+    /// let markdown = game.to_markdown();
+    /// assert!(markdown.starts_with("---\nEvent: \"?\"\n"));
+    /// assert!(markdown.ends_with(
+    ///     "\n\n1. e4 e5 *Classical*\n\
+    ///      - 1... c5 2. Nf3\n  \
+    ///        - 2. c3\n\
+    ///      \n\
+    ///      2. Nf3 **\\***\n"
+    /// ));
+    /// ```
+    pub fn to_markdown(&self) -> String {
+        self.visit(&mut MarkdownWriter::new())
+    }
+}
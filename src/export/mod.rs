@@ -0,0 +1,23 @@
+//! Exports of games to document formats.
+
+pub mod html;
+pub use html::HtmlWriter;
+pub mod latex;
+pub use latex::LatexWriter;
+pub mod markdown;
+pub use markdown::MarkdownWriter;
+
+/// Escapes text for use in HTML content and attribute values.
+pub(crate) fn escape_html(text: &str) -> String {
+    let mut ret = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            '&' => ret.push_str("&amp;"),
+            '<' => ret.push_str("&lt;"),
+            '>' => ret.push_str("&gt;"),
+            '"' => ret.push_str("&quot;"),
+            _ => ret.push(ch),
+        }
+    }
+    ret
+}
@@ -0,0 +1,212 @@
+use super::{ClassifyOptions, Eval, Game, MoveError};
+use crate::{Color, Position};
+
+/// How well one player played, judged by the evaluations of the game.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlayerAccuracy {
+    /// Number of moves judged
+    pub moves: usize,
+    /// Average centipawn loss per move
+    pub acpl: f64,
+    /// Accuracy from 0 to 100, computed as lichess does
+    pub accuracy: f64,
+    pub inaccuracies: usize,
+    pub mistakes: usize,
+    pub blunders: usize,
+}
+
+/// Accuracy of both players of a game; a player is `None` if none of
+/// their moves could be judged.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AccuracyReport {
+    pub white: Option<PlayerAccuracy>,
+    pub black: Option<PlayerAccuracy>,
+}
+
+impl AccuracyReport {
+    /// Returns the accuracy of the given player.
+    pub fn get(&self, color: Color) -> Option<&PlayerAccuracy> {
+        match color {
+            Color::White => self.white.as_ref(),
+            Color::Black => self.black.as_ref(),
+        }
+    }
+}
+
+/// One judged move.
+struct MoveLoss {
+    color: Color,
+    /// Centipawns lost by the mover, at least 0
+    cp_loss: f64,
+    /// Accuracy of the move, from 0 to 100
+    accuracy: f64,
+    error: Option<MoveError>,
+}
+
+/// Returns White's winning chances as a percentage.
+fn win_percent(cp: i32) -> f64 {
+    50.0 + 50.0 * Eval::Centipawns(cp).winning_chances()
+}
+
+/// Returns the accuracy of a move by the mover's win percentages before
+/// and after it, by the lichess formula.
+fn move_accuracy(before: f64, after: f64) -> f64 {
+    if after >= before {
+        return 100.0;
+    }
+    let raw =
+        103.1668100711649 * (-0.04354415386753951 * (before - after)).exp() - 3.166924740191411;
+    // lichess adds one point for the uncertainty of the evaluations
+    (raw + 1.0).clamp(0.0, 100.0)
+}
+
+fn standard_deviation(value_vec: &[f64]) -> f64 {
+    let mean = value_vec.iter().sum::<f64>() / value_vec.len() as f64;
+    let variance =
+        value_vec.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / value_vec.len() as f64;
+    variance.sqrt()
+}
+
+impl Game {
+    /// Returns the average centipawn loss and the accuracy of each player,
+    /// judged by the evaluations stored on the mainline nodes, e.g. by
+    /// [`Game::analyze_all`].
+    ///
+    /// The moves are judged from the root, or from the first move if the
+    /// root has no evaluation, up to the first node without one; a
+    /// checkmate counts as a decisive evaluation for the side that gave
+    /// it. Evaluations are capped at 1000 centipawns. The accuracy weighs
+    /// each move by how volatile the game was around it, as lichess does,
+    /// and the errors are counted with the default [`ClassifyOptions`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let game = sacrifice::read_pgn(
+    ///     "1. e4 { [%eval 0.3] } 1... e5 { [%eval 0.3] } 2. Qh5 { [%eval -1.0] } 2... Nc6 { [%eval -1.0] } *"
+    /// ).unwrap();
+    /// let report = game.accuracy();
+    /// let white = report.white.unwrap();
+    /// // Without an evaluation of the start, 1. e4 is not judged
+    /// assert_eq!(white.moves, 1);
+    /// assert_eq!(white.acpl, 130.0);
+    /// assert_eq!(white.mistakes, 1);
+    /// let black = report.black.unwrap();
+    /// assert_eq!(black.acpl, 0.0);
+    /// assert!(black.accuracy > white.accuracy);
+    /// ```
+    pub fn accuracy(&self) -> AccuracyReport {
+        let options = ClassifyOptions::default();
+
+        // Centipawns of each judged node, from White's point of view
+        let mut cp_vec = Vec::new();
+        let mut color_vec = Vec::new();
+        let root = self.root();
+        let mut node = if root.eval().is_some() {
+            root
+        } else if let Some(val) = root.mainline() {
+            val
+        } else {
+            return AccuracyReport {
+                white: None,
+                black: None,
+            };
+        };
+        loop {
+            let cp = if let Some(val) = node.eval() {
+                val.clamped_centipawns()
+            } else if node.position().is_checkmate() {
+                match node.turn() {
+                    Color::White => -1000,
+                    Color::Black => 1000,
+                }
+            } else {
+                break;
+            };
+            cp_vec.push(cp);
+            color_vec.push(node.turn());
+            node = if let Some(val) = node.mainline() {
+                val
+            } else {
+                break;
+            };
+        }
+        if cp_vec.len() < 2 {
+            return AccuracyReport {
+                white: None,
+                black: None,
+            };
+        }
+
+        let win_vec: Vec<f64> = cp_vec.iter().map(|&cp| win_percent(cp)).collect();
+        let move_vec: Vec<MoveLoss> = (1..cp_vec.len())
+            .map(|i| {
+                let color = color_vec[i - 1];
+                let (cp_before, cp_after, win_before, win_after) = match color {
+                    Color::White => (cp_vec[i - 1], cp_vec[i], win_vec[i - 1], win_vec[i]),
+                    Color::Black => (
+                        -cp_vec[i - 1],
+                        -cp_vec[i],
+                        100.0 - win_vec[i - 1],
+                        100.0 - win_vec[i],
+                    ),
+                };
+                let chance_loss = (win_before - win_after) / 50.0;
+                MoveLoss {
+                    color,
+                    cp_loss: f64::from((cp_before - cp_after).max(0)),
+                    accuracy: move_accuracy(win_before, win_after),
+                    error: options.classify(chance_loss),
+                }
+            })
+            .collect();
+
+        // Weigh each move by the volatility of the window ending with it
+        let window_size = (move_vec.len() / 10).clamp(2, 8).min(win_vec.len());
+        let mut weight_vec: Vec<f64> = Vec::new();
+        for _ in 0..window_size.saturating_sub(2) {
+            weight_vec.push(standard_deviation(&win_vec[..window_size]));
+        }
+        weight_vec.extend(win_vec.windows(window_size).map(standard_deviation));
+        for weight in weight_vec.iter_mut() {
+            *weight = weight.clamp(0.5, 12.0);
+        }
+
+        let player = |color: Color| {
+            let judged: Vec<(&MoveLoss, f64)> = move_vec
+                .iter()
+                .zip(weight_vec.iter().copied())
+                .filter(|(m, _)| m.color == color)
+                .collect();
+            if judged.is_empty() {
+                return None;
+            }
+
+            let count = judged.len() as f64;
+            let weight_sum: f64 = judged.iter().map(|(_, w)| w).sum();
+            let weighted_mean =
+                judged.iter().map(|(m, w)| m.accuracy * w).sum::<f64>() / weight_sum;
+            let harmonic_mean = count / judged.iter().map(|(m, _)| 1.0 / m.accuracy).sum::<f64>();
+            let count_errors = |error: MoveError| {
+                judged
+                    .iter()
+                    .filter(|(m, _)| m.error == Some(error))
+                    .count()
+            };
+
+            Some(PlayerAccuracy {
+                moves: judged.len(),
+                acpl: judged.iter().map(|(m, _)| m.cp_loss).sum::<f64>() / count,
+                accuracy: (weighted_mean + harmonic_mean) / 2.0,
+                inaccuracies: count_errors(MoveError::Inaccuracy),
+                mistakes: count_errors(MoveError::Mistake),
+                blunders: count_errors(MoveError::Blunder),
+            })
+        };
+
+        AccuracyReport {
+            white: player(Color::White),
+            black: player(Color::Black),
+        }
+    }
+}
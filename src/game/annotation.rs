@@ -0,0 +1,300 @@
+use crate::{Move, Square};
+
+/// An engine evaluation, from White's point of view.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Eval {
+    /// Advantage in centipawns
+    Centipawns(i32),
+    /// Forced mate in the given number of moves; negative if Black mates
+    Mate(i32),
+}
+
+impl Eval {
+    /// Returns the evaluation in centipawns, capped at 1000 either way,
+    /// with a forced mate counting as the cap.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sacrifice::game::Eval;
+    ///
+    /// assert_eq!(Eval::Centipawns(-250).clamped_centipawns(), -250);
+    /// assert_eq!(Eval::Centipawns(1500).clamped_centipawns(), 1000);
+    /// assert_eq!(Eval::Mate(-3).clamped_centipawns(), -1000);
+    /// ```
+    pub fn clamped_centipawns(self) -> i32 {
+        match self {
+            Eval::Centipawns(cp) => cp.clamp(-1000, 1000),
+            Eval::Mate(moves) => moves.signum() * 1000,
+        }
+    }
+
+    /// Returns White's winning chances, from -1 (Black wins) to 1 (White
+    /// wins), by the formula lichess uses to classify moves.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sacrifice::game::Eval;
+    ///
+    /// assert_eq!(Eval::Centipawns(0).winning_chances(), 0.0);
+    /// assert!((Eval::Centipawns(100).winning_chances() - 0.182).abs() < 0.001);
+    /// assert!(Eval::Mate(2).winning_chances() > 0.95);
+    /// ```
+    pub fn winning_chances(self) -> f64 {
+        let cp = f64::from(self.clamped_centipawns());
+        2.0 / (1.0 + (-0.00368208 * cp).exp()) - 1.0
+    }
+
+    /// Returns the NAG describing the advantage: `$10` (equal) below 0.3
+    /// pawns, then `$14`/`$15` (slight), `$16`/`$17` (moderate) and from
+    /// 1.5 pawns or a forced mate `$18`/`$19` (decisive) for White/Black.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sacrifice::game::Eval;
+    ///
+    /// assert_eq!(Eval::Centipawns(10).advantage_nag(), 10);
+    /// assert_eq!(Eval::Centipawns(-90).advantage_nag(), 17);
+    /// assert_eq!(Eval::Mate(4).advantage_nag(), 18);
+    /// ```
+    pub fn advantage_nag(self) -> u8 {
+        let cp = self.clamped_centipawns();
+        let nag = match cp.abs() {
+            0..=29 => return 10,
+            30..=69 => 14,
+            70..=149 => 16,
+            _ => 18,
+        };
+        if cp > 0 {
+            nag
+        } else {
+            nag + 1
+        }
+    }
+}
+
+/// An engine evaluation with the search that produced it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EvalInfo {
+    pub eval: Eval,
+    /// Search depth in plies, written as `[%eval 0.17,23]`
+    pub depth: Option<u32>,
+    /// Name of the engine; not part of PGN
+    pub engine: Option<String>,
+    /// The line the engine expects from the position; not part of PGN
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub pv: Vec<Move>,
+}
+
+impl From<Eval> for EvalInfo {
+    fn from(eval: Eval) -> Self {
+        Self {
+            eval,
+            depth: None,
+            engine: None,
+            pv: Vec::new(),
+        }
+    }
+}
+
+/// Color of an arrow or square highlight, as in lichess annotations.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ShapeColor {
+    #[default]
+    Green,
+    Red,
+    Yellow,
+    Blue,
+}
+
+/// An arrow (`[%cal]`) or, if both squares are the same, a highlighted
+/// square (`[%csl]`) drawn on the board at a node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Shape {
+    pub color: ShapeColor,
+    pub from: Square,
+    pub to: Square,
+}
+
+impl Shape {
+    pub fn arrow(color: ShapeColor, from: Square, to: Square) -> Self {
+        Self { color, from, to }
+    }
+
+    pub fn circle(color: ShapeColor, square: Square) -> Self {
+        Self {
+            color,
+            from: square,
+            to: square,
+        }
+    }
+
+    pub fn is_circle(&self) -> bool {
+        self.from == self.to
+    }
+}
+
+/// Descriptions of the standard NAGs, indexed by NAG.
+const NAG_GLOSSES: [&str; 140] = [
+    "null annotation",
+    "good move",
+    "poor move",
+    "very good move",
+    "very poor move",
+    "speculative move",
+    "questionable move",
+    "forced move (all others lose quickly)",
+    "singular move (no reasonable alternatives)",
+    "worst move",
+    "drawish position",
+    "equal chances, quiet position",
+    "equal chances, active position",
+    "unclear position",
+    "White has a slight advantage",
+    "Black has a slight advantage",
+    "White has a moderate advantage",
+    "Black has a moderate advantage",
+    "White has a decisive advantage",
+    "Black has a decisive advantage",
+    "White has a crushing advantage (Black should resign)",
+    "Black has a crushing advantage (White should resign)",
+    "White is in zugzwang",
+    "Black is in zugzwang",
+    "White has a slight space advantage",
+    "Black has a slight space advantage",
+    "White has a moderate space advantage",
+    "Black has a moderate space advantage",
+    "White has a decisive space advantage",
+    "Black has a decisive space advantage",
+    "White has a slight time (development) advantage",
+    "Black has a slight time (development) advantage",
+    "White has a moderate time (development) advantage",
+    "Black has a moderate time (development) advantage",
+    "White has a decisive time (development) advantage",
+    "Black has a decisive time (development) advantage",
+    "White has the initiative",
+    "Black has the initiative",
+    "White has a lasting initiative",
+    "Black has a lasting initiative",
+    "White has the attack",
+    "Black has the attack",
+    "White has insufficient compensation for material deficit",
+    "Black has insufficient compensation for material deficit",
+    "White has sufficient compensation for material deficit",
+    "Black has sufficient compensation for material deficit",
+    "White has more than adequate compensation for material deficit",
+    "Black has more than adequate compensation for material deficit",
+    "White has a slight center control advantage",
+    "Black has a slight center control advantage",
+    "White has a moderate center control advantage",
+    "Black has a moderate center control advantage",
+    "White has a decisive center control advantage",
+    "Black has a decisive center control advantage",
+    "White has a slight kingside control advantage",
+    "Black has a slight kingside control advantage",
+    "White has a moderate kingside control advantage",
+    "Black has a moderate kingside control advantage",
+    "White has a decisive kingside control advantage",
+    "Black has a decisive kingside control advantage",
+    "White has a slight queenside control advantage",
+    "Black has a slight queenside control advantage",
+    "White has a moderate queenside control advantage",
+    "Black has a moderate queenside control advantage",
+    "White has a decisive queenside control advantage",
+    "Black has a decisive queenside control advantage",
+    "White has a vulnerable first rank",
+    "Black has a vulnerable first rank",
+    "White has a well protected first rank",
+    "Black has a well protected first rank",
+    "White has a poorly protected king",
+    "Black has a poorly protected king",
+    "White has a well protected king",
+    "Black has a well protected king",
+    "White has a poorly placed king",
+    "Black has a poorly placed king",
+    "White has a well placed king",
+    "Black has a well placed king",
+    "White has a very weak pawn structure",
+    "Black has a very weak pawn structure",
+    "White has a moderately weak pawn structure",
+    "Black has a moderately weak pawn structure",
+    "White has a moderately strong pawn structure",
+    "Black has a moderately strong pawn structure",
+    "White has a very strong pawn structure",
+    "Black has a very strong pawn structure",
+    "White has poor knight placement",
+    "Black has poor knight placement",
+    "White has good knight placement",
+    "Black has good knight placement",
+    "White has poor bishop placement",
+    "Black has poor bishop placement",
+    "White has good bishop placement",
+    "Black has good bishop placement",
+    "White has poor rook placement",
+    "Black has poor rook placement",
+    "White has good rook placement",
+    "Black has good rook placement",
+    "White has poor queen placement",
+    "Black has poor queen placement",
+    "White has good queen placement",
+    "Black has good queen placement",
+    "White has poor piece coordination",
+    "Black has poor piece coordination",
+    "White has good piece coordination",
+    "Black has good piece coordination",
+    "White has played the opening very poorly",
+    "Black has played the opening very poorly",
+    "White has played the opening poorly",
+    "Black has played the opening poorly",
+    "White has played the opening well",
+    "Black has played the opening well",
+    "White has played the opening very well",
+    "Black has played the opening very well",
+    "White has played the middlegame very poorly",
+    "Black has played the middlegame very poorly",
+    "White has played the middlegame poorly",
+    "Black has played the middlegame poorly",
+    "White has played the middlegame well",
+    "Black has played the middlegame well",
+    "White has played the middlegame very well",
+    "Black has played the middlegame very well",
+    "White has played the ending very poorly",
+    "Black has played the ending very poorly",
+    "White has played the ending poorly",
+    "Black has played the ending poorly",
+    "White has played the ending well",
+    "Black has played the ending well",
+    "White has played the ending very well",
+    "Black has played the ending very well",
+    "White has slight counterplay",
+    "Black has slight counterplay",
+    "White has moderate counterplay",
+    "Black has moderate counterplay",
+    "White has decisive counterplay",
+    "Black has decisive counterplay",
+    "White has moderate time control pressure",
+    "Black has moderate time control pressure",
+    "White has severe time control pressure",
+    "Black has severe time control pressure",
+];
+
+/// Returns the description of a standard NAG (`$0` to `$139`).
+///
+/// # Arguments
+///
+/// * `nag` - the NAG, without the `$`
+///
+/// # Examples
+///
+/// ```
+/// assert_eq!(sacrifice::game::nag_gloss(14), Some("White has a slight advantage"));
+/// assert_eq!(sacrifice::game::nag_gloss(146), None);
+/// ```
+pub fn nag_gloss(nag: u8) -> Option<&'static str> {
+    NAG_GLOSSES.get(usize::from(nag)).copied()
+}
@@ -0,0 +1,180 @@
+use super::variant::{parse_variant, position_from_fen, variant_tag};
+use super::{Game, GameResult, Header, Node};
+use crate::{Variant, VariantPosition};
+
+use std::collections::HashMap;
+
+/// Error from building a game with [`GameBuilder`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BuildError {
+    /// The starting FEN is invalid or not a legal chess position
+    InvalidFen(String),
+    /// The move at `index` of the move list is malformed or illegal
+    IllegalMove { index: usize, token: String },
+}
+
+impl std::fmt::Display for BuildError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BuildError::InvalidFen(fen) => write!(f, "invalid fen {}", fen),
+            BuildError::IllegalMove { index, token } => {
+                write!(f, "illegal move {} at index {}", token, index)
+            }
+        }
+    }
+}
+
+impl std::error::Error for BuildError {}
+
+/// Builds a [`Game`] from headers, a starting position and a main line.
+///
+/// # Examples
+///
+/// ```
+/// use sacrifice::game::{GameBuilder, GameResult};
+///
+/// let game = GameBuilder::new()
+///     .event("Casual game")
+///     .white("Anderssen")
+///     .black("Kieseritzky")
+///     .date("1851.06.21")
+///     .result(GameResult::WhiteWins)
+///     .tag("ECO", "C33")
+///     .moves(["e4", "e5", "f4", "exf4"])
+///     .build()
+///     .unwrap();
+/// assert_eq!(game.header.white.as_deref(), Some("Anderssen"));
+/// assert_eq!(game.opt_headers.get("ECO").map(String::as_str), Some("C33"));
+/// assert_eq!(game.movetext(), "1. e4 e5 2. f4 exf4");
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct GameBuilder {
+    header: Header,
+    opt_header_map: HashMap<String, String>,
+    variant: Option<Variant>,
+    fen: Option<String>,
+    move_vec: Vec<String>,
+}
+
+impl GameBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn event(mut self, event: impl Into<String>) -> Self {
+        self.header.event = Some(event.into());
+        self
+    }
+
+    pub fn site(mut self, site: impl Into<String>) -> Self {
+        self.header.site = Some(site.into());
+        self
+    }
+
+    /// Sets the date, written as `YYYY.MM.DD` with `??` for unknown parts.
+    pub fn date(mut self, date: impl Into<String>) -> Self {
+        self.header.date = Some(date.into());
+        self
+    }
+
+    pub fn round(mut self, round: impl Into<String>) -> Self {
+        self.header.round = Some(round.into());
+        self
+    }
+
+    pub fn white(mut self, white: impl Into<String>) -> Self {
+        self.header.white = Some(white.into());
+        self
+    }
+
+    pub fn black(mut self, black: impl Into<String>) -> Self {
+        self.header.black = Some(black.into());
+        self
+    }
+
+    pub fn result(mut self, result: GameResult) -> Self {
+        self.header.result = result;
+        self
+    }
+
+    /// Sets any tag; the Seven Tag Roster goes to the [`Header`].
+    pub fn tag(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        let (key, value) = (key.into(), value.into());
+        if !self.header.parse(&key, &value) {
+            self.opt_header_map.insert(key, value);
+        }
+        self
+    }
+
+    /// Sets the variant, recorded in the `Variant` header. By default it
+    /// is read from a `Variant` tag, or else standard chess.
+    pub fn variant(mut self, variant: Variant) -> Self {
+        self.variant = Some(variant);
+        self
+    }
+
+    /// Sets the starting position, recorded as given in the `SetUp` and
+    /// `FEN` headers.
+    pub fn fen(mut self, fen: impl Into<String>) -> Self {
+        self.fen = Some(fen.into());
+        self
+    }
+
+    /// Appends moves to the main line, in SAN (`Nf3`) or UCI (`g1f3`)
+    /// notation.
+    pub fn moves<S: Into<String>>(mut self, moves: impl IntoIterator<Item = S>) -> Self {
+        self.move_vec.extend(moves.into_iter().map(Into::into));
+        self
+    }
+
+    /// Returns the game, or an error if the FEN or a move is invalid.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sacrifice::game::{BuildError, GameBuilder};
+    ///
+    /// let error = GameBuilder::new()
+    ///     .fen("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1")
+    ///     .moves(["e4", "Ke7", "e4"])
+    ///     .build()
+    ///     .unwrap_err();
+    /// assert_eq!(error, BuildError::IllegalMove { index: 2, token: "e4".to_string() });
+    /// ```
+    pub fn build(self) -> Result<Game, BuildError> {
+        let mut game = Game {
+            header: self.header,
+            opt_headers: self.opt_header_map,
+            ..Game::default()
+        };
+
+        let variant = self
+            .variant
+            .or_else(|| parse_variant(game.opt_headers.get("Variant")?))
+            .unwrap_or_default();
+        if let Some(val) = variant_tag(variant) {
+            game.opt_headers
+                .insert("Variant".to_string(), val.to_string());
+        }
+        game.root = Node::from_position(VariantPosition::new(variant));
+
+        if let Some(fen) = self.fen {
+            let (position, _) = position_from_fen(variant, &fen)
+                .ok_or_else(|| BuildError::InvalidFen(fen.clone()))?;
+            game.root = Node::from_position(position);
+            game.opt_headers
+                .insert("SetUp".to_string(), "1".to_string());
+            game.opt_headers.insert("FEN".to_string(), fen);
+        }
+
+        let mut node = game.root();
+        for (index, token) in self.move_vec.into_iter().enumerate() {
+            node = node
+                .parse_move(&token)
+                .and_then(|m| node.new_variation(m))
+                .ok_or(BuildError::IllegalMove { index, token })?;
+        }
+
+        Ok(game)
+    }
+}
@@ -0,0 +1,309 @@
+use super::{Comment, Eval, Game, Node};
+use crate::pgn::command::format_eval;
+use crate::{Color, Position, SanPlus};
+
+/// How much a move worsened the position of the player who made it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum MoveError {
+    Inaccuracy,
+    Mistake,
+    Blunder,
+}
+
+impl MoveError {
+    /// Returns the NAG of the error: `$6` (?!), `$2` (?) or `$4` (??).
+    pub fn nag(self) -> u8 {
+        match self {
+            MoveError::Inaccuracy => 6,
+            MoveError::Mistake => 2,
+            MoveError::Blunder => 4,
+        }
+    }
+}
+
+/// Returns the symbol of an advantage NAG from [`Eval::advantage_nag`].
+fn advantage_symbol(nag: u8) -> &'static str {
+    match nag {
+        14 => "\u{2a72}",
+        15 => "\u{2a71}",
+        16 => "\u{b1}",
+        17 => "\u{2213}",
+        18 => "+\u{2212}",
+        19 => "\u{2212}+",
+        _ => "=",
+    }
+}
+
+impl std::fmt::Display for MoveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            MoveError::Inaccuracy => "inaccuracy",
+            MoveError::Mistake => "mistake",
+            MoveError::Blunder => "blunder",
+        };
+        f.write_str(name)
+    }
+}
+
+/// How the loss of a move is measured.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LossMetric {
+    /// Drop in [winning chances](Eval::winning_chances), from 0 to 2
+    #[default]
+    WinningChances,
+    /// Drop in [centipawns](Eval::clamped_centipawns), from 0 to 2000
+    Centipawns,
+}
+
+/// Smallest losses that make a move an inaccuracy, a mistake or a
+/// blunder.
+///
+/// The default is what lichess uses: a drop in winning chances of 0.1,
+/// 0.2 and 0.3.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClassifyOptions {
+    pub metric: LossMetric,
+    pub inaccuracy: f64,
+    pub mistake: f64,
+    pub blunder: f64,
+}
+
+impl Default for ClassifyOptions {
+    fn default() -> Self {
+        Self {
+            metric: LossMetric::WinningChances,
+            inaccuracy: 0.1,
+            mistake: 0.2,
+            blunder: 0.3,
+        }
+    }
+}
+
+impl ClassifyOptions {
+    /// Returns thresholds of 50, 100 and 300 centipawns.
+    pub fn centipawns() -> Self {
+        Self {
+            metric: LossMetric::Centipawns,
+            inaccuracy: 50.0,
+            mistake: 100.0,
+            blunder: 300.0,
+        }
+    }
+
+    /// Returns the error of a move with the given loss, if any.
+    pub(crate) fn classify(&self, loss: f64) -> Option<MoveError> {
+        if loss >= self.blunder {
+            Some(MoveError::Blunder)
+        } else if loss >= self.mistake {
+            Some(MoveError::Mistake)
+        } else if loss >= self.inaccuracy {
+            Some(MoveError::Inaccuracy)
+        } else {
+            None
+        }
+    }
+}
+
+impl Node {
+    /// Returns how much the move to this node worsened the evaluation for
+    /// the player who made it, measured as given, or `None` if this node
+    /// or its parent has no evaluation.
+    ///
+    /// The loss is negative if the move improved the evaluation.
+    ///
+    /// # Arguments
+    ///
+    /// * `metric` - how the loss is measured
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sacrifice::game::LossMetric;
+    ///
+    /// let game = sacrifice::read_pgn("1. e4 { [%eval 0.3] } 1... f6 { [%eval 1.1] } *").unwrap();
+    /// let f6_node = game.root().mainline_iter().last().unwrap();
+    /// assert_eq!(f6_node.eval_loss(LossMetric::Centipawns), Some(80.0));
+    /// ```
+    pub fn eval_loss(&self, metric: LossMetric) -> Option<f64> {
+        let before = self.parent()?.eval()?;
+        let after = self.eval()?;
+        let value = |eval: Eval| match metric {
+            LossMetric::WinningChances => eval.winning_chances(),
+            LossMetric::Centipawns => f64::from(eval.clamped_centipawns()),
+        };
+
+        let loss = value(before) - value(after);
+        // The player who moved is the one to move before the move
+        Some(match self.parent()?.position().turn() {
+            Color::White => loss,
+            Color::Black => -loss,
+        })
+    }
+
+    /// Returns whether the move to this node is an inaccuracy, a mistake
+    /// or a blunder, judged by the evaluations of this node and its
+    /// parent.
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - the thresholds of each error
+    pub fn move_error(&self, options: &ClassifyOptions) -> Option<MoveError> {
+        options.classify(self.eval_loss(options.metric)?)
+    }
+}
+
+impl Game {
+    /// Marks every mainline move that is an inaccuracy, a mistake or a
+    /// blunder with `$6`, `$2` or `$4`, judged by the evaluations stored
+    /// on the nodes, e.g. by [`Game::analyze_all`]. Error NAGs already on
+    /// the moves are replaced. Moves without an evaluation before and
+    /// after them are left as they are.
+    ///
+    /// Returns the marked nodes with their errors, in order.
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - the thresholds of each error
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sacrifice::game::{ClassifyOptions, MoveError};
+    ///
+    /// let game = sacrifice::read_pgn(
+    ///     "1. e4 { [%eval 0.3] } 1... f6 { [%eval 0.9] } 2. d4 { [%eval 0.8] } 2... g5 { [%eval #1] } *"
+    /// ).unwrap();
+    /// let error_vec = game.annotate_errors(&ClassifyOptions::default());
+    /// assert_eq!(error_vec.len(), 2);
+    /// assert_eq!(error_vec[0].1, MoveError::Inaccuracy);
+    /// assert_eq!(error_vec[1].1, MoveError::Blunder);
+    /// assert!(error_vec[1].0.nags().unwrap().contains(&4));
+    /// ```
+    pub fn annotate_errors(&self, options: &ClassifyOptions) -> Vec<(Node, MoveError)> {
+        let mut error_vec = Vec::new();
+        for mut node in self.root().mainline_iter() {
+            let loss = if let Some(val) = node.eval_loss(options.metric) {
+                val
+            } else {
+                continue;
+            };
+
+            let mut nags = node.nags().unwrap_or_default();
+            let error_nags = [
+                MoveError::Inaccuracy,
+                MoveError::Mistake,
+                MoveError::Blunder,
+            ]
+            .map(MoveError::nag);
+            nags.retain(|nag| !error_nags.contains(nag));
+            if let Some(error) = options.classify(loss) {
+                nags.insert(error.nag());
+                error_vec.push((node.clone(), error));
+            }
+            if Some(&nags) != node.nags().as_ref() {
+                node.set_nags(nags);
+            }
+        }
+        error_vec
+    }
+}
+
+impl Game {
+    /// Marks the mainline errors as [`Game::annotate_errors`] does, then
+    /// adds the line the engine preferred before each error as a variation
+    /// under the move's parent, with a comment on the move such as
+    /// `Mistake. Better was 14.Nxe5 ± +1.3`.
+    ///
+    /// The preferred line is the principal variation stored with the
+    /// parent's evaluation, e.g. by [`Game::analyze_all`]; errors without
+    /// one only get their NAG. Lines already in the tree are reused.
+    ///
+    /// Returns the marked nodes with their errors, in order.
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - the thresholds of each error
+    /// * `max_plies` - the most moves of each preferred line to add
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sacrifice::game::ClassifyOptions;
+    /// use sacrifice::San;
+    ///
+    /// let game = sacrifice::read_pgn("1. e4 { [%eval 0.3] } 1... g5 { [%eval 1.6] } *").unwrap();
+    /// let mut e4_node = game.root().mainline().unwrap();
+    /// let mut info = e4_node.eval_info().unwrap();
+    /// let c5: San = "c5".parse().unwrap();
+    /// info.pv = vec![c5.to_move(&e4_node.position()).unwrap()];
+    /// e4_node.set_eval_info(Some(info));
+    ///
+    /// game.annotate_best_lines(&ClassifyOptions::default(), 1);
+    /// assert_eq!(
+    ///     game.movetext(),
+    ///     "1. e4 { [%eval 0.30] } 1... g5 $2 { [%eval 1.60] Mistake. Better was 1...c5 \u{2a72} +0.3 } ( 1... c5 )"
+    /// );
+    /// ```
+    pub fn annotate_best_lines(
+        &self,
+        options: &ClassifyOptions,
+        max_plies: usize,
+    ) -> Vec<(Node, MoveError)> {
+        let error_vec = self.annotate_errors(options);
+        for (node, error) in &error_vec {
+            let parent = node.parent().expect("a move has a parent");
+            let info = if let Some(val) = parent.eval_info().filter(|info| !info.pv.is_empty()) {
+                val
+            } else {
+                continue;
+            };
+            let best_move = &info.pv[0];
+            if node.prev_move().as_ref() == Some(best_move) {
+                continue;
+            }
+
+            let position = parent.position();
+            let number = position.fullmoves();
+            let dots = match position.turn() {
+                Color::White => ".",
+                Color::Black => "...",
+            };
+            let eval = match info.eval {
+                Eval::Centipawns(cp) if cp > 0 => format!("+{}", format_eval(info.eval, 1)),
+                _ => format_eval(info.eval, 1),
+            };
+            let text = format!(
+                "{}. Better was {}{}{} {} {}",
+                capitalize(&error.to_string()),
+                number,
+                dots,
+                SanPlus::from_move(position, best_move),
+                advantage_symbol(info.eval.advantage_nag()),
+                eval
+            );
+
+            let mut line_node = parent.clone();
+            for m in info.pv.iter().take(max_plies.max(1)) {
+                line_node = if let Some(val) = line_node.new_variation(m.clone()) {
+                    val
+                } else {
+                    break;
+                };
+            }
+
+            let mut node = node.clone();
+            if !node.comments().iter().any(|comment| comment.text == text) {
+                node.push_comment(Comment::after_move(text));
+            }
+        }
+        error_vec
+    }
+}
+
+fn capitalize(text: &str) -> String {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
@@ -0,0 +1,47 @@
+/// Where a comment appears relative to the move of its node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CommentPlacement {
+    /// Before the move, e.g. the comment that starts a variation
+    BeforeMove,
+    /// After the move (and its NAGs)
+    AfterMove,
+}
+
+/// A single `{ ... }` comment attached to a node.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Comment {
+    pub text: String,
+    pub placement: CommentPlacement,
+}
+
+impl Comment {
+    pub fn before_move(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            placement: CommentPlacement::BeforeMove,
+        }
+    }
+
+    pub fn after_move(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            placement: CommentPlacement::AfterMove,
+        }
+    }
+}
+
+/// Joins the text of all comments with the given placement.
+pub(crate) fn join_comments(comments: &[Comment], placement: CommentPlacement) -> Option<String> {
+    let text_vec = comments
+        .iter()
+        .filter(|c| c.placement == placement)
+        .map(|c| c.text.as_str())
+        .collect::<Vec<&str>>();
+    if text_vec.is_empty() {
+        return None;
+    }
+
+    Some(text_vec.join(" "))
+}
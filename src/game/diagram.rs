@@ -0,0 +1,124 @@
+use super::Node;
+use crate::{File, Position, Rank, Square, VariantPosition};
+
+/// A text diagram of a position, one rank per line.
+///
+/// # Examples
+///
+/// ```
+/// use sacrifice::game::BoardDiagram;
+///
+/// let diagram = BoardDiagram {
+///     unicode: true,
+///     ..BoardDiagram::new(sacrifice::Chess::default())
+/// };
+/// assert!(diagram.to_string().starts_with("8 ♜ ♞ ♝ ♛ ♚ ♝ ♞ ♜\n"));
+/// ```
+#[derive(Debug, Clone)]
+pub struct BoardDiagram {
+    pub position: VariantPosition,
+
+    /// Use chess piece symbols instead of letters
+    pub unicode: bool,
+    /// Label the ranks and files
+    pub coordinates: bool,
+    /// Draw the board from Black's side
+    pub flipped: bool,
+}
+
+impl BoardDiagram {
+    /// Returns a letter diagram with coordinates, from White's side.
+    pub fn new(position: impl Into<VariantPosition>) -> Self {
+        Self {
+            position: position.into(),
+
+            unicode: false,
+            coordinates: true,
+            flipped: false,
+        }
+    }
+}
+
+impl std::fmt::Display for BoardDiagram {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let board = self.position.board();
+
+        let mut rank_vec: Vec<Rank> = Rank::ALL.into_iter().rev().collect();
+        let mut file_vec: Vec<File> = File::ALL.into_iter().collect();
+        if self.flipped {
+            rank_vec.reverse();
+            file_vec.reverse();
+        }
+
+        for rank in rank_vec {
+            if self.coordinates {
+                write!(f, "{} ", rank.char())?;
+            }
+
+            let square_vec = file_vec
+                .iter()
+                .map(|&file| {
+                    let piece = board.piece_at(Square::from_coords(file, rank));
+                    match piece {
+                        Some(piece) if self.unicode => unicode_symbol(piece),
+                        Some(piece) => piece.char(),
+                        None => '.',
+                    }
+                })
+                .map(String::from)
+                .collect::<Vec<String>>();
+            writeln!(f, "{}", square_vec.join(" "))?;
+        }
+
+        if self.coordinates {
+            let file_vec = file_vec
+                .iter()
+                .map(|file| file.char().to_string())
+                .collect::<Vec<String>>();
+            writeln!(f, "  {}", file_vec.join(" "))?;
+        }
+
+        Ok(())
+    }
+}
+
+pub(crate) fn unicode_symbol(piece: crate::Piece) -> char {
+    use crate::{Color, Role};
+
+    match (piece.color, piece.role) {
+        (Color::White, Role::King) => '♔',
+        (Color::White, Role::Queen) => '♕',
+        (Color::White, Role::Rook) => '♖',
+        (Color::White, Role::Bishop) => '♗',
+        (Color::White, Role::Knight) => '♘',
+        (Color::White, Role::Pawn) => '♙',
+        (Color::Black, Role::King) => '♚',
+        (Color::Black, Role::Queen) => '♛',
+        (Color::Black, Role::Rook) => '♜',
+        (Color::Black, Role::Bishop) => '♝',
+        (Color::Black, Role::Knight) => '♞',
+        (Color::Black, Role::Pawn) => '♟',
+    }
+}
+
+impl Node {
+    /// Returns a diagram of the given node's position, which can be
+    /// adjusted before printing.
+    pub fn diagram(&self) -> BoardDiagram {
+        BoardDiagram::new(self.position())
+    }
+
+    /// Returns the given node's position as a letter diagram.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let game = sacrifice::read_pgn("1. e4").unwrap();
+    /// let diagram = game.root().mainline().unwrap().board_ascii();
+    /// assert_eq!(diagram.lines().nth(4).unwrap(), "4 . . . . P . . .");
+    /// assert_eq!(diagram.lines().last().unwrap(), "  a b c d e f g h");
+    /// ```
+    pub fn board_ascii(&self) -> String {
+        self.diagram().to_string()
+    }
+}
@@ -0,0 +1,186 @@
+use super::Node;
+use crate::{Outcome, Position};
+
+/// Why a position is drawn, see [`Node::draw_reason`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DrawReason {
+    /// The side to move has no legal move and is not in check
+    Stalemate,
+    /// Neither side has enough material to checkmate
+    InsufficientMaterial,
+    /// The position occurred for the fifth time
+    FivefoldRepetition,
+    /// Seventy-five moves by each side without a capture or pawn move
+    SeventyFiveMoveRule,
+    /// The position occurred for the third time; a draw only if claimed
+    ThreefoldRepetition,
+    /// Fifty moves by each side without a capture or pawn move; a draw
+    /// only if claimed
+    FiftyMoveRule,
+    /// Drawn by a rule of the variant, such as both kings reaching the
+    /// last rank in Racing Kings
+    VariantDraw,
+}
+
+impl DrawReason {
+    /// Returns whether the game is only drawn if a player claims it.
+    pub fn is_claimable(&self) -> bool {
+        matches!(self, Self::ThreefoldRepetition | Self::FiftyMoveRule)
+    }
+}
+
+impl std::fmt::Display for DrawReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DrawReason::Stalemate => write!(f, "stalemate"),
+            DrawReason::InsufficientMaterial => write!(f, "insufficient material"),
+            DrawReason::FivefoldRepetition => write!(f, "fivefold repetition"),
+            DrawReason::SeventyFiveMoveRule => write!(f, "seventy-five-move rule"),
+            DrawReason::ThreefoldRepetition => write!(f, "threefold repetition"),
+            DrawReason::FiftyMoveRule => write!(f, "fifty-move rule"),
+            DrawReason::VariantDraw => write!(f, "variant draw"),
+        }
+    }
+}
+
+impl Node {
+    /// Returns how many times the position of the given node has occurred
+    /// on the way from the root to it, counting the node itself.
+    ///
+    /// Positions are the same if they have the same pieces, side to move,
+    /// castling rights and legal en passant captures. Only positions since
+    /// the last capture or pawn move are compared.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let game = sacrifice::read_pgn("1. Nf3 Nf6 2. Ng1 Ng8 3. Nf3 Nf6 4. Ng1 Ng8").unwrap();
+    /// let node_vec: Vec<_> = game.root().mainline_iter().collect();
+    /// assert_eq!(game.root().repetition_count(), 1);
+    /// assert_eq!(node_vec[3].repetition_count(), 2); // 2... Ng8
+    /// assert_eq!(node_vec[5].repetition_count(), 2); // 3... Nf6
+    /// assert_eq!(node_vec[7].repetition_count(), 3); // 4... Ng8
+    /// ```
+    pub fn repetition_count(&self) -> usize {
+        let hash = self.zobrist();
+
+        let mut count = 1;
+        let mut node = self.clone();
+        for _ in 0..self.halfmove_clock() {
+            node = if let Some(val) = node.parent() {
+                val
+            } else {
+                break;
+            };
+            if node.zobrist() == hash {
+                count += 1;
+            }
+        }
+
+        count
+    }
+
+    /// Returns whether the position of the given node has occurred at
+    /// least three times, so that a draw can be claimed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let game = sacrifice::read_pgn("1. Nf3 Nf6 2. Ng1 Ng8 3. Nf3 Nf6 4. Ng1 Ng8").unwrap();
+    /// let last_node = game.root().mainline_iter().last().unwrap();
+    /// assert!(last_node.is_threefold_repetition());
+    /// assert!(!last_node.parent().unwrap().is_threefold_repetition());
+    /// ```
+    pub fn is_threefold_repetition(&self) -> bool {
+        self.repetition_count() >= 3
+    }
+
+    /// Returns the number of half-moves since the last capture or pawn
+    /// move, as in FEN.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let game = sacrifice::read_pgn("1. e4 e5 2. Nf3 Nc6 3. Bb5").unwrap();
+    /// let bb5_node = game.root().mainline_iter().last().unwrap();
+    /// assert_eq!(bb5_node.halfmove_clock(), 3);
+    /// ```
+    pub fn halfmove_clock(&self) -> u32 {
+        self.position().halfmoves()
+    }
+
+    /// Returns whether fifty moves by each side have passed without a
+    /// capture or pawn move, so that a draw can be claimed. A checkmate
+    /// on the last of these moves still wins.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let game = sacrifice::read_pgn(
+    ///     "[SetUp \"1\"]\n[FEN \"7k/8/8/8/8/8/8/KR6 w - - 99 80\"]\n\n80. Rb2 *"
+    /// ).unwrap();
+    /// assert!(game.root().mainline().unwrap().is_fifty_move_draw());
+    /// assert!(!game.root().is_fifty_move_draw());
+    /// ```
+    pub fn is_fifty_move_draw(&self) -> bool {
+        let position = self.position();
+        position.halfmoves() >= 100 && !position.is_checkmate()
+    }
+
+    /// Returns whether neither side has enough material left to
+    /// checkmate, whatever the moves.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let game = sacrifice::read_pgn(
+    ///     "[SetUp \"1\"]\n[FEN \"8/8/4k3/8/3n4/8/4K3/8 w - - 0 1\"]\n\n*"
+    /// ).unwrap();
+    /// assert!(game.root().is_insufficient_material());
+    /// ```
+    pub fn is_insufficient_material(&self) -> bool {
+        self.position().is_insufficient_material()
+    }
+
+    /// Returns why the game is drawn at the given node, or `None` if it is
+    /// not. Draws that happen by themselves come before those that must
+    /// be claimed; a checkmate or a win by the rules of the variant is
+    /// never a draw.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sacrifice::game::DrawReason;
+    ///
+    /// let game = sacrifice::read_pgn("1. Nf3 Nf6 2. Ng1 Ng8 3. Nf3 Nf6 4. Ng1 Ng8").unwrap();
+    /// let last_node = game.root().mainline_iter().last().unwrap();
+    /// assert_eq!(last_node.draw_reason(), Some(DrawReason::ThreefoldRepetition));
+    /// assert!(last_node.draw_reason().unwrap().is_claimable());
+    /// assert_eq!(game.root().draw_reason(), None);
+    /// ```
+    pub fn draw_reason(&self) -> Option<DrawReason> {
+        let position = self.position();
+        match position.variant_outcome() {
+            Some(Outcome::Draw) => return Some(DrawReason::VariantDraw),
+            Some(Outcome::Decisive { .. }) => return None,
+            None if position.is_checkmate() => return None,
+            None => {}
+        }
+
+        if position.is_stalemate() {
+            Some(DrawReason::Stalemate)
+        } else if position.is_insufficient_material() {
+            Some(DrawReason::InsufficientMaterial)
+        } else if self.repetition_count() >= 5 {
+            Some(DrawReason::FivefoldRepetition)
+        } else if position.halfmoves() >= 150 {
+            Some(DrawReason::SeventyFiveMoveRule)
+        } else if self.repetition_count() >= 3 {
+            Some(DrawReason::ThreefoldRepetition)
+        } else if position.halfmoves() >= 100 {
+            Some(DrawReason::FiftyMoveRule)
+        } else {
+            None
+        }
+    }
+}
@@ -0,0 +1,88 @@
+use super::{Game, Node};
+
+/// A change to the game tree, passed to the listeners added with
+/// [`Game::subscribe`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum TreeEvent {
+    /// A node was added as a child of its parent
+    NodeAdded(Node),
+    /// A node, with everything after it, was removed from its parent
+    NodeRemoved(Node),
+    /// A node became the mainline continuation of its parent
+    NodePromoted(Node),
+    /// The variations of the node changed order; its mainline is the same
+    VariationsReordered(Node),
+    /// The comments of the node changed
+    CommentChanged(Node),
+    /// The NAGs of the node changed
+    NagsChanged(Node),
+}
+
+/// A callback for tree changes.
+#[cfg(not(feature = "sync"))]
+pub trait Listener: FnMut(&TreeEvent) + 'static {}
+
+#[cfg(not(feature = "sync"))]
+impl<F: FnMut(&TreeEvent) + 'static> Listener for F {}
+
+/// A callback for tree changes; shared between threads with the tree.
+#[cfg(feature = "sync")]
+pub trait Listener: FnMut(&TreeEvent) + Send + Sync + 'static {}
+
+#[cfg(feature = "sync")]
+impl<F: FnMut(&TreeEvent) + Send + Sync + 'static> Listener for F {}
+
+/// Identifies a listener added with [`Game::subscribe`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ListenerId(pub(crate) u64);
+
+// Change notifications
+impl Game {
+    /// Adds a listener that is called after every change to the tree,
+    /// through any of its nodes, including undo and redo.
+    ///
+    /// Listeners may read the tree, but changes they make to it are not
+    /// reported. Deep clones start without listeners.
+    ///
+    /// # Arguments
+    ///
+    /// * `listener` - the callback
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sacrifice::game::TreeEvent;
+    /// use std::sync::mpsc;
+    ///
+    /// let game = sacrifice::read_pgn("1. e4 e5 *").unwrap();
+    /// let (sender, receiver) = mpsc::channel();
+    /// game.subscribe(move |event: &TreeEvent| sender.send(event.clone()).unwrap());
+    ///
+    /// let mut root = game.root();
+    /// let d4_node = root.play_san("d4").unwrap();
+    /// root.promote_variation(d4_node.clone());
+    /// d4_node.set_comment(Some("Queen's pawn".to_string()));
+    /// assert_eq!(
+    ///     receiver.try_iter().collect::<Vec<TreeEvent>>(),
+    ///     vec![
+    ///         TreeEvent::NodeAdded(d4_node.clone()),
+    ///         TreeEvent::NodePromoted(d4_node.clone()),
+    ///         TreeEvent::CommentChanged(d4_node),
+    ///     ]
+    /// );
+    /// ```
+    pub fn subscribe(&self, listener: impl Listener) -> ListenerId {
+        self.root.add_listener(Box::new(listener))
+    }
+
+    /// Removes a listener added with [`Game::subscribe`].
+    ///
+    /// Returns whether the listener was found.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - the id returned by [`Game::subscribe`]
+    pub fn unsubscribe(&self, id: ListenerId) -> bool {
+        self.root.remove_listener(id)
+    }
+}
@@ -1,36 +1,52 @@
 use super::writer::{PartialAcceptor, Visitor};
+use crate::{Color, Outcome};
 
-#[derive(Debug, Clone)]
+/// The `Result` tag of a game.
+///
+/// # Examples
+///
+/// ```
+/// use sacrifice::game::GameResult;
+///
+/// assert_eq!(GameResult::from("1/2-1/2"), GameResult::Draw);
+/// assert_eq!(GameResult::BlackWins.to_string(), "0-1");
+/// assert_eq!(GameResult::from("2-0"), GameResult::Ongoing); // Unrecognized
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
 pub enum GameResult {
-    Finished { white_score: u32, black_score: u32 },
+    /// `1-0`
+    WhiteWins,
+    /// `0-1`
+    BlackWins,
+    /// `1/2-1/2`
+    Draw,
+    /// `*`, the game is ongoing, abandoned or its result unknown
+    #[default]
     Ongoing,
 }
 
 impl From<&str> for GameResult {
+    /// Parses a result; anything unrecognized is `Ongoing`.
     fn from(value: &str) -> Self {
-        if value == "*" {
-            return Self::Ongoing;
-        }
-
-        let vec = value.split("-").collect::<Vec<&str>>();
-        if vec.len() != 2 {
-            return Self::Ongoing;
+        match value.trim() {
+            "1-0" => Self::WhiteWins,
+            "0-1" => Self::BlackWins,
+            "1/2-1/2" | "\u{bd}-\u{bd}" => Self::Draw,
+            _ => Self::Ongoing,
         }
+    }
+}
 
-        let white_score = if let Ok(val) = vec[0].parse::<u32>() {
-            val
-        } else {
-            return Self::Ongoing;
-        };
-        let black_score = if let Ok(val) = vec[1].parse::<u32>() {
-            val
-        } else {
-            return Self::Ongoing;
-        };
-
-        Self::Finished {
-            white_score,
-            black_score,
+impl From<Outcome> for GameResult {
+    fn from(outcome: Outcome) -> Self {
+        match outcome {
+            Outcome::Decisive {
+                winner: Color::White,
+            } => Self::WhiteWins,
+            Outcome::Decisive {
+                winner: Color::Black,
+            } => Self::BlackWins,
+            Outcome::Draw => Self::Draw,
         }
     }
 }
@@ -38,15 +54,137 @@ impl From<&str> for GameResult {
 impl std::fmt::Display for GameResult {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            GameResult::Finished {
-                white_score,
-                black_score,
-            } => write!(f, "{}-{}", white_score, black_score),
+            GameResult::WhiteWins => write!(f, "1-0"),
+            GameResult::BlackWins => write!(f, "0-1"),
+            GameResult::Draw => write!(f, "1/2-1/2"),
             GameResult::Ongoing => write!(f, "*"),
         }
     }
 }
 
+/// Error from parsing a typed header value such as a [`Date`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidHeaderValue(pub String);
+
+impl std::fmt::Display for InvalidHeaderValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "invalid header value {}", self.0)
+    }
+}
+
+impl std::error::Error for InvalidHeaderValue {}
+
+/// A PGN date, `YYYY.MM.DD`, where any part may be unknown (`??`).
+///
+/// Dates compare by year, then month, then day; unknown parts come first.
+///
+/// # Examples
+///
+/// ```
+/// use sacrifice::game::Date;
+///
+/// let date: Date = "1972.07.??".parse().unwrap();
+/// assert_eq!(date.year, Some(1972));
+/// assert_eq!(date.day, None);
+/// assert_eq!(date.to_string(), "1972.07.??");
+/// assert!(date < "1972.08.01".parse().unwrap());
+/// assert!("1972.13.01".parse::<Date>().is_err());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Date {
+    pub year: Option<u16>,
+    pub month: Option<u8>,
+    pub day: Option<u8>,
+}
+
+/// Parses one part of a date, `None` if it is unknown.
+fn parse_date_part<T: std::str::FromStr + PartialOrd>(
+    part: &str,
+    width: usize,
+    range: std::ops::RangeInclusive<T>,
+) -> Result<Option<T>, ()> {
+    if part.len() != width {
+        return Err(());
+    }
+    if part.chars().all(|c| c == '?') {
+        return Ok(None);
+    }
+    match part.parse::<T>() {
+        Ok(val) if range.contains(&val) => Ok(Some(val)),
+        _ => Err(()),
+    }
+}
+
+impl std::str::FromStr for Date {
+    type Err = InvalidHeaderValue;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = |_| InvalidHeaderValue(s.to_string());
+
+        let part_vec = s.split('.').collect::<Vec<&str>>();
+        if part_vec.len() != 3 {
+            return Err(InvalidHeaderValue(s.to_string()));
+        }
+        Ok(Self {
+            year: parse_date_part(part_vec[0], 4, 0..=9999).map_err(invalid)?,
+            month: parse_date_part(part_vec[1], 2, 1..=12).map_err(invalid)?,
+            day: parse_date_part(part_vec[2], 2, 1..=31).map_err(invalid)?,
+        })
+    }
+}
+
+impl std::fmt::Display for Date {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.year {
+            Some(year) => write!(f, "{:04}", year)?,
+            None => write!(f, "????")?,
+        }
+        for part in [self.month, self.day] {
+            match part {
+                Some(val) => write!(f, ".{:02}", val)?,
+                None => write!(f, ".??")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A PGN round, such as `3` or `3.1` for the first game of round 3.
+///
+/// Rounds compare part by part, numerically.
+///
+/// # Examples
+///
+/// ```
+/// use sacrifice::game::Round;
+///
+/// let round: Round = "3.1".parse().unwrap();
+/// assert_eq!(round, Round(vec![3, 1]));
+/// assert!(round < "10".parse().unwrap());
+/// assert!("-".parse::<Round>().is_err());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Round(pub Vec<u32>);
+
+impl std::str::FromStr for Round {
+    type Err = InvalidHeaderValue;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.split('.')
+            .map(|part| part.parse::<u32>())
+            .collect::<Result<Vec<u32>, _>>()
+            .map(Self)
+            .map_err(|_| InvalidHeaderValue(s.to_string()))
+    }
+}
+
+impl std::fmt::Display for Round {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let part_vec = self.0.iter().map(u32::to_string).collect::<Vec<String>>();
+        write!(f, "{}", part_vec.join("."))
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Header {
     pub event: Option<String>,
@@ -56,6 +194,12 @@ pub struct Header {
     pub white: Option<String>,
     pub black: Option<String>,
     pub result: GameResult,
+
+    /// `WhiteElo`; a value that is not a number is kept in the optional
+    /// headers as it is
+    pub white_elo: Option<u16>,
+    /// `BlackElo`, like `white_elo`
+    pub black_elo: Option<u16>,
 }
 
 impl Default for Header {
@@ -68,6 +212,9 @@ impl Default for Header {
             white: None,
             black: None,
             result: GameResult::Ongoing,
+
+            white_elo: None,
+            black_elo: None,
         }
     }
 }
@@ -103,11 +250,50 @@ impl Header {
             "White" => self.white = parse_header_value(value),
             "Black" => self.black = parse_header_value(value),
             "Result" => self.result = GameResult::from(value),
+            "WhiteElo" | "BlackElo" => {
+                let elo = if let Ok(val) = value.parse::<u16>() {
+                    val
+                } else {
+                    return false;
+                };
+                if key == "WhiteElo" {
+                    self.white_elo = Some(elo);
+                } else {
+                    self.black_elo = Some(elo);
+                }
+            }
             _ => return false,
         }
 
         true
     }
+
+    /// Returns the date as a [`Date`], or `None` if it is missing or not
+    /// a valid PGN date.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let game = sacrifice::read_pgn("[Date \"1858.??.??\"]\n\n*").unwrap();
+    /// assert_eq!(game.header.parsed_date().unwrap().year, Some(1858));
+    /// ```
+    pub fn parsed_date(&self) -> Option<Date> {
+        self.date.as_deref()?.parse().ok()
+    }
+
+    /// Returns the round as a [`Round`], or `None` if it is missing or not
+    /// numeric, e.g. `-`.
+    pub fn parsed_round(&self) -> Option<Round> {
+        self.round.as_deref()?.parse().ok()
+    }
+
+    /// Returns the typed tags outside the Seven Tag Roster that are set.
+    pub(crate) fn extra_tags(&self) -> Vec<(&'static str, String)> {
+        [("WhiteElo", self.white_elo), ("BlackElo", self.black_elo)]
+            .into_iter()
+            .filter_map(|(key, value)| Some((key, value?.to_string())))
+            .collect()
+    }
 }
 
 impl PartialAcceptor for Header {
@@ -0,0 +1,161 @@
+use super::Node;
+use crate::{Move, VariantPosition};
+
+use std::collections::VecDeque;
+
+/// Iterator over the successive mainline nodes below a node.
+///
+/// Returned by [`Node::mainline_iter`].
+#[derive(Debug, Clone)]
+pub struct MainlineIter {
+    node: Option<Node>,
+}
+
+impl Iterator for MainlineIter {
+    type Item = Node;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let next = self.node.as_ref()?.mainline();
+        self.node = next.clone();
+        next
+    }
+}
+
+/// Iterator over the mainline moves below a node, each with the position
+/// it is played in.
+///
+/// Returned by [`Node::mainline_moves`].
+#[derive(Debug, Clone)]
+pub struct MainlineMoves {
+    inner: MainlineIter,
+    position: VariantPosition,
+}
+
+impl Iterator for MainlineMoves {
+    type Item = (VariantPosition, Move);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = self.inner.next()?;
+        let position = std::mem::replace(&mut self.position, node.position());
+        Some((position, node.prev_move()?))
+    }
+}
+
+impl Node {
+    /// Returns an iterator over the mainline nodes following this node,
+    /// not including the node itself.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let game = sacrifice::read_pgn("1. e4 (1. d4) 1... e5 2. Nf3").unwrap();
+    /// let to_vec = game.root().mainline_iter()
+    ///     .map(|node| node.prev_move().unwrap().to())
+    ///     .collect::<Vec<_>>();
+    /// assert_eq!(to_vec, [sacrifice::Square::E4, sacrifice::Square::E5, sacrifice::Square::F3]);
+    /// ```
+    pub fn mainline_iter(&self) -> MainlineIter {
+        MainlineIter {
+            node: Some(self.clone()),
+        }
+    }
+
+    /// Returns an iterator over the mainline moves following this node,
+    /// each with the position before it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sacrifice::{Color, Position};
+    ///
+    /// let game = sacrifice::read_pgn("1. e4 e5 2. Nf3").unwrap();
+    /// let turn_vec = game.root().mainline_moves()
+    ///     .map(|(position, _)| position.turn())
+    ///     .collect::<Vec<_>>();
+    /// assert_eq!(turn_vec, [Color::White, Color::Black, Color::White]);
+    /// ```
+    pub fn mainline_moves(&self) -> MainlineMoves {
+        MainlineMoves {
+            inner: self.mainline_iter(),
+            position: self.position(),
+        }
+    }
+}
+
+/// Depth-first, pre-order iterator over the nodes below a node.
+///
+/// Returned by [`Node::descendants`].
+#[derive(Debug, Clone)]
+pub struct Descendants {
+    stack: Vec<Node>,
+}
+
+impl Iterator for Descendants {
+    type Item = Node;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = self.stack.pop()?;
+        // The mainline is visited before the other variations
+        self.stack.extend(node.variation_vec().into_iter().rev());
+        Some(node)
+    }
+}
+
+/// Breadth-first iterator over the nodes below a node.
+///
+/// Returned by [`Node::descendants_bfs`].
+#[derive(Debug, Clone)]
+pub struct DescendantsBfs {
+    queue: VecDeque<Node>,
+}
+
+impl Iterator for DescendantsBfs {
+    type Item = Node;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = self.queue.pop_front()?;
+        self.queue.extend(node.variation_vec());
+        Some(node)
+    }
+}
+
+impl Node {
+    /// Returns a depth-first iterator over every node below this node,
+    /// not including the node itself.
+    ///
+    /// Each node comes before its children, and the mainline before the
+    /// other variations, as in PGN order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let game = sacrifice::read_pgn("1. e4 (1. d4 d5) 1... e5").unwrap();
+    /// let to_vec = game.root().descendants()
+    ///     .map(|node| node.prev_move().unwrap().to().to_string())
+    ///     .collect::<Vec<_>>();
+    /// assert_eq!(to_vec, ["e4", "e5", "d4", "d5"]);
+    /// ```
+    pub fn descendants(&self) -> Descendants {
+        Descendants {
+            stack: self.variation_vec().into_iter().rev().collect(),
+        }
+    }
+
+    /// Returns a breadth-first iterator over every node below this node,
+    /// not including the node itself.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let game = sacrifice::read_pgn("1. e4 (1. d4 d5) 1... e5").unwrap();
+    /// let to_vec = game.root().descendants_bfs()
+    ///     .map(|node| node.prev_move().unwrap().to().to_string())
+    ///     .collect::<Vec<_>>();
+    /// assert_eq!(to_vec, ["e4", "d4", "e5", "d5"]);
+    /// ```
+    pub fn descendants_bfs(&self) -> DescendantsBfs {
+        DescendantsBfs {
+            queue: self.variation_vec().into(),
+        }
+    }
+}
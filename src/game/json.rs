@@ -0,0 +1,334 @@
+use super::variant::{parse_variant, position_from_fen};
+use super::{Comment, CommentPlacement, Eval, Game, Node};
+use crate::{Fen, Position, VariantPosition};
+
+use serde::{Deserialize, Serialize};
+use shakmaty::san::{San, SanPlus};
+use shakmaty::EnPassantMode;
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+/// Error from reading a game from JSON.
+#[derive(Debug)]
+pub enum JsonError {
+    /// The input is not JSON of the expected shape
+    Syntax(serde_json::Error),
+    /// The starting FEN is invalid or not a legal chess position
+    InvalidFen(String),
+    /// A move is malformed or illegal in its position
+    IllegalMove(String),
+    /// A clock is negative or too large to be a duration
+    InvalidClock(f64),
+}
+
+impl std::fmt::Display for JsonError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            JsonError::Syntax(err) => write!(f, "invalid json: {}", err),
+            JsonError::InvalidFen(fen) => write!(f, "invalid fen {}", fen),
+            JsonError::IllegalMove(san) => write!(f, "illegal move {}", san),
+            JsonError::InvalidClock(clock) => write!(f, "invalid clock {}", clock),
+        }
+    }
+}
+
+impl std::error::Error for JsonError {}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum JsonEval {
+    Cp(i32),
+    Mate(i32),
+}
+
+#[derive(Serialize, Deserialize)]
+struct JsonNode {
+    san: String,
+    /// Only informative when reading
+    #[serde(default)]
+    uci: String,
+    /// Position after the move; only informative when reading
+    #[serde(default)]
+    fen: String,
+
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    nags: Vec<u8>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    starting_comments: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    comments: Vec<String>,
+    /// Remaining clock time in seconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    clock: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    eval: Option<JsonEval>,
+
+    /// Alternatives to this move, each a line of moves
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    variations: Vec<Vec<JsonNode>>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct JsonGame {
+    #[serde(default)]
+    headers: BTreeMap<String, String>,
+    /// Starting position
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    fen: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    comments: Vec<String>,
+    /// The main line
+    #[serde(default)]
+    moves: Vec<JsonNode>,
+}
+
+fn to_fen(position: &VariantPosition) -> String {
+    Fen::from_position(position.clone(), EnPassantMode::Legal).to_string()
+}
+
+fn comment_texts(node: &Node, placement: CommentPlacement) -> Vec<String> {
+    node.comments()
+        .into_iter()
+        .filter(|c| c.placement == placement)
+        .map(|c| c.text)
+        .collect()
+}
+
+impl JsonNode {
+    /// Converts a node without its variations.
+    fn from_node(node: &Node, prev_position: &VariantPosition) -> Self {
+        let m = node.prev_move().unwrap();
+        let mut nags: Vec<u8> = node.nags().unwrap_or_default().into_iter().collect();
+        nags.sort_unstable();
+
+        Self {
+            san: SanPlus::from_move(prev_position.clone(), &m).to_string(),
+            uci: m.to_uci(prev_position.castles().mode()).to_string(),
+            fen: to_fen(&node.position()),
+
+            nags,
+            starting_comments: comment_texts(node, CommentPlacement::BeforeMove),
+            comments: comment_texts(node, CommentPlacement::AfterMove),
+            clock: node.clock().map(|d| d.as_secs_f64()),
+            eval: node.eval().map(|eval| match eval {
+                Eval::Centipawns(cp) => JsonEval::Cp(cp),
+                Eval::Mate(moves) => JsonEval::Mate(moves),
+            }),
+
+            variations: Vec::new(),
+        }
+    }
+
+    /// Converts the line starting at the given node, following its mainline.
+    fn from_line(first: &Node) -> Vec<Self> {
+        let mut line = Vec::new();
+
+        let mut node = first.clone();
+        loop {
+            let parent = node.parent().unwrap();
+            let position = parent.position();
+            let mut json_node = Self::from_node(&node, &position);
+            if Some(&node) == parent.variation_vec().first() {
+                json_node.variations = parent
+                    .other_variations()
+                    .iter()
+                    .map(Self::from_line)
+                    .collect();
+            }
+            line.push(json_node);
+
+            node = if let Some(val) = node.mainline() {
+                val
+            } else {
+                break;
+            };
+        }
+
+        line
+    }
+
+    fn add_line(line: Vec<Self>, parent: &Node) -> Result<(), JsonError> {
+        let mut parent = parent.clone();
+        for mut json_node in line {
+            let variations = std::mem::take(&mut json_node.variations);
+            let node = json_node.add_to(&mut parent)?;
+            for variation in variations {
+                Self::add_line(variation, &parent)?;
+            }
+            parent = node;
+        }
+
+        Ok(())
+    }
+
+    fn add_to(self, parent: &mut Node) -> Result<Node, JsonError> {
+        let illegal_move = || JsonError::IllegalMove(self.san.clone());
+        let m = self
+            .san
+            .parse::<San>()
+            .ok()
+            .and_then(|san| san.to_move(&parent.position()).ok())
+            .ok_or_else(illegal_move)?;
+        let clock = self
+            .clock
+            .map(|clock| {
+                Duration::try_from_secs_f64(clock).map_err(|_| JsonError::InvalidClock(clock))
+            })
+            .transpose()?;
+        let mut node = parent.push_variation(m).ok_or_else(illegal_move)?;
+
+        let comment_vec = self
+            .starting_comments
+            .into_iter()
+            .map(Comment::before_move)
+            .chain(self.comments.into_iter().map(Comment::after_move))
+            .collect();
+        node.set_comments(comment_vec);
+        node.set_nags(self.nags.into_iter().collect());
+        node.set_clock(clock);
+        node.set_eval(self.eval.map(|eval| match eval {
+            JsonEval::Cp(cp) => Eval::Centipawns(cp),
+            JsonEval::Mate(moves) => Eval::Mate(moves),
+        }));
+
+        Ok(node)
+    }
+}
+
+impl Game {
+    /// Returns the game as a JSON tree.
+    ///
+    /// The main line is a list of moves, and every move carries its SAN and
+    /// UCI, the FEN after the move, its NAGs and comments, and the
+    /// alternatives to it as lists of moves.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let game = sacrifice::read_pgn("[White \"Carlsen\"]\n\n1. e4 $1 { Best } (1. d4) *").unwrap();
+    /// let json = game.to_json();
+    /// assert!(json.contains(r#""White":"Carlsen""#));
+    /// assert!(json.contains(r#""san":"e4","uci":"e2e4""#));
+    /// assert!(json.contains(r#""nags":[1],"comments":["Best"]"#));
+    /// ```
+    pub fn to_json(&self) -> String {
+        let initial_position = self.initial_position();
+        let json_game = JsonGame {
+            headers: self.header_map(),
+            fen: (initial_position != VariantPosition::new(initial_position.variant()))
+                .then(|| to_fen(&initial_position)),
+            comments: self.root.comments().into_iter().map(|c| c.text).collect(),
+            moves: self
+                .root
+                .mainline()
+                .map_or_else(Vec::new, |node| JsonNode::from_line(&node)),
+        };
+
+        serde_json::to_string(&json_game).expect("serializing the tree never fails")
+    }
+
+    /// Reads a game from a JSON tree, as written by [`Game::to_json`].
+    ///
+    /// Moves are read from their SAN; the `uci` and `fen` fields are
+    /// optional and ignored.
+    ///
+    /// # Arguments
+    ///
+    /// * `json` - the JSON tree
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sacrifice::game::Game;
+    ///
+    /// let json = r#"{"moves": [{"san": "e4", "variations": [[{"san": "d4"}]]}, {"san": "c5"}]}"#;
+    /// let game = Game::from_json(json).unwrap();
+    /// assert_eq!(game.movetext(), "1. e4 ( 1. d4 ) 1... c5");
+    /// assert!(Game::from_json(r#"{"moves": [{"san": "e5"}]}"#).is_err());
+    /// ```
+    pub fn from_json(json: &str) -> Result<Self, JsonError> {
+        let json_game: JsonGame = serde_json::from_str(json).map_err(JsonError::Syntax)?;
+
+        let mut game = Game::default();
+        game.set_header_map(json_game.headers);
+
+        // The variant comes from the tags, as in PGN
+        let variant = game.opt_headers.get("Variant");
+        let variant = variant
+            .and_then(|val| parse_variant(val))
+            .unwrap_or_default();
+        game.root = Node::from_position(VariantPosition::new(variant));
+        if let Some(fen) = json_game.fen {
+            let position = position_from_fen(variant, &fen)
+                .map(|(position, _)| position)
+                .ok_or(JsonError::InvalidFen(fen))?;
+            game.root = Node::from_position(position);
+        }
+
+        let comment_vec = json_game
+            .comments
+            .into_iter()
+            .map(Comment::after_move)
+            .collect();
+        game.root.set_comments(comment_vec);
+
+        JsonNode::add_line(json_game.moves, &game.root)?;
+
+        Ok(game)
+    }
+
+    /// Returns every tag of the game, including the Seven Tag Roster.
+    pub(super) fn header_map(&self) -> BTreeMap<String, String> {
+        self.tags().collect()
+    }
+
+    /// Sets the tags of the game from [`Game::header_map`].
+    pub(super) fn set_header_map(&mut self, headers: BTreeMap<String, String>) {
+        for (key, value) in headers {
+            if !self.header.parse(&key, &value) {
+                self.opt_headers.insert(key, value);
+            }
+        }
+    }
+}
+
+impl Node {
+    /// Returns the metadata entry under `key`, deserialized from JSON, or
+    /// `None` if there is none or it does not have the shape of `T`.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - name of the entry
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let game = sacrifice::read_pgn("1. e4 e5").unwrap();
+    /// let mut e4_node = game.root().mainline().unwrap();
+    /// e4_node.set_meta_value("scores", &vec![3, 5]).unwrap();
+    /// assert_eq!(e4_node.meta("scores"), Some("[3,5]".to_string()));
+    /// assert_eq!(e4_node.meta_value::<Vec<u32>>("scores"), Some(vec![3, 5]));
+    /// assert_eq!(e4_node.meta_value::<String>("scores"), None);
+    /// ```
+    pub fn meta_value<T: serde::de::DeserializeOwned>(&self, key: &str) -> Option<T> {
+        serde_json::from_str(&self.meta(key)?).ok()
+    }
+
+    /// Stores `value` as JSON in the metadata entry under `key`.
+    ///
+    /// Returns the previous value, or an error if `value` cannot be
+    /// serialized, in which case the entry is unchanged.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - name of the entry
+    /// * `value` - new value of the entry
+    pub fn set_meta_value<T: Serialize + ?Sized>(
+        &mut self,
+        key: &str,
+        value: &T,
+    ) -> serde_json::Result<Option<String>> {
+        let json = serde_json::to_string(value)?;
+        Ok(self.set_meta(key, Some(json)))
+    }
+}
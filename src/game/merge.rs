@@ -0,0 +1,132 @@
+use super::{Game, Node};
+
+/// How annotations are combined when both games annotate the same node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MergePolicy {
+    /// Keep both: comments and shapes are appended unless already present,
+    /// NAGs are united, and clocks, move times, evaluations and metadata
+    /// entries are kept from this game
+    #[default]
+    Combine,
+    /// Keep this game's annotations, taking the other's only where this
+    /// game has none
+    KeepOurs,
+    /// Take the other game's annotations wherever it has some
+    KeepTheirs,
+}
+
+impl Game {
+    /// Adds the moves of another game that this one lacks, so that the
+    /// tree holds the variations of both.
+    ///
+    /// Nodes reached by the same moves in both games are annotated
+    /// according to `policy`. Tag pairs are left unchanged.
+    ///
+    /// Returns `false` without changing anything if the games do not
+    /// start from the same position.
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - the game to merge into this one
+    /// * `policy` - how to combine annotations of shared nodes
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sacrifice::game::MergePolicy;
+    ///
+    /// let game = sacrifice::read_pgn("1. e4 e5 2. Nf3 *").unwrap();
+    /// let other = sacrifice::read_pgn("1. e4 { best by test } c5 *").unwrap();
+    /// assert!(game.merge(&other, MergePolicy::Combine));
+    /// assert_eq!(game.movetext(), "1. e4 { best by test } 1... e5 ( 1... c5 ) 2. Nf3");
+    /// ```
+    pub fn merge(&self, other: &Game, policy: MergePolicy) -> bool {
+        if self.initial_position() != other.initial_position() {
+            return false;
+        }
+
+        self.root.edit_group(|| {
+            let mut node_vec = vec![(other.root(), self.root())];
+            while let Some((src, mut dst)) = node_vec.pop() {
+                merge_annotations(&src, &mut dst, policy);
+
+                for src_child in src.variation_vec() {
+                    let m = if let Some(val) = src_child.prev_move() {
+                        val
+                    } else {
+                        continue; // Children always have a move
+                    };
+
+                    let dst_child = match dst.child_by_move(&m) {
+                        Some(node) => node,
+                        None => {
+                            if let Some(val) = dst.new_variation(m) {
+                                val
+                            } else {
+                                continue; // Same position, so this never happens
+                            }
+                        }
+                    };
+                    node_vec.push((src_child, dst_child));
+                }
+            }
+        });
+
+        true
+    }
+}
+
+/// Returns the merged list of annotations such as comments or shapes.
+fn merge_vec<T: PartialEq>(src_vec: Vec<T>, mut dst_vec: Vec<T>, policy: MergePolicy) -> Vec<T> {
+    match policy {
+        MergePolicy::Combine => {
+            for item in src_vec {
+                if !dst_vec.contains(&item) {
+                    dst_vec.push(item);
+                }
+            }
+        }
+        MergePolicy::KeepOurs if !dst_vec.is_empty() => {}
+        _ if src_vec.is_empty() => {}
+        _ => dst_vec = src_vec,
+    }
+    dst_vec
+}
+
+pub(super) fn merge_annotations(src: &Node, dst: &mut Node, policy: MergePolicy) {
+    dst.set_comments(merge_vec(src.comments(), dst.comments(), policy));
+    dst.set_shapes(merge_vec(src.shapes(), dst.shapes(), policy));
+
+    if let (Some(src_nags), Some(mut dst_nags)) = (src.nags(), dst.nags()) {
+        match policy {
+            MergePolicy::Combine => dst_nags.extend(src_nags),
+            MergePolicy::KeepOurs if !dst_nags.is_empty() => {}
+            _ if src_nags.is_empty() => {}
+            _ => dst_nags = src_nags,
+        }
+        dst.set_nags(dst_nags);
+    }
+
+    let (clock, eval) = match policy {
+        MergePolicy::KeepTheirs => (
+            src.clock().or(dst.clock()),
+            src.eval_info().or(dst.eval_info()),
+        ),
+        _ => (
+            dst.clock().or(src.clock()),
+            dst.eval_info().or(src.eval_info()),
+        ),
+    };
+    dst.set_clock(clock);
+    dst.set_eval_info(eval);
+    dst.set_emt(match policy {
+        MergePolicy::KeepTheirs => src.emt().or(dst.emt()),
+        _ => dst.emt().or(src.emt()),
+    });
+
+    for (key, value) in src.meta_map() {
+        if policy == MergePolicy::KeepTheirs || dst.meta(&key).is_none() {
+            dst.set_meta(&key, Some(value));
+        }
+    }
+}
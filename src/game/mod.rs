@@ -1,19 +1,68 @@
 mod node;
 pub use node::Node;
 mod header;
-pub use header::Header;
+pub use header::{Date, GameResult, Header, InvalidHeaderValue, Round};
+mod builder;
+pub use builder::{BuildError, GameBuilder};
+mod classify;
+pub use classify::{ClassifyOptions, LossMetric, MoveError};
+mod comment;
+pub use comment::{Comment, CommentPlacement};
+mod accuracy;
+pub use accuracy::{AccuracyReport, PlayerAccuracy};
+mod series;
+pub use series::EvalPoint;
+mod annotation;
+pub use annotation::{nag_gloss, Eval, EvalInfo, Shape, ShapeColor};
+mod draw;
+pub use draw::DrawReason;
+mod diagram;
+#[cfg(feature = "render")]
+pub(crate) use diagram::unicode_symbol;
+pub use diagram::BoardDiagram;
+mod uci;
+pub use uci::UciPositionError;
+mod merge;
+pub use merge::MergePolicy;
+mod opening;
+mod outcome;
+mod transposition;
+pub use outcome::{GameOutcome, Termination};
+mod prune;
+pub use prune::PruneOptions;
+mod tag;
+mod variant;
+pub(crate) use variant::{parse_variant, position_fen, position_from_fen};
+mod validate;
+pub use validate::Violation;
+mod event;
+pub use event::{Listener, ListenerId, TreeEvent};
+mod iter;
+pub use iter::{Descendants, DescendantsBfs, MainlineIter, MainlineMoves};
+#[cfg(feature = "serde")]
+mod json;
+#[cfg(feature = "serde")]
+pub use json::JsonError;
+#[cfg(feature = "serde")]
+mod serialize;
 
 use std::collections::HashMap;
 
 use crate::pgn::writer;
-use crate::Chess;
+use crate::VariantPosition;
 
 /// A chess game with possible variations.
 ///
 /// It contains a simple Game Tree, plus headers.
+///
+/// Cloning is shallow: the headers are copied, but the clone shares the
+/// tree with the original, so moves added to one show up in the other.
+/// Use [`Game::deep_clone`] for an independent copy.
 #[derive(Debug, Clone, Default)]
 pub struct Game {
     pub header: Header,
+    /// Tags other than the Seven Tag Roster; exported with `SetUp` and
+    /// `FEN` first, then sorted by name
     pub opt_headers: HashMap<String, String>,
 
     pub(crate) root: Node,
@@ -26,34 +75,204 @@ impl Game {
     /// # Examples
     ///
     /// ```
-    /// let game = sacrifice::read_pgn("1. e4 e5");
+    /// let game = sacrifice::read_pgn("1. e4 e5").unwrap();
     /// let root_node = game.root();
     /// ```
     pub fn root(&self) -> Node {
         self.root.clone()
     }
 
-    pub fn initial_position(&self) -> Chess {
+    pub fn initial_position(&self) -> VariantPosition {
         self.root.position()
     }
+
+    /// Sets the starting position of the game, and plays every move again
+    /// from it. Moves are kept by their squares, as in UCI.
+    ///
+    /// Lines whose first move becomes illegal are removed, with everything
+    /// after them. Returns the first node of each removed line, which keeps
+    /// its SAN from before. The `SetUp`, `FEN` and `Variant` headers are
+    /// updated.
+    ///
+    /// # Arguments
+    ///
+    /// * `position` - the new starting position
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sacrifice::{CastlingMode, Chess, Fen};
+    ///
+    /// let mut game = sacrifice::read_pgn("1. e4 e5 (1... Nc6) 2. Nf3 *").unwrap();
+    /// let fen: Fen = "r1bqkbnr/pppppppp/2n5/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 1 2".parse().unwrap();
+    /// let position: Chess = fen.into_position(CastlingMode::Standard).unwrap();
+    ///
+    /// let removed = game.set_initial_position(position.clone());
+    /// assert_eq!(removed.len(), 1);
+    /// assert_eq!(removed[0].san().unwrap().to_string(), "Nc6");
+    /// assert_eq!(game.movetext(), "2. e4 e5 3. Nf3");
+    /// assert_eq!(game.initial_position(), position.into());
+    /// assert_eq!(game.opt_headers.get("SetUp").map(String::as_str), Some("1"));
+    /// ```
+    pub fn set_initial_position(&mut self, position: impl Into<VariantPosition>) -> Vec<Node> {
+        let position = position.into();
+        self.set_position_tags(&position);
+        self.root.replay_from(position)
+    }
+
+    /// Returns the node with the given [id](Node::id), or `None` if there
+    /// is none or it was removed from the tree.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - the id of the node
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let game = sacrifice::read_pgn("1. e4 e5").unwrap();
+    /// let mut e5_node = game.root().mainline().unwrap().mainline().unwrap();
+    /// let id = e5_node.id();
+    /// e5_node.remove_node();
+    /// assert!(game.node_by_id(id).is_none());
+    /// ```
+    pub fn node_by_id(&self, id: u64) -> Option<Node> {
+        let node = self.root.by_id(id)?;
+
+        // Removed nodes keep their parent link, but their parent no longer
+        // lists them
+        let mut child = node.clone();
+        while let Some(parent) = child.parent() {
+            if !parent.variation_vec().contains(&child) {
+                return None;
+            }
+            child = parent;
+        }
+        (child == self.root).then_some(node)
+    }
+
+    /// Returns the node reached from the root by the given moves, or
+    /// `None` if the tree has no such line.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - moves in SAN (`Nf3`) or UCI (`g1f3`) notation
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let game = sacrifice::read_pgn("1. e4 c5 (1... e5 2. Nf3) 2. Nf3 *").unwrap();
+    /// let node = game.node_at(&["e4", "e7e5", "Nf3"]).unwrap();
+    /// assert_eq!(node.moves().len(), 3);
+    /// assert!(game.node_at(&["e4", "e5", "Bc4"]).is_none());
+    /// assert_eq!(game.node_at::<&str>(&[]), Some(game.root()));
+    /// ```
+    pub fn node_at<S: AsRef<str>>(&self, path: &[S]) -> Option<Node> {
+        let mut node = self.root();
+        for token in path {
+            let m = node.parse_move(token.as_ref())?;
+            node = node.child_by_move(&m)?;
+        }
+        Some(node)
+    }
+
+    /// Returns a copy of the game with its own tree, which can be edited
+    /// without affecting the original.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let game = sacrifice::read_pgn("1. e4 e5 *").unwrap();
+    /// let scratch = game.deep_clone();
+    /// scratch.root().mainline().unwrap().remove_node();
+    /// assert_eq!(scratch.movetext(), "");
+    /// assert_eq!(game.movetext(), "1. e4 e5");
+    /// ```
+    pub fn deep_clone(&self) -> Self {
+        Self {
+            header: self.header.clone(),
+            opt_headers: self.opt_headers.clone(),
+            root: self.root.deep_clone(),
+        }
+    }
+}
+
+// Edit history
+impl Game {
+    /// Starts or stops recording edits of the tree for [`Game::undo`] and
+    /// [`Game::redo`].
+    ///
+    /// Recording is off by default, so that reading games costs no extra
+    /// memory. Stopping it discards the recorded edits. Header changes are
+    /// not recorded.
+    ///
+    /// # Arguments
+    ///
+    /// * `enabled` - whether edits are recorded from now on
+    pub fn set_history_enabled(&self, enabled: bool) {
+        self.root.set_history_enabled(enabled);
+    }
+
+    /// Reverts the last recorded edit of the tree.
+    ///
+    /// Adding, removing or reordering variations, and changing comments,
+    /// NAGs, clocks or evaluations, are each one edit; so are
+    /// [`Node::promote_to_mainline`], [`Node::graft`] and [`Game::merge`]
+    /// as a whole.
+    ///
+    /// Returns `false` if there is nothing to undo.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let game = sacrifice::read_pgn("1. e4 e5 *").unwrap();
+    /// game.set_history_enabled(true);
+    /// let mut e5_node = game.root().mainline().unwrap().mainline().unwrap();
+    /// e5_node.set_comment(Some("Open game".to_string()));
+    /// e5_node.remove_node();
+    /// assert_eq!(game.movetext(), "1. e4");
+    ///
+    /// assert!(game.undo());
+    /// assert_eq!(game.movetext(), "1. e4 e5 { Open game }");
+    /// assert!(game.undo());
+    /// assert_eq!(game.movetext(), "1. e4 e5");
+    /// assert!(!game.undo());
+    ///
+    /// assert!(game.redo());
+    /// assert_eq!(game.movetext(), "1. e4 e5 { Open game }");
+    /// ```
+    pub fn undo(&self) -> bool {
+        self.root.undo()
+    }
+
+    /// Reapplies the last edit reverted by [`Game::undo`].
+    ///
+    /// Any new edit discards the edits that could be redone.
+    ///
+    /// Returns `false` if there is nothing to redo.
+    pub fn redo(&self) -> bool {
+        self.root.redo()
+    }
+
+    /// Returns whether [`Game::undo`] has an edit to revert.
+    pub fn can_undo(&self) -> bool {
+        self.root.can_undo()
+    }
+
+    /// Returns whether [`Game::redo`] has an edit to reapply.
+    pub fn can_redo(&self) -> bool {
+        self.root.can_redo()
+    }
 }
 
 impl std::fmt::Display for Game {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let mut visitor = if let Some(max_width) = f.width() {
-            writer::PgnWriter::with_max_width(max_width as u32)
-        } else {
-            writer::PgnWriter::new()
+        let options = writer::PgnWriterOptions {
+            max_width: f.width().map(|w| w as u32),
+            ..writer::PgnWriterOptions::default()
         };
 
-        use writer::FullAcceptor;
-        let line_vec = self.accept(&mut visitor);
-
         // This always ends with \n.
-        for line in line_vec {
-            writeln!(f, "{}", line)?;
-        }
-
-        Ok(())
+        f.write_str(&self.to_pgn_with(&options))
     }
 }
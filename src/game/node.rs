@@ -1,84 +1,534 @@
-use crate::{Chess, Move, Position};
+use super::annotation::{Eval, EvalInfo, Shape};
+use super::comment::{join_comments, Comment, CommentPlacement};
+use super::event::{Listener, ListenerId, TreeEvent};
+use super::merge::merge_annotations;
+use super::{Game, MergePolicy};
+use crate::{ByColor, ByRole, Color, Fen, Move, Outcome, Position, SanPlus, VariantPosition};
+use shakmaty::uci::Uci;
+use shakmaty::zobrist::{Zobrist64, ZobristHash};
+use shakmaty::EnPassantMode;
 
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashSet};
+use std::sync::OnceLock;
+use std::time::Duration;
 
-use std::cell::RefCell;
-use std::rc::Rc;
+use shared::{Rc, RefCell};
+
+/// Single-threaded shared ownership.
+#[cfg(not(feature = "sync"))]
+mod shared {
+    pub(super) use std::cell::RefCell;
+    pub(super) use std::rc::Rc;
+}
+
+/// Thread-safe shared ownership, with the `RefCell` borrowing API so that
+/// the tree code is the same for both.
+#[cfg(feature = "sync")]
+mod shared {
+    use std::sync::{PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+    pub(super) use std::sync::Arc as Rc;
+
+    #[derive(Debug, Default)]
+    pub(super) struct RefCell<T>(RwLock<T>);
+
+    impl<T> RefCell<T> {
+        pub(super) fn new(value: T) -> Self {
+            Self(RwLock::new(value))
+        }
+
+        pub(super) fn borrow(&self) -> RwLockReadGuard<'_, T> {
+            // A panic elsewhere cannot leave a node half-updated
+            self.0.read().unwrap_or_else(PoisonError::into_inner)
+        }
+
+        pub(super) fn borrow_mut(&self) -> RwLockWriteGuard<'_, T> {
+            self.0.write().unwrap_or_else(PoisonError::into_inner)
+        }
+    }
+}
 
 #[derive(Debug, Clone)]
 struct ParentState {
-    /// This node's parent
-    node: Node,
+    /// Index of this node's parent in the arena
+    index: usize,
     /// The move that leads to this position
     move_next: Move,
+    /// `move_next` in SAN, computed when first asked for
+    san: OnceLock<SanPlus>,
 
-    /// Comment about the start of a variation
-    starting_comment: Option<String>,
     /// this node's nag attributes
     nag_set: HashSet<u8>,
 }
 
 #[derive(Debug, Clone, Default)]
-pub struct NodeImpl {
+struct NodeImpl {
     parent: Option<ParentState>,
 
     /// Position of current node
-    position: Chess,
+    position: VariantPosition,
+
+    /// Indices of children nodes (variations), including mainline
+    variation_vec: Vec<usize>,
+    /// Comments against this node, in order of appearance
+    comment_vec: Vec<Comment>,
+
+    /// Remaining time of the player who just moved (`[%clk]`)
+    clock: Option<Duration>,
+    /// Time spent on the move (`[%emt]`)
+    emt: Option<Duration>,
+    /// Evaluation of the position (`[%eval]`)
+    eval: Option<EvalInfo>,
+    /// Arrows and highlighted squares (`[%cal]` and `[%csl]`)
+    shape_vec: Vec<Shape>,
+
+    /// Application data, by key
+    meta_map: BTreeMap<String, String>,
+}
+
+/// Every node of one game tree; nodes refer to each other by index, so
+/// the tree has no reference cycles.
+///
+/// Removed nodes are only detached, and are freed with the arena.
+#[derive(Debug, Default)]
+struct Arena {
+    node_vec: Vec<NodeImpl>,
+    history: History,
+    listeners: Listeners,
+}
+
+/// Callbacks for tree changes, by id.
+#[derive(Default)]
+struct Listeners {
+    listener_vec: Vec<(u64, Box<dyn Listener>)>,
+    next_id: u64,
+}
+
+impl std::fmt::Debug for Listeners {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Listeners")
+            .field("count", &self.listener_vec.len())
+            .finish()
+    }
+}
+
+/// A change to one node, found by comparing its states.
+#[derive(Debug, Clone, Copy)]
+enum Change {
+    Added(usize),
+    Removed(usize),
+    Promoted(usize),
+    Reordered(usize),
+    Comment(usize),
+    Nags(usize),
+}
+
+/// The parts of a node whose changes are reported to listeners.
+struct Observed {
+    variation_vec: Vec<usize>,
+    comment_vec: Vec<Comment>,
+    nag_set: Option<HashSet<u8>>,
+}
+
+impl Observed {
+    fn of(node: &NodeImpl) -> Self {
+        Self {
+            variation_vec: node.variation_vec.clone(),
+            comment_vec: node.comment_vec.clone(),
+            nag_set: node.parent.as_ref().map(|p| p.nag_set.clone()),
+        }
+    }
+
+    /// Returns the changes from this state to `node`, the node at `index`.
+    fn changes(&self, index: usize, node: &NodeImpl) -> Vec<Change> {
+        let (prev_vec, next_vec) = (&self.variation_vec, &node.variation_vec);
+        let mut change_vec: Vec<Change> = prev_vec
+            .iter()
+            .filter(|i| !next_vec.contains(i))
+            .map(|&i| Change::Removed(i))
+            .collect();
+        change_vec.extend(
+            next_vec
+                .iter()
+                .filter(|i| !prev_vec.contains(i))
+                .map(|&i| Change::Added(i)),
+        );
+
+        let kept_prev: Vec<&usize> = prev_vec.iter().filter(|i| next_vec.contains(i)).collect();
+        let kept_next: Vec<&usize> = next_vec.iter().filter(|i| prev_vec.contains(i)).collect();
+        if kept_prev != kept_next {
+            match next_vec.first() {
+                Some(&first) if prev_vec.first() != Some(&first) && prev_vec.contains(&first) => {
+                    change_vec.push(Change::Promoted(first))
+                }
+                _ => change_vec.push(Change::Reordered(index)),
+            }
+        }
+
+        if self.comment_vec != node.comment_vec {
+            change_vec.push(Change::Comment(index));
+        }
+        if self.nag_set.as_ref() != node.parent.as_ref().map(|p| &p.nag_set) {
+            change_vec.push(Change::Nags(index));
+        }
+        change_vec
+    }
+}
+
+/// Earlier states of the nodes changed by one edit.
+type Edit = Vec<(usize, NodeImpl)>;
+
+/// Journal of tree edits, for undo and redo.
+///
+/// Each edit keeps a copy of every node it changed, taken just before the
+/// first change. Nodes added by an edit stay in the arena when it is
+/// undone; they are only unreachable.
+#[derive(Debug, Default)]
+struct History {
+    enabled: bool,
+    undo_vec: Vec<Edit>,
+    redo_vec: Vec<Edit>,
+
+    /// Number of open edit groups; changes inside them form one edit
+    group_depth: usize,
+    group_edit: Edit,
+    group_index_set: HashSet<usize>,
+}
+
+impl Arena {
+    /// Saves the state of a node about to be changed.
+    fn record(&mut self, index: usize) {
+        let history = &mut self.history;
+        if !history.enabled {
+            return;
+        }
+
+        let prev = self.node_vec[index].clone();
+        if history.group_depth > 0 {
+            if history.group_index_set.insert(index) {
+                history.group_edit.push((index, prev));
+            }
+        } else {
+            history.undo_vec.push(vec![(index, prev)]);
+            history.redo_vec.clear();
+        }
+    }
 
-    /// Children nodes (variations), including mainline
-    variation_vec: Vec<Node>,
-    /// Comment against this node
-    comment: Option<String>,
+    /// Returns the changes made by restoring an edit, given the replaced
+    /// states it returned.
+    fn changes(&self, replaced: &Edit) -> Vec<Change> {
+        if self.listeners.listener_vec.is_empty() {
+            return Vec::new();
+        }
+        replaced
+            .iter()
+            .flat_map(|(index, prev)| Observed::of(prev).changes(*index, &self.node_vec[*index]))
+            .collect()
+    }
+
+    /// Restores the nodes of an edit, returning their replaced states.
+    fn restore(&mut self, edit: Edit) -> Edit {
+        edit.into_iter()
+            .rev()
+            .map(|(index, node)| (index, std::mem::replace(&mut self.node_vec[index], node)))
+            .collect()
+    }
 }
 
 /// A node in the game tree.
-#[derive(Debug, Clone, Default)]
-pub struct Node(Rc<RefCell<NodeImpl>>);
+///
+/// This is a handle into the storage of the whole tree, which lives as
+/// long as the game or any of its node handles.
+#[derive(Clone)]
+pub struct Node {
+    arena: Rc<RefCell<Arena>>,
+    index: usize,
+}
 
 impl PartialEq<Self> for Node {
     fn eq(&self, other: &Self) -> bool {
-        Rc::ptr_eq(&self.0, &other.0)
+        Rc::ptr_eq(&self.arena, &other.arena) && self.index == other.index
+    }
+}
+
+impl std::fmt::Debug for Node {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Node")
+            .field("index", &self.index)
+            .field("prev_move", &self.prev_move())
+            .finish()
+    }
+}
+
+impl Default for Node {
+    fn default() -> Self {
+        Self::from_position(VariantPosition::default())
     }
 }
 
 // Constructors
 impl Node {
-    pub(crate) fn from_position(position: Chess) -> Self {
+    pub(crate) fn from_position(position: VariantPosition) -> Self {
         let ret = NodeImpl {
             position,
             ..NodeImpl::default()
         };
-        let ret = Rc::new(RefCell::new(ret));
+        let arena = Arena {
+            node_vec: vec![ret],
+            history: History::default(),
+            listeners: Listeners::default(),
+        };
 
-        Self(ret)
+        Self {
+            arena: Rc::new(RefCell::new(arena)),
+            index: 0,
+        }
     }
 
     pub(crate) fn from_node(node: Self, move_next: Move) -> Option<Self> {
-        let position_next = if let Ok(inner) = node.position()
-            .play(&move_next) {
+        let ret = node.child_impl(move_next)?;
+
+        let mut arena = node.arena.borrow_mut();
+        arena.node_vec.push(ret);
+        let index = arena.node_vec.len() - 1;
+        drop(arena);
+
+        Some(node.handle(index))
+    }
+
+    /// Returns the state of a new child reached by the given move.
+    fn child_impl(&self, move_next: Move) -> Option<NodeImpl> {
+        let position_next = if let Ok(inner) = self.position().play(&move_next) {
             inner
-        } else { return None; };
+        } else {
+            return None;
+        };
 
-        let ret = NodeImpl {
+        Some(NodeImpl {
             parent: Some(ParentState {
-                node,
+                index: self.index,
                 move_next,
-                starting_comment: None,
+                san: OnceLock::new(),
                 nag_set: HashSet::new(),
             }),
 
             position: position_next,
 
             variation_vec: Vec::new(),
-            comment: None,
+            comment_vec: Vec::new(),
+
+            clock: None,
+            emt: None,
+            eval: None,
+            shape_vec: Vec::new(),
+
+            meta_map: BTreeMap::new(),
+        })
+    }
+}
+
+impl Node {
+    /// Returns the legal move written as `token` in SAN or UCI notation.
+    pub(crate) fn parse_move(&self, token: &str) -> Option<Move> {
+        self.parse_san(token)
+            .or_else(|| token.parse::<Uci>().ok()?.to_move(&self.position()).ok())
+    }
+
+    /// Returns the legal move written as `san` in SAN notation.
+    fn parse_san(&self, san: &str) -> Option<Move> {
+        let san = san.parse::<SanPlus>().ok()?.san;
+        san.to_move(&self.position()).ok()
+    }
+
+    /// Returns the same node in an independent copy of the whole tree.
+    pub(crate) fn deep_clone(&self) -> Self {
+        let node_vec = self.arena.borrow().node_vec.clone();
+        let arena = Arena {
+            node_vec,
+            history: History::default(),
+            listeners: Listeners::default(),
+        };
+        Self {
+            arena: Rc::new(RefCell::new(arena)),
+            index: self.index,
+        }
+    }
+
+    /// Returns the node with the given id in the same tree, attached or not.
+    pub(crate) fn by_id(&self, id: u64) -> Option<Self> {
+        let index = usize::try_from(id).ok()?;
+        if index >= self.arena.borrow().node_vec.len() {
+            return None;
+        }
+        Some(self.handle(index))
+    }
+
+    /// Returns a handle to another node of the same tree.
+    fn handle(&self, index: usize) -> Self {
+        Self {
+            arena: self.arena.clone(),
+            index,
+        }
+    }
+
+    fn with<R>(&self, f: impl FnOnce(&NodeImpl) -> R) -> R {
+        f(&self.arena.borrow().node_vec[self.index])
+    }
+
+    fn with_mut<R>(&self, f: impl FnOnce(&mut NodeImpl) -> R) -> R {
+        let mut arena = self.arena.borrow_mut();
+        arena.record(self.index);
+        if arena.listeners.listener_vec.is_empty() {
+            return f(&mut arena.node_vec[self.index]);
+        }
+
+        let prev = Observed::of(&arena.node_vec[self.index]);
+        let ret = f(&mut arena.node_vec[self.index]);
+        let change_vec = prev.changes(self.index, &arena.node_vec[self.index]);
+        drop(arena);
+
+        self.notify(change_vec);
+        ret
+    }
+}
+
+// Change notifications
+impl Node {
+    pub(crate) fn add_listener(&self, listener: Box<dyn Listener>) -> ListenerId {
+        let listeners = &mut self.arena.borrow_mut().listeners;
+        let id = listeners.next_id;
+        listeners.next_id += 1;
+        listeners.listener_vec.push((id, listener));
+        ListenerId(id)
+    }
+
+    pub(crate) fn remove_listener(&self, id: ListenerId) -> bool {
+        let listener_vec = &mut self.arena.borrow_mut().listeners.listener_vec;
+        let len = listener_vec.len();
+        listener_vec.retain(|(listener_id, _)| *listener_id != id.0);
+        listener_vec.len() < len
+    }
+
+    /// Calls every listener for each change, without holding the tree.
+    fn notify(&self, change_vec: Vec<Change>) {
+        if change_vec.is_empty() {
+            return;
+        }
+
+        // Taken out so that listeners can read the tree; changes they make
+        // find no listeners
+        let mut listener_vec = std::mem::take(&mut self.arena.borrow_mut().listeners.listener_vec);
+        for change in change_vec {
+            let event = match change {
+                Change::Added(index) => TreeEvent::NodeAdded(self.handle(index)),
+                Change::Removed(index) => TreeEvent::NodeRemoved(self.handle(index)),
+                Change::Promoted(index) => TreeEvent::NodePromoted(self.handle(index)),
+                Change::Reordered(index) => TreeEvent::VariationsReordered(self.handle(index)),
+                Change::Comment(index) => TreeEvent::CommentChanged(self.handle(index)),
+                Change::Nags(index) => TreeEvent::NagsChanged(self.handle(index)),
+            };
+            for (_, listener) in listener_vec.iter_mut() {
+                listener(&event);
+            }
+        }
+
+        // Keep listeners added in the meantime
+        let listeners = &mut self.arena.borrow_mut().listeners;
+        listener_vec.append(&mut listeners.listener_vec);
+        listeners.listener_vec = listener_vec;
+    }
+}
+
+// Edit history
+impl Node {
+    pub(crate) fn set_history_enabled(&self, enabled: bool) {
+        let history = &mut self.arena.borrow_mut().history;
+        history.enabled = enabled;
+        if !enabled {
+            history.undo_vec.clear();
+            history.redo_vec.clear();
+        }
+    }
+
+    pub(crate) fn can_undo(&self) -> bool {
+        !self.arena.borrow().history.undo_vec.is_empty()
+    }
+
+    pub(crate) fn can_redo(&self) -> bool {
+        !self.arena.borrow().history.redo_vec.is_empty()
+    }
+
+    pub(crate) fn undo(&self) -> bool {
+        let mut arena = self.arena.borrow_mut();
+        let edit = if let Some(val) = arena.history.undo_vec.pop() {
+            val
+        } else {
+            return false;
+        };
+        let redo_edit = arena.restore(edit);
+        let change_vec = arena.changes(&redo_edit);
+        arena.history.redo_vec.push(redo_edit);
+        drop(arena);
+
+        self.notify(change_vec);
+        true
+    }
+
+    pub(crate) fn redo(&self) -> bool {
+        let mut arena = self.arena.borrow_mut();
+        let edit = if let Some(val) = arena.history.redo_vec.pop() {
+            val
+        } else {
+            return false;
         };
-        let ret = Rc::new(RefCell::new(ret));
+        let undo_edit = arena.restore(edit);
+        let change_vec = arena.changes(&undo_edit);
+        arena.history.undo_vec.push(undo_edit);
+        drop(arena);
 
-        Some(Self(ret))
+        self.notify(change_vec);
+        true
+    }
+
+    /// Runs `f`, recording all of its changes to the tree as one edit.
+    pub(crate) fn edit_group<R>(&self, f: impl FnOnce() -> R) -> R {
+        self.arena.borrow_mut().history.group_depth += 1;
+        let ret = f();
+
+        let mut arena = self.arena.borrow_mut();
+        let history = &mut arena.history;
+        history.group_depth -= 1;
+        if history.group_depth == 0 {
+            history.group_index_set.clear();
+            let edit = std::mem::take(&mut history.group_edit);
+            if !edit.is_empty() {
+                history.undo_vec.push(edit);
+                history.redo_vec.clear();
+            }
+        }
+        ret
     }
 }
 
 impl Node {
+    /// Returns the identifier of the given node, unique within its tree.
+    ///
+    /// Ids are assigned when nodes are created and never reused, even
+    /// after a node is removed; the root is always `0`. Deep clones keep
+    /// the ids of the original.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let game = sacrifice::read_pgn("1. e4 (1. d4) 1... e5").unwrap();
+    /// let e5_node = game.root().mainline().unwrap().mainline().unwrap();
+    /// assert_eq!(game.root().id(), 0);
+    /// assert_eq!(game.node_by_id(e5_node.id()), Some(e5_node));
+    /// ```
+    pub fn id(&self) -> u64 {
+        self.index as u64
+    }
+
     /// Returns the parent node of the given node.
     ///
     /// # Arguments
@@ -88,7 +538,7 @@ impl Node {
     /// # Examples
     ///
     /// ```
-    /// let game = sacrifice::read_pgn("1. e4 e5");
+    /// let game = sacrifice::read_pgn("1. e4 e5").unwrap();
     /// let root = game.root();
     /// assert!(root.parent().is_none()); // root node needs no parent
     /// let mainline_node_1 = root.mainline().unwrap(); // 1. e4 node
@@ -98,11 +548,8 @@ impl Node {
     /// );
     /// ```
     pub fn parent(&self) -> Option<Self> {
-        if let Some(ref parent) = self.0.borrow().parent {
-            return Some(parent.node.clone());
-        }
-
-        None
+        let index = self.with(|n| n.parent.as_ref().map(|p| p.index))?;
+        Some(self.handle(index))
     }
 
     /// Returns the move that leads to the given node.
@@ -114,7 +561,7 @@ impl Node {
     /// # Examples
     ///
     /// ```
-    /// let game = sacrifice::read_pgn("1. e4 e5");
+    /// let game = sacrifice::read_pgn("1. e4 e5").unwrap();
     /// let mainline_node_1 = game.root().mainline().unwrap(); // 1. e4 node
     /// assert_eq!(
     ///   mainline_node_1.prev_move().unwrap().to(),
@@ -122,22 +569,175 @@ impl Node {
     /// );
     /// ```
     pub fn prev_move(&self) -> Option<Move> {
-        if let Some(ref parent) = self.0.borrow().parent {
-            return Some(parent.move_next.clone());
-        }
+        self.with(|n| n.parent.as_ref().map(|p| p.move_next.clone()))
+    }
 
-        None
+    /// Returns the move that leads to the given node in SAN, with the
+    /// check or checkmate suffix.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let game = sacrifice::read_pgn("1. f3 e5 2. g4 Qh4#").unwrap();
+    /// let nodes: Vec<_> = game.root().mainline_iter().collect();
+    /// assert_eq!(nodes[0].san().unwrap().to_string(), "f3");
+    /// assert_eq!(nodes[3].san().unwrap().to_string(), "Qh4#");
+    /// assert!(game.root().san().is_none());
+    /// ```
+    pub fn san(&self) -> Option<SanPlus> {
+        let arena = self.arena.borrow();
+        let parent = arena.node_vec[self.index].parent.as_ref()?;
+        let san = parent.san.get_or_init(|| {
+            let position = arena.node_vec[parent.index].position.clone();
+            SanPlus::from_move(position, &parent.move_next)
+        });
+        Some(san.clone())
+    }
+
+    /// Returns the move that leads to the given node in UCI notation
+    /// (`e2e4`, `e7e8q`).
+    ///
+    /// Castling is written as the king's move (`e1g1`), or as king takes
+    /// rook (`e1h1`) in Chess960 games.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let game = sacrifice::read_pgn("1. e4 e5 2. Nf3 Nc6 3. Bc4 Bc5 4. O-O").unwrap();
+    /// let castle_node = game.root().mainline_iter().last().unwrap();
+    /// assert_eq!(castle_node.uci().unwrap().to_string(), "e1g1");
+    /// assert!(game.root().uci().is_none());
+    /// ```
+    pub fn uci(&self) -> Option<Uci> {
+        self.with(|n| {
+            let parent = n.parent.as_ref()?;
+            Some(parent.move_next.to_uci(n.position.castles().mode()))
+        })
+    }
+
+    /// Returns whether the side to move is in check at the given node.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let game = sacrifice::read_pgn("1. e4 f5 2. Qh5+").unwrap();
+    /// let check_node = game.root().mainline_iter().last().unwrap();
+    /// assert!(check_node.is_check());
+    /// assert!(!check_node.parent().unwrap().is_check());
+    /// ```
+    pub fn is_check(&self) -> bool {
+        self.with(|n| n.position.is_check())
+    }
+
+    /// Returns whether the move that leads to the given node gives check.
+    /// Unlike [`Node::is_check`], it is `false` for the root node.
+    pub fn gives_check(&self) -> bool {
+        self.with(|n| n.parent.is_some() && n.position.is_check())
+    }
+
+    /// Returns whether the move that leads to the given node is a capture,
+    /// en passant included.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let game = sacrifice::read_pgn("1. e4 d5 2. exd5 Qxd5").unwrap();
+    /// let node_vec: Vec<_> = game.root().mainline_iter().collect();
+    /// assert!(!node_vec[1].is_capture());
+    /// assert!(node_vec[2].is_capture());
+    /// assert!(!game.root().is_capture());
+    /// ```
+    pub fn is_capture(&self) -> bool {
+        self.with(|n| n.parent.as_ref().is_some_and(|p| p.move_next.is_capture()))
+    }
+
+    /// Returns whether the move that leads to the given node is a
+    /// promotion.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let game = sacrifice::read_pgn(
+    ///     "[SetUp \"1\"]\n[FEN \"8/P7/8/8/8/8/8/K6k w - - 0 1\"]\n\n1. a8=N *"
+    /// ).unwrap();
+    /// assert!(game.root().mainline().unwrap().is_promotion());
+    /// ```
+    pub fn is_promotion(&self) -> bool {
+        self.with(|n| {
+            n.parent
+                .as_ref()
+                .is_some_and(|p| p.move_next.is_promotion())
+        })
+    }
+
+    /// Returns the legal moves from the position of the given node in
+    /// SAN, with the check or checkmate suffix. The moves are in the same
+    /// order as by [`Node::legal_moves_uci`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let game = sacrifice::read_pgn("1. e4 e5 2. Qh5 Nc6 3. Bc4 Nf6").unwrap();
+    /// let nf6_node = game.root().mainline_iter().last().unwrap();
+    /// let san_vec = nf6_node.legal_moves_san();
+    /// assert!(san_vec.contains(&"Qxf7#".to_string()));
+    /// assert_eq!(game.root().legal_moves_san().len(), 20);
+    /// ```
+    pub fn legal_moves_san(&self) -> Vec<String> {
+        let position = self.position();
+        position
+            .legal_moves()
+            .into_iter()
+            .map(|m| SanPlus::from_move(position.clone(), &m).to_string())
+            .collect()
+    }
+
+    /// Returns the legal moves from the position of the given node in UCI
+    /// notation. The moves are in the same order as by
+    /// [`Node::legal_moves_san`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let game = sacrifice::read_pgn("1. e4").unwrap();
+    /// let e4_node = game.root().mainline().unwrap();
+    /// let pair_vec: Vec<_> = e4_node
+    ///     .legal_moves_san()
+    ///     .into_iter()
+    ///     .zip(e4_node.legal_moves_uci())
+    ///     .collect();
+    /// assert!(pair_vec.contains(&("Nf6".to_string(), "g8f6".to_string())));
+    /// ```
+    pub fn legal_moves_uci(&self) -> Vec<String> {
+        let position = self.position();
+        let mode = position.castles().mode();
+        position
+            .legal_moves()
+            .into_iter()
+            .map(|m| m.to_uci(mode).to_string())
+            .collect()
     }
 
     pub fn variation_vec(&self) -> Vec<Self> {
-        self.0.borrow().variation_vec.clone()
+        let index_vec = self.with(|n| n.variation_vec.clone());
+        index_vec
+            .into_iter()
+            .map(|index| self.handle(index))
+            .collect()
     }
 
     pub fn set_variation_vec(&mut self, new_variation_vec: Vec<Self>) -> Vec<Self> {
-        std::mem::replace(
-            &mut self.0.borrow_mut().variation_vec,
-            new_variation_vec,
-        )
+        // Nodes of other trees cannot be children
+        let index_vec = new_variation_vec
+            .into_iter()
+            .filter(|node| Rc::ptr_eq(&node.arena, &self.arena))
+            .map(|node| node.index)
+            .collect();
+        let prev_vec = self.with_mut(|n| std::mem::replace(&mut n.variation_vec, index_vec));
+        prev_vec
+            .into_iter()
+            .map(|index| self.handle(index))
+            .collect()
     }
 
     /// Returns the starting comment (comment that starts a variation)
@@ -152,7 +752,7 @@ impl Node {
     /// ```
     /// let ok_str = "Ok";
     /// let pgn_str = format!("1. e4 ({{ {} }} 1. d4) 1... e5", ok_str);
-    /// let game = sacrifice::read_pgn(pgn_str.as_str());
+    /// let game = sacrifice::read_pgn(pgn_str.as_str()).unwrap();
     /// let variation_node_1_0 = game.root().other_variations()[0].clone(); // {Ok} 1. d4
     /// assert_eq!(
     ///   variation_node_1_0.starting_comment(),
@@ -160,11 +760,7 @@ impl Node {
     /// );
     /// ```
     pub fn starting_comment(&self) -> Option<String> {
-        if let Some(ref parent) = self.0.borrow().parent {
-            return parent.starting_comment.clone();
-        }
-
-        None
+        self.with(|n| join_comments(&n.comment_vec, CommentPlacement::BeforeMove))
     }
 
     /// Sets the starting comment of the given node.
@@ -177,7 +773,7 @@ impl Node {
     /// # Examples
     ///
     /// ```
-    /// let game = sacrifice::read_pgn("1. e4 (1. d4) 1... e5");
+    /// let game = sacrifice::read_pgn("1. e4 (1. d4) 1... e5").unwrap();
     /// let mut variation_node_1_0 = game.root().other_variations()[0].clone(); // {Ok} 1. d4
     /// assert!(variation_node_1_0.starting_comment().is_none()); // 1... e5
     /// variation_node_1_0.set_starting_comment(Some("Ok".to_string()));
@@ -187,11 +783,10 @@ impl Node {
     /// );
     /// ```
     pub fn set_starting_comment(&mut self, comment_next: Option<String>) -> Option<String> {
-        if let Some(ref mut parent) = self.0.borrow_mut().parent {
-            return std::mem::replace(&mut parent.starting_comment, comment_next);
-        }
+        // The root node has no move to precede
+        self.parent()?;
 
-        None
+        self.replace_comments(CommentPlacement::BeforeMove, comment_next)
     }
 
     /// Returns the NAGs of the given node.
@@ -203,26 +798,21 @@ impl Node {
     /// # Examples
     ///
     /// ```
-    /// let game = sacrifice::read_pgn("1. e4?? c5!");
+    /// let game = sacrifice::read_pgn("1. e4?? c5!").unwrap();
     /// let mainline_node_1 = game.root().mainline().unwrap(); // [1. e4??]
     /// assert!(mainline_node_1.nags().unwrap().contains(&4)); // ?? -> $4
     /// let mainline_node_2 = mainline_node_1.mainline().unwrap(); // [1... c5!]
     /// assert!(mainline_node_2.nags().unwrap().contains(&1)); // ! -> $1
     /// ```
     pub fn nags(&self) -> Option<HashSet<u8>> {
-        if let Some(ref parent) = self.0.borrow().parent {
-            return Some(parent.nag_set.clone());
-        }
-
-        None
+        self.with(|n| n.parent.as_ref().map(|p| p.nag_set.clone()))
     }
 
     pub fn set_nags(&mut self, nags_next: HashSet<u8>) -> Option<HashSet<u8>> {
-        if let Some(ref mut parent) = self.0.borrow_mut().parent {
-            return Some(std::mem::replace(&mut parent.nag_set, nags_next));
-        }
-
-        None
+        self.with_mut(|n| {
+            let parent = n.parent.as_mut()?;
+            Some(std::mem::replace(&mut parent.nag_set, nags_next))
+        })
     }
 
     /// Returns the comment on a given node.
@@ -236,7 +826,7 @@ impl Node {
     /// ```
     /// let e4_comment_str = "this blunders into the Sicilian Defense";
     /// let pgn_str = format!("1. e4 {{ {} }}  1... c5", e4_comment_str);
-    /// let game = sacrifice::read_pgn(pgn_str.as_str());
+    /// let game = sacrifice::read_pgn(pgn_str.as_str()).unwrap();
     /// let mainline_node_1 = game.root().mainline().unwrap(); // 1. e4
     /// assert_eq!(
     ///   mainline_node_1.comment(),
@@ -244,7 +834,7 @@ impl Node {
     /// );
     /// ```
     pub fn comment(&self) -> Option<String> {
-        self.0.borrow().comment.clone()
+        self.with(|n| join_comments(&n.comment_vec, CommentPlacement::AfterMove))
     }
 
     /// Sets the comment on a given node.
@@ -259,7 +849,7 @@ impl Node {
     /// ```
     /// let e4_comment_str = "this blunders into the Sicilian Defense";
     /// let pgn_str = format!("1. e4 {{ {} }}  1... c5", e4_comment_str);
-    /// let game = sacrifice::read_pgn(pgn_str.as_str());
+    /// let game = sacrifice::read_pgn(pgn_str.as_str()).unwrap();
     /// let mainline_node_1 = game.root().mainline().unwrap(); // 1. e4
     /// assert_eq!(
     ///   mainline_node_1.comment(),
@@ -273,7 +863,235 @@ impl Node {
     /// );
     /// ```
     pub fn set_comment(&self, comment_next: Option<String>) -> Option<String> {
-        std::mem::replace(&mut self.0.borrow_mut().comment, comment_next)
+        self.replace_comments(CommentPlacement::AfterMove, comment_next)
+    }
+
+    /// Returns all comments of the given node, in order of appearance.
+    ///
+    /// Unlike [`Node::comment`] and [`Node::starting_comment`], successive
+    /// comments are kept apart instead of being joined.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sacrifice::game::Comment;
+    ///
+    /// let game = sacrifice::read_pgn("1. e4 {a} {b} 1... c5").unwrap();
+    /// let mainline_node_1 = game.root().mainline().unwrap(); // 1. e4
+    /// assert_eq!(
+    ///   mainline_node_1.comments(),
+    ///   vec![Comment::after_move("a"), Comment::after_move("b")]
+    /// );
+    /// assert_eq!(mainline_node_1.comment(), Some("a b".to_string()));
+    /// ```
+    pub fn comments(&self) -> Vec<Comment> {
+        self.with(|n| n.comment_vec.clone())
+    }
+
+    pub fn set_comments(&mut self, comments_next: Vec<Comment>) -> Vec<Comment> {
+        self.with_mut(|n| std::mem::replace(&mut n.comment_vec, comments_next))
+    }
+
+    pub fn push_comment(&mut self, comment: Comment) {
+        self.with_mut(|n| n.comment_vec.push(comment));
+    }
+
+    /// Returns the remaining clock time of the player who made the move
+    /// leading to the given node.
+    ///
+    /// This is read from and written as a `[%clk]` comment command.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let game = sacrifice::read_pgn("1. e4 { [%clk 0:09:58.5] } 1... c5").unwrap();
+    /// let mainline_node_1 = game.root().mainline().unwrap(); // 1. e4
+    /// assert_eq!(
+    ///   mainline_node_1.clock(),
+    ///   Some(std::time::Duration::from_millis(598_500))
+    /// );
+    /// assert!(mainline_node_1.comment().is_none()); // the command is not a comment
+    /// ```
+    pub fn clock(&self) -> Option<Duration> {
+        self.with(|n| n.clock)
+    }
+
+    pub fn set_clock(&mut self, clock_next: Option<Duration>) -> Option<Duration> {
+        self.with_mut(|n| std::mem::replace(&mut n.clock, clock_next))
+    }
+
+    /// Returns the time spent on the move leading to the given node.
+    ///
+    /// This is read from and written as an `[%emt]` comment command.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let game = sacrifice::read_pgn("1. e4 { [%emt 0:00:17] } 1... c5").unwrap();
+    /// let mut mainline_node_1 = game.root().mainline().unwrap(); // 1. e4
+    /// assert_eq!(mainline_node_1.emt(), Some(std::time::Duration::from_secs(17)));
+    ///
+    /// mainline_node_1.set_emt(Some(std::time::Duration::from_secs(62)));
+    /// assert_eq!(game.movetext(), "1. e4 { [%emt 0:01:02] } 1... c5");
+    /// ```
+    pub fn emt(&self) -> Option<Duration> {
+        self.with(|n| n.emt)
+    }
+
+    pub fn set_emt(&mut self, emt_next: Option<Duration>) -> Option<Duration> {
+        self.with_mut(|n| std::mem::replace(&mut n.emt, emt_next))
+    }
+
+    /// Returns the evaluation of the given node's position.
+    ///
+    /// This is read from and written as an `[%eval]` comment command.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sacrifice::game::Eval;
+    ///
+    /// let game = sacrifice::read_pgn("1. e4 { [%eval 0.17] } 1... f6 { [%eval #-9] }").unwrap();
+    /// let mainline_node_1 = game.root().mainline().unwrap(); // 1. e4
+    /// assert_eq!(mainline_node_1.eval(), Some(Eval::Centipawns(17)));
+    /// let mainline_node_2 = mainline_node_1.mainline().unwrap(); // 1... f6
+    /// assert_eq!(mainline_node_2.eval(), Some(Eval::Mate(-9)));
+    /// ```
+    pub fn eval(&self) -> Option<Eval> {
+        self.with(|n| n.eval.as_ref().map(|info| info.eval))
+    }
+
+    /// Sets the evaluation of the given node's position, without depth or
+    /// engine.
+    pub fn set_eval(&mut self, eval_next: Option<Eval>) -> Option<Eval> {
+        let prev = self.set_eval_info(eval_next.map(EvalInfo::from));
+        prev.map(|info| info.eval)
+    }
+
+    /// Returns the evaluation of the given node's position, with the
+    /// search depth and engine name if known.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sacrifice::game::{Eval, EvalInfo};
+    ///
+    /// let game = sacrifice::read_pgn("1. e4 { [%eval 0.17,23] } 1... e5").unwrap();
+    /// let mut e4_node = game.root().mainline().unwrap();
+    /// let mut info = e4_node.eval_info().unwrap();
+    /// assert_eq!(info.depth, Some(23));
+    ///
+    /// info.engine = Some("Stockfish 16".to_string());
+    /// e4_node.set_eval_info(Some(info));
+    /// assert_eq!(e4_node.eval_info().unwrap().engine.as_deref(), Some("Stockfish 16"));
+    /// assert_eq!(e4_node.eval(), Some(Eval::Centipawns(17)));
+    /// assert_eq!(game.movetext(), "1. e4 { [%eval 0.17,23] } 1... e5");
+    /// ```
+    pub fn eval_info(&self) -> Option<EvalInfo> {
+        self.with(|n| n.eval.clone())
+    }
+
+    pub fn set_eval_info(&mut self, eval_next: Option<EvalInfo>) -> Option<EvalInfo> {
+        self.with_mut(|n| std::mem::replace(&mut n.eval, eval_next))
+    }
+
+    /// Returns the arrows and highlighted squares drawn at the given node.
+    ///
+    /// These are read from and written as `[%cal]` and `[%csl]` comment
+    /// commands.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sacrifice::game::{Shape, ShapeColor};
+    /// use sacrifice::Square;
+    ///
+    /// let game = sacrifice::read_pgn("1. e4 { [%csl Gd5] [%cal Re4d5,Gg1f3] } 1... d5").unwrap();
+    /// let mut e4_node = game.root().mainline().unwrap();
+    /// assert_eq!(e4_node.shapes()[0], Shape::circle(ShapeColor::Green, Square::D5));
+    /// assert_eq!(e4_node.shapes()[1], Shape::arrow(ShapeColor::Red, Square::E4, Square::D5));
+    ///
+    /// e4_node.set_shapes(vec![Shape::arrow(ShapeColor::Blue, Square::D1, Square::H5)]);
+    /// assert_eq!(game.movetext(), "1. e4 { [%cal Bd1h5] } 1... d5");
+    /// ```
+    pub fn shapes(&self) -> Vec<Shape> {
+        self.with(|n| n.shape_vec.clone())
+    }
+
+    pub fn set_shapes(&mut self, shapes_next: Vec<Shape>) -> Vec<Shape> {
+        self.with_mut(|n| std::mem::replace(&mut n.shape_vec, shapes_next))
+    }
+
+    /// Returns the application data stored on the given node under `key`.
+    ///
+    /// Metadata is not part of PGN; it is only written and read as
+    /// `[%key value]` comment commands when the key is listed in
+    /// `PgnWriterOptions::meta_commands` or `ReaderOptions::meta_commands`.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - name of the entry
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let game = sacrifice::read_pgn("1. e4 e5").unwrap();
+    /// let mut e4_node = game.root().mainline().unwrap();
+    /// assert!(e4_node.meta("reviewed").is_none());
+    /// e4_node.set_meta("reviewed", Some("yes".to_string()));
+    /// assert_eq!(e4_node.meta("reviewed"), Some("yes".to_string()));
+    /// assert_eq!(game.movetext(), "1. e4 e5"); // not written by default
+    /// ```
+    pub fn meta(&self, key: &str) -> Option<String> {
+        self.with(|n| n.meta_map.get(key).cloned())
+    }
+
+    /// Stores application data on the given node under `key`, or removes
+    /// the entry if `value_next` is `None`.
+    ///
+    /// Returns the previous value.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - name of the entry
+    /// * `value_next` - new value of the entry
+    pub fn set_meta(&mut self, key: &str, value_next: Option<String>) -> Option<String> {
+        self.with_mut(|n| match value_next {
+            Some(value) => n.meta_map.insert(key.to_string(), value),
+            None => n.meta_map.remove(key),
+        })
+    }
+
+    /// Returns all application data stored on the given node, by key.
+    pub fn meta_map(&self) -> BTreeMap<String, String> {
+        self.with(|n| n.meta_map.clone())
+    }
+
+    fn replace_comments(
+        &self,
+        placement: CommentPlacement,
+        text_next: Option<String>,
+    ) -> Option<String> {
+        self.with_mut(|n| {
+            let comment_vec = &mut n.comment_vec;
+            let prev = join_comments(comment_vec, placement);
+
+            // Keep the new comment where the old ones were
+            let index = comment_vec
+                .iter()
+                .position(|c| c.placement == placement)
+                .unwrap_or(match placement {
+                    CommentPlacement::BeforeMove => 0,
+                    CommentPlacement::AfterMove => comment_vec.len(),
+                });
+            comment_vec.retain(|c| c.placement != placement);
+            if let Some(text) = text_next {
+                let index = index.min(comment_vec.len());
+                comment_vec.insert(index, Comment { text, placement });
+            }
+
+            prev
+        })
     }
 }
 
@@ -289,21 +1107,107 @@ impl Node {
         self.set_nags(HashSet::new());
     }
 
-    /// Returns the mainline variation of the given node.
-    ///
-    /// # Arguments
-    ///
-    /// * `node_id` - id of the given node
+    /// Returns the mainline variation of the given node.
+    ///
+    /// # Arguments
+    ///
+    /// * `node_id` - id of the given node
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let game = sacrifice::read_pgn("1. e4 e5").unwrap();
+    /// let mainline_node_1 = game.root().mainline(); // 1. e4 node
+    /// assert!(mainline_node_1.is_some()); // It exists
+    /// ```
+    pub fn mainline(&self) -> Option<Self> {
+        self.variation_vec().first().cloned()
+    }
+
+    /// Returns the child reached by the given move, if the tree has it.
+    ///
+    /// # Arguments
+    ///
+    /// * `m` - the move to look for
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let game = sacrifice::read_pgn("1. e4 (1. d4) 1... e5").unwrap();
+    /// let d4_node = game.root().other_variations()[0].clone();
+    /// assert_eq!(game.root().child_by_move(&d4_node.prev_move().unwrap()), Some(d4_node));
+    /// ```
+    pub fn child_by_move(&self, m: &Move) -> Option<Self> {
+        self.variation_vec()
+            .into_iter()
+            .find(|child| child.prev_move().as_ref() == Some(m))
+    }
+
+    /// Returns the child reached by the given move in SAN, if the tree has
+    /// it.
+    ///
+    /// # Arguments
+    ///
+    /// * `san` - the move to look for, e.g. `Nf3`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let game = sacrifice::read_pgn("1. e4 (1. d4) 1... e5").unwrap();
+    /// let d4_node = game.root().child_by_san("d4").unwrap();
+    /// assert_eq!(d4_node, game.root().other_variations()[0]);
+    /// assert!(game.root().child_by_san("c4").is_none());
+    /// assert!(game.root().child_by_san("Ke2").is_none()); // illegal
+    /// ```
+    pub fn child_by_san(&self, san: &str) -> Option<Self> {
+        let m = self.parse_san(san)?;
+        self.child_by_move(&m)
+    }
+
+    /// Returns whether the given node lies on the main line of the game,
+    /// i.e. it and each of its ancestors is the mainline of its parent.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let game = sacrifice::read_pgn("1. e4 e5 (1... c5 2. Nf3) 2. Nf3 *").unwrap();
+    /// assert!(game.root().is_mainline());
+    /// assert!(game.node_at(&["e4", "e5", "Nf3"]).unwrap().is_mainline());
+    /// assert!(!game.node_at(&["e4", "c5", "Nf3"]).unwrap().is_mainline());
+    /// ```
+    pub fn is_mainline(&self) -> bool {
+        let mut node = self.clone();
+        while let Some(parent) = node.parent() {
+            if parent.mainline().as_ref() != Some(&node) {
+                return false;
+            }
+            node = parent;
+        }
+        true
+    }
+
+    /// Returns how deeply the variation containing the given node is
+    /// nested: `0` on the main line, `1` in a variation of it, and so on.
     ///
     /// # Examples
     ///
     /// ```
-    /// let game = sacrifice::read_pgn("1. e4 e5");
-    /// let mainline_node_1 = game.root().mainline(); // 1. e4 node
-    /// assert!(mainline_node_1.is_some()); // It exists
+    /// let game = sacrifice::read_pgn("1. e4 e5 (1... c5 2. Nf3 (2. c3 d5)) 2. Nf3 *").unwrap();
+    /// assert_eq!(game.node_at(&["e4", "e5", "Nf3"]).unwrap().variation_depth(), 0);
+    /// assert_eq!(game.node_at(&["e4", "c5", "Nf3"]).unwrap().variation_depth(), 1);
+    /// assert_eq!(game.node_at(&["e4", "c5", "c3", "d5"]).unwrap().variation_depth(), 2);
     /// ```
-    pub fn mainline(&self) -> Option<Self> {
-        self.variation_vec().get(0).cloned()
+    pub fn variation_depth(&self) -> u32 {
+        let mut result: u32 = 0;
+
+        let mut node = self.clone();
+        while let Some(parent) = node.parent() {
+            if parent.mainline().as_ref() != Some(&node) {
+                result += 1;
+            }
+            node = parent;
+        }
+        result
     }
 
     /// Returns variations (excluding mainline) of the given node.
@@ -317,7 +1221,7 @@ impl Node {
     /// # Examples
     ///
     /// ```
-    /// let game = sacrifice::read_pgn("1. e4 (1. d4) 1... e5");
+    /// let game = sacrifice::read_pgn("1. e4 (1. d4) 1... e5").unwrap();
     /// let variation_nodes_1 = game.root().other_variations(); // [1. d4]
     /// assert!(!variation_nodes_1.is_empty()); // It exists
     /// ```
@@ -342,7 +1246,7 @@ impl Node {
     /// # Examples
     ///
     /// ```
-    /// let game = sacrifice::read_pgn("1. e4 (1. d4) 1... e5");
+    /// let game = sacrifice::read_pgn("1. e4 (1. d4) 1... e5").unwrap();
     /// let root = game.root();
     /// let e4_node = root.mainline().expect("e4 node should exist");
     /// let e4_siblings = e4_node.siblings();
@@ -362,6 +1266,9 @@ impl Node {
 
     /// Add a move to a given node in the game tree.
     ///
+    /// If the node already has a child for this move, that child is
+    /// returned instead of adding a duplicate.
+    ///
     /// Returns `None` if the move is illegal, or if given node is not found in the tree.
     ///
     /// # Arguments
@@ -372,7 +1279,7 @@ impl Node {
     /// # Examples
     ///
     /// ```
-    /// let mut game = sacrifice::read_pgn("1. d4");
+    /// let mut game = sacrifice::read_pgn("1. d4").unwrap();
     /// let mut mainline_node_1 = game.root().mainline().unwrap();
     /// let illegal_move = sacrifice::Move::Normal {
     ///    role: sacrifice::Role::Queen,
@@ -389,14 +1296,77 @@ impl Node {
     ///    capture: None,
     ///    promotion: None,
     /// };
-    /// let new_node = mainline_node_1.new_variation(legal_move);
+    /// let new_node = mainline_node_1.new_variation(legal_move.clone());
     /// assert!(new_node.is_some());
     /// assert_eq!(
     ///   mainline_node_1.mainline().unwrap(),
-    ///   new_node.unwrap()
+    ///   new_node.clone().unwrap()
     /// );
+    /// // Playing the same move again does not fork the tree
+    /// assert_eq!(mainline_node_1.new_variation(legal_move), new_node);
+    /// assert_eq!(mainline_node_1.variation_vec().len(), 1);
     /// ```
     pub fn new_variation(&mut self, move_next: Move) -> Option<Self> {
+        if let Some(child) = self.child_by_move(&move_next) {
+            return Some(child);
+        }
+        self.push_variation(move_next)
+    }
+
+    /// Plays a move written in SAN, adding it as a child of the given node
+    /// or returning the existing child for it.
+    ///
+    /// Returns `None` if the move cannot be parsed or is illegal.
+    ///
+    /// # Arguments
+    ///
+    /// * `san` - the move, e.g. `Nf3`, `exd5` or `O-O`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let game = sacrifice::read_pgn("1. e4 c5").unwrap();
+    /// let mut c5_node = game.root().mainline().unwrap().mainline().unwrap();
+    /// let nf3_node = c5_node.play_san("Nf3").unwrap();
+    /// assert_eq!(game.movetext(), "1. e4 c5 2. Nf3");
+    /// assert_eq!(c5_node.play_san("Nf3+"), Some(nf3_node)); // suffixes are ignored
+    /// assert!(c5_node.play_san("Nf6").is_none());
+    /// ```
+    pub fn play_san(&mut self, san: &str) -> Option<Self> {
+        let m = self.parse_san(san)?;
+        self.new_variation(m)
+    }
+
+    /// Plays a move written in UCI notation, adding it as a child of the
+    /// given node or returning the existing child for it.
+    ///
+    /// Castling may be written as the king's move (`e1g1`) or as king
+    /// takes rook (`e1h1`).
+    ///
+    /// Returns `None` if the move cannot be parsed or is illegal.
+    ///
+    /// # Arguments
+    ///
+    /// * `uci` - the move, e.g. `g1f3` or `e7e8q`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let game = sacrifice::read_pgn("1. e4 e5 2. Nf3 Nc6 3. Bc4 Bc5").unwrap();
+    /// let mut bc5_node = game.root().mainline_iter().last().unwrap();
+    /// let castle_node = bc5_node.play_uci("e1g1").unwrap();
+    /// assert_eq!(castle_node.san().unwrap().to_string(), "O-O");
+    /// assert_eq!(bc5_node.play_uci("e1h1"), Some(castle_node));
+    /// assert!(bc5_node.play_uci("e1c1").is_none());
+    /// ```
+    pub fn play_uci(&mut self, uci: &str) -> Option<Self> {
+        let m = uci.parse::<Uci>().ok()?.to_move(&self.position()).ok()?;
+        self.new_variation(m)
+    }
+
+    /// Adds a move as a new child, even if another child has the same
+    /// move, so that trees read from PGN keep duplicate lines as written.
+    pub(crate) fn push_variation(&mut self, move_next: Move) -> Option<Self> {
         let node_next = Self::from_node(self.clone(), move_next)?;
         let mut variation_vec = self.variation_vec();
         variation_vec.push(node_next.clone());
@@ -404,6 +1374,37 @@ impl Node {
         Some(node_next)
     }
 
+    /// Adds a move as a new child with the given id, so that a stored tree
+    /// can be rebuilt with its original ids. Unused ids below it are
+    /// filled with nodes outside the tree.
+    ///
+    /// Returns `None` if the move is illegal or the id is already taken.
+    #[cfg(feature = "serde")]
+    pub(crate) fn push_variation_with_id(&mut self, move_next: Move, id: u64) -> Option<Self> {
+        let index = usize::try_from(id).ok()?;
+        let node_impl = self.child_impl(move_next)?;
+
+        let mut arena = self.arena.borrow_mut();
+        if index < arena.node_vec.len() {
+            // Only filler nodes lack a parent, besides the root
+            let slot = &mut arena.node_vec[index];
+            if index == 0 || slot.parent.is_some() {
+                return None;
+            }
+            *slot = node_impl;
+        } else {
+            arena.node_vec.resize_with(index, NodeImpl::default);
+            arena.node_vec.push(node_impl);
+        }
+        drop(arena);
+
+        let node_next = self.handle(index);
+        let mut variation_vec = self.variation_vec();
+        variation_vec.push(node_next.clone());
+        self.set_variation_vec(variation_vec);
+        Some(node_next)
+    }
+
     pub fn remove_variation(&mut self, node: Self) -> bool {
         let mut variation_vec = self.variation_vec();
         let variations_size = variation_vec.len();
@@ -422,7 +1423,7 @@ impl Node {
     /// # Examples
     ///
     /// ```
-    /// let mut game = sacrifice::read_pgn("1. d4 (1. e4) 1... d5");
+    /// let mut game = sacrifice::read_pgn("1. d4 (1. e4) 1... d5").unwrap();
     /// let variation_node_1_0 = game.root().other_variations()[0].clone(); // (1. e4)
     /// assert!(
     ///   game.root().promote_variation(variation_node_1_0.clone()), // promote 1. e4 to mainline
@@ -447,9 +1448,387 @@ impl Node {
 
         true
     }
+
+    /// Moves a node one place down in the variations of its parent; the
+    /// inverse of [`Node::promote_variation`] for the mainline.
+    ///
+    /// Returns `false` if the node is not a child, or is already the last
+    /// variation.
+    ///
+    /// # Arguments
+    ///
+    /// * `node` - the child node to demote
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let game = sacrifice::read_pgn("1. d4 (1. e4) (1. c4) 1... d5").unwrap();
+    /// let d4_node = game.root().mainline().unwrap();
+    /// assert!(game.root().demote_variation(d4_node.clone()));
+    /// assert_eq!(game.movetext(), "1. e4 ( 1. d4 d5 ) ( 1. c4 )");
+    /// assert!(game.root().demote_variation(d4_node.clone()));
+    /// assert!(!game.root().demote_variation(d4_node));
+    /// ```
+    pub fn demote_variation(&mut self, node: Self) -> bool {
+        let mut variation_vec = self.variation_vec();
+        let index = if let Some(val) = variation_vec.iter().position(|v| v == &node) {
+            val
+        } else {
+            return false;
+        };
+        if index + 1 == variation_vec.len() {
+            return false;
+        }
+
+        variation_vec.swap(index, index + 1);
+        self.set_variation_vec(variation_vec);
+        true
+    }
+
+    /// Moves the variation at index `from` so that it ends up at index `to`,
+    /// shifting the ones in between; index `0` is the mainline.
+    ///
+    /// Returns `false` if either index is out of range.
+    ///
+    /// # Arguments
+    ///
+    /// * `from` - current index of the variation
+    /// * `to` - new index of the variation
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let game = sacrifice::read_pgn("1. d4 (1. e4) (1. c4) (1. Nf3) *").unwrap();
+    /// assert!(game.root().move_variation(3, 1));
+    /// assert_eq!(game.movetext(), "1. d4 ( 1. Nf3 ) ( 1. e4 ) ( 1. c4 )");
+    /// assert!(!game.root().move_variation(0, 4));
+    /// ```
+    pub fn move_variation(&mut self, from: usize, to: usize) -> bool {
+        let mut variation_vec = self.variation_vec();
+        if from >= variation_vec.len() || to >= variation_vec.len() {
+            return false;
+        }
+
+        let node = variation_vec.remove(from);
+        variation_vec.insert(to, node);
+        self.set_variation_vec(variation_vec);
+        true
+    }
+
+    /// Sorts the variations of the given node, mainline included, with a
+    /// comparator. The sort is stable.
+    ///
+    /// # Arguments
+    ///
+    /// * `compare` - ordering of two child nodes
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// // Longest line first
+    /// let game = sacrifice::read_pgn("1. d4 (1. e4 e5 2. Nf3) (1. c4 e5) *").unwrap();
+    /// game.root().sort_variations_by(|a, b| {
+    ///     b.mainline_iter().count().cmp(&a.mainline_iter().count())
+    /// });
+    /// assert_eq!(game.movetext(), "1. e4 ( 1. c4 e5 ) ( 1. d4 ) 1... e5 2. Nf3");
+    /// ```
+    pub fn sort_variations_by<F>(&mut self, mut compare: F)
+    where
+        F: FnMut(&Self, &Self) -> std::cmp::Ordering,
+    {
+        let mut variation_vec = self.variation_vec();
+        variation_vec.sort_by(|a, b| compare(a, b));
+        self.set_variation_vec(variation_vec);
+    }
+
+    /// Moves the given node one place down in the variations of its
+    /// parent.
+    ///
+    /// Returns `false` for the root node, or if the node is already the
+    /// last variation.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let game = sacrifice::read_pgn("1. e4 e5 (1... c5) 2. Nf3 *").unwrap();
+    /// let mut e5_node = game.root().mainline().unwrap().mainline().unwrap();
+    /// assert!(e5_node.demote());
+    /// assert_eq!(game.movetext(), "1. e4 c5 ( 1... e5 2. Nf3 )");
+    /// ```
+    pub fn demote(&mut self) -> bool {
+        match self.parent() {
+            Some(mut parent) => parent.demote_variation(self.clone()),
+            None => false,
+        }
+    }
+
+    /// Promotes the given node and each of its ancestors to the mainline
+    /// variation of their parents, making the line leading to it the main
+    /// line of the game.
+    ///
+    /// Returns `false` if the node already was on the main line, or was
+    /// removed from the tree.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let game = sacrifice::read_pgn("1. e4 e5 (1... c5 2. Nf3 (2. c3) 2... d6) 2. Nf3 *").unwrap();
+    /// let sicilian_node = game.root().mainline().unwrap().other_variations()[0].clone();
+    /// let mut c3_node = sicilian_node.other_variations()[0].clone();
+    /// assert!(c3_node.promote_to_mainline());
+    /// assert_eq!(game.movetext(), "1. e4 c5 ( 1... e5 2. Nf3 ) 2. c3 ( 2. Nf3 d6 )");
+    /// assert!(!c3_node.promote_to_mainline());
+    /// ```
+    pub fn promote_to_mainline(&mut self) -> bool {
+        // Check the whole line first, so that nothing changes on failure
+        let mut line_vec = Vec::new();
+        let mut node = self.clone();
+        while let Some(parent) = node.parent() {
+            let variation_vec = parent.variation_vec();
+            if !variation_vec.contains(&node) {
+                return false;
+            }
+            if variation_vec[0] != node {
+                line_vec.push((parent.clone(), node));
+            }
+            node = parent;
+        }
+        if line_vec.is_empty() {
+            return false;
+        }
+
+        self.edit_group(|| {
+            for (mut parent, node) in line_vec {
+                parent.promote_variation(node);
+            }
+        });
+        true
+    }
+
+    /// Sets the position of the given node and plays every move after it
+    /// again from there, by its squares as in UCI.
+    ///
+    /// Lines whose first move is now illegal are removed; returns the
+    /// first node of each, which keeps its SAN from before.
+    pub(crate) fn replay_from(&mut self, position: VariantPosition) -> Vec<Self> {
+        self.edit_group(|| {
+            let mut removed_vec = Vec::new();
+
+            let mut node_vec = vec![(self.clone(), position)];
+            while let Some((mut node, position)) = node_vec.pop() {
+                let child_vec = node.variation_vec();
+                for child in &child_vec {
+                    child.san(); // Cached from the old position
+                }
+                node.with_mut(|n| n.position = position.clone());
+
+                let mode = position.castles().mode();
+                let child_count = child_vec.len();
+                let mut kept_vec = Vec::new();
+                for child in child_vec {
+                    let m = child
+                        .prev_move()
+                        .and_then(|m| m.to_uci(mode).to_move(&position).ok());
+                    let position_next = m.as_ref().and_then(|m| position.clone().play(m).ok());
+                    let (m, position_next) = if let Some(val) = m.zip(position_next) {
+                        val
+                    } else {
+                        removed_vec.push(child);
+                        continue;
+                    };
+
+                    child.with_mut(|n| {
+                        if let Some(parent) = n.parent.as_mut() {
+                            parent.move_next = m;
+                            parent.san = OnceLock::new();
+                        }
+                    });
+                    kept_vec.push(child.clone());
+                    node_vec.push((child, position_next));
+                }
+                if kept_vec.len() < child_count {
+                    node.set_variation_vec(kept_vec);
+                }
+            }
+
+            removed_vec
+        })
+    }
 }
 
 impl Node {
+    /// Returns a new game starting from the given node's position, with
+    /// the subtree below it as its moves.
+    ///
+    /// The starting position is recorded in the `SetUp` and `FEN` headers,
+    /// and the comments after the node's move become the game comment.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let game = sacrifice::read_pgn("1. e4 e5 { Open game } 2. Nf3 (2. f4) 2... Nc6 *").unwrap();
+    /// let e5_node = game.root().mainline().unwrap().mainline().unwrap();
+    /// let practice = e5_node.to_game();
+    /// assert_eq!(
+    ///   practice.opt_headers.get("FEN").unwrap(),
+    ///   "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 2"
+    /// );
+    /// assert!(practice.to_string().ends_with("{ Open game } 2. Nf3 ( 2. f4 ) 2... Nc6 *\n"));
+    /// ```
+    pub fn to_game(&self) -> Game {
+        let mut game = Self::game_from_position(self.position());
+
+        let game_comment_vec = self
+            .comments()
+            .into_iter()
+            .filter(|c| c.placement == CommentPlacement::AfterMove)
+            .collect();
+        game.root.set_comments(game_comment_vec);
+        Self::copy_variations(self, &mut game.root);
+
+        game
+    }
+
+    /// Removes the given node, with everything below it, from the tree.
+    ///
+    /// Returns the removed branch as a new game starting from the parent's
+    /// position, recorded in the `SetUp` and `FEN` headers, so it can be
+    /// pasted elsewhere with [`Node::graft`]. Returns `None` for the root
+    /// node.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let game = sacrifice::read_pgn("1. e4 e5 (1... c5 2. Nf3) *").unwrap();
+    /// let mut sicilian_node = game.root().mainline().unwrap().other_variations()[0].clone();
+    /// let sicilian = sicilian_node.detach().unwrap();
+    /// assert_eq!(
+    ///     sicilian.opt_headers.get("FEN").unwrap(),
+    ///     "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1"
+    /// );
+    /// assert_eq!(sicilian.movetext(), "1... c5 2. Nf3");
+    /// assert_eq!(game.movetext(), "1. e4 e5");
+    /// ```
+    pub fn detach(&mut self) -> Option<Game> {
+        let mut parent = self.parent()?;
+
+        let mut game = Self::game_from_position(parent.position());
+        let mut dst = game.root.new_variation(self.prev_move()?)?;
+        Self::copy_annotations(self, &mut dst);
+        Self::copy_variations(self, &mut dst);
+
+        parent.remove_variation(self.clone());
+        Some(game)
+    }
+
+    /// Attaches the moves of a game as variations of the given node.
+    ///
+    /// The moves are replayed from the node's position, so a branch can
+    /// be moved anywhere its moves are legal. Comments, NAGs, clocks and
+    /// evaluations are kept. Moves the tree already has are not added
+    /// again: their nodes are reused, with annotations combined as by
+    /// [`Game::merge`] with [`MergePolicy::Combine`].
+    ///
+    /// Returns the children reached by the first moves of the game, or
+    /// `None` without changing the tree if any move is illegal at its new
+    /// place.
+    ///
+    /// # Arguments
+    ///
+    /// * `subtree` - e.g. a branch returned by [`Node::detach`]
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let game = sacrifice::read_pgn("1. e4 e5 2. Nf3 (2. Bc4 Nf6) 2... Nc6 *").unwrap();
+    /// let mut nf3_node = game.root().mainline().unwrap().mainline().unwrap().mainline().unwrap();
+    /// let bishop = nf3_node.siblings()[0].clone().detach().unwrap();
+    /// assert!(nf3_node.graft(&bishop).is_none()); // Black to move
+    ///
+    /// let mut nc6_node = nf3_node.mainline().unwrap();
+    /// let new_node_vec = nc6_node.graft(&bishop).unwrap();
+    /// assert_eq!(new_node_vec.len(), 1);
+    /// assert_eq!(game.movetext(), "1. e4 e5 2. Nf3 Nc6 3. Bc4 Nf6");
+    /// ```
+    pub fn graft(&mut self, subtree: &Game) -> Option<Vec<Self>> {
+        // Replay into a scratch tree first, so nothing is attached on failure
+        let scratch = Self::from_position(self.position());
+        let mut node_vec = vec![(subtree.root(), scratch.clone())];
+        while let Some((src, mut dst)) = node_vec.pop() {
+            for src_child in src.variation_vec() {
+                let m = src_child
+                    .prev_move()?
+                    .to_uci(dst.position().castles().mode())
+                    .to_move(&dst.position())
+                    .ok()?;
+                let dst_child = Self::graft_child(&mut dst, &src_child, m)?;
+                node_vec.push((src_child, dst_child));
+            }
+        }
+
+        self.edit_group(|| Self::copy_variations(&scratch, &mut self.clone()));
+        scratch
+            .variation_vec()
+            .iter()
+            .map(|child| self.child_by_move(&child.prev_move()?))
+            .collect()
+    }
+
+    /// Adds a copy of `src_child` to `dst`, or merges it into the child
+    /// that `dst` already has for the same move.
+    fn graft_child(dst: &mut Self, src_child: &Self, m: Move) -> Option<Self> {
+        if let Some(mut dst_child) = dst.child_by_move(&m) {
+            merge_annotations(src_child, &mut dst_child, MergePolicy::Combine);
+            return Some(dst_child);
+        }
+        let mut dst_child = dst.push_variation(m)?;
+        Self::copy_annotations(src_child, &mut dst_child);
+        Some(dst_child)
+    }
+
+    fn game_from_position(position: VariantPosition) -> Game {
+        let mut game = Game {
+            root: Self::from_position(position.clone()),
+            ..Game::default()
+        };
+        game.set_position_tags(&position);
+        // A subtree starts from a set-up position even if it is the
+        // initial one
+        if !game.opt_headers.contains_key("FEN") {
+            let fen = Fen::from_position(position, EnPassantMode::Legal);
+            game.opt_headers
+                .insert("SetUp".to_string(), "1".to_string());
+            game.opt_headers.insert("FEN".to_string(), fen.to_string());
+        }
+        game
+    }
+
+    fn copy_annotations(src: &Self, dst: &mut Self) {
+        dst.set_comments(src.comments());
+        if let Some(nags) = src.nags() {
+            dst.set_nags(nags);
+        }
+        dst.set_clock(src.clock());
+        dst.set_emt(src.emt());
+        dst.set_eval_info(src.eval_info());
+        dst.set_shapes(src.shapes());
+        let meta_map = src.meta_map();
+        dst.with_mut(|n| n.meta_map = meta_map);
+    }
+
+    fn copy_variations(src: &Self, dst: &mut Self) {
+        for src_child in src.variation_vec() {
+            let m = src_child.prev_move().unwrap();
+            let mut dst_child = if let Some(val) = Self::graft_child(dst, &src_child, m) {
+                val
+            } else {
+                continue; // Same position, so this never happens
+            };
+
+            Self::copy_variations(&src_child, &mut dst_child);
+        }
+    }
+
     pub fn root(&self) -> Self {
         let mut node = self.clone();
         while let Some(parent) = node.parent() {
@@ -458,6 +1837,61 @@ impl Node {
         node
     }
 
+    /// Returns the number of half-moves played before the given node's
+    /// position, counted from the standard start, so that games from a FEN
+    /// continue the FEN's move counter.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let game = sacrifice::read_pgn(
+    ///     "[SetUp \"1\"]\n[FEN \"4k3/8/8/8/8/8/4P3/4K3 b - - 0 12\"]\n\n12... Kd7 13. e4 *"
+    /// ).unwrap();
+    /// let kd7_node = game.root().mainline().unwrap();
+    /// assert_eq!(game.root().ply(), 23);
+    /// assert_eq!(kd7_node.ply(), 24);
+    /// assert_eq!(kd7_node.depth(), 1);
+    /// ```
+    pub fn ply(&self) -> u32 {
+        self.with(|n| {
+            let fullmoves = n.position.fullmoves().get();
+            (fullmoves - 1) * 2 + u32::from(n.position.turn() == Color::Black)
+        })
+    }
+
+    /// Returns the number of the move leading to the given node, as
+    /// written before it in PGN, or `None` for the root node.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sacrifice::Color;
+    ///
+    /// let game = sacrifice::read_pgn(
+    ///     "[SetUp \"1\"]\n[FEN \"4k3/8/8/8/8/8/4P3/4K3 b - - 0 12\"]\n\n12... Kd7 13. e4 *"
+    /// ).unwrap();
+    /// let kd7_node = game.root().mainline().unwrap();
+    /// assert_eq!(kd7_node.move_number(), Some(12));
+    /// assert_eq!(kd7_node.turn(), Color::White); // so Black played it
+    /// assert_eq!(kd7_node.mainline().unwrap().move_number(), Some(13));
+    /// assert!(game.root().move_number().is_none());
+    /// ```
+    pub fn move_number(&self) -> Option<u32> {
+        self.parent()?;
+        self.with(|n| {
+            let fullmoves = n.position.fullmoves().get();
+            Some(match n.position.turn() {
+                Color::White => fullmoves - 1,
+                Color::Black => fullmoves,
+            })
+        })
+    }
+
+    /// Returns the side to move in the given node's position.
+    pub fn turn(&self) -> Color {
+        self.with(|n| n.position.turn())
+    }
+
     pub fn depth(&self) -> u32 {
         let mut result: u32 = 0;
 
@@ -478,7 +1912,7 @@ impl Node {
     /// # Examples
     ///
     /// ```
-    /// let game = sacrifice::read_pgn("1. e4 c5");
+    /// let game = sacrifice::read_pgn("1. e4 c5").unwrap();
     /// let mainline_node_1 = game.root().mainline().unwrap(); // 1. e4
     /// let mainline_node_2 = mainline_node_1.mainline().unwrap(); // 1... c5
     /// let moves = mainline_node_2.moves(); // 1. e4 c5
@@ -510,18 +1944,133 @@ impl Node {
     /// # Examples
     ///
     /// ```
-    /// let game = sacrifice::read_pgn("1. e4 c5");
+    /// let game = sacrifice::read_pgn("1. e4 c5").unwrap();
     /// let mainline_node_1 = game.root().mainline().unwrap(); // 1. e4
     /// let mainline_node_2 = mainline_node_1.mainline().unwrap(); // 1... c5
     /// let fen: sacrifice::Fen = "rnbqkbnr/pp1ppppp/8/2p5/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 2".parse().unwrap();
     /// let actual_position: sacrifice::Chess = fen.clone().into_position(sacrifice::CastlingMode::Standard).unwrap();
     /// assert_eq!(
     ///   mainline_node_2.position(),
-    ///   actual_position
+    ///   actual_position.into()
     /// )
     /// ```
-    pub fn position(&self) -> Chess {
-        self.0.borrow().position.clone()
+    pub fn position(&self) -> VariantPosition {
+        self.with(|n| n.position.clone())
+    }
+
+    /// Returns the outcome if the game is over at the given node, by
+    /// checkmate, stalemate, insufficient material or a rule of the
+    /// variant.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sacrifice::{Color, Outcome};
+    ///
+    /// let game = sacrifice::read_pgn("1. f3 e5 2. g4 Qh4#").unwrap();
+    /// let mate_node = game.root().mainline_iter().last().unwrap();
+    /// assert_eq!(mate_node.outcome(), Some(Outcome::Decisive { winner: Color::Black }));
+    /// assert_eq!(mate_node.outcome().unwrap().to_string(), "0-1");
+    /// assert!(game.root().outcome().is_none());
+    /// ```
+    pub fn outcome(&self) -> Option<Outcome> {
+        self.with(|n| n.position.outcome())
+    }
+
+    /// Returns the pieces in hand of both sides at the given node, or
+    /// `None` if the variant has no pockets. Only Crazyhouse has them.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sacrifice::{Color, Role};
+    ///
+    /// let game = sacrifice::read_pgn(
+    ///     "[Variant \"Crazyhouse\"]\n\n1. e4 d5 2. exd5 Qxd5 3. Nc3 Qa5 4. P@d4 *"
+    /// ).unwrap();
+    /// let qxd5_node = game.node_at(&["e4", "d5", "exd5", "Qxd5"]).unwrap();
+    /// let pockets = qxd5_node.pockets().unwrap();
+    /// assert_eq!(*pockets.get(Color::White).get(Role::Pawn), 1);
+    /// assert_eq!(*pockets.get(Color::Black).get(Role::Pawn), 1);
+    ///
+    /// let drop_node = game.root().mainline_iter().last().unwrap();
+    /// assert_eq!(drop_node.uci().unwrap().to_string(), "P@d4");
+    /// assert_eq!(*drop_node.pockets().unwrap().get(Color::White).get(Role::Pawn), 0);
+    /// assert!(game.root().mainline().unwrap().pockets().is_some());
+    ///
+    /// let game = sacrifice::read_pgn("1. e4 *").unwrap();
+    /// assert!(game.root().pockets().is_none());
+    /// ```
+    pub fn pockets(&self) -> Option<ByColor<ByRole<u8>>> {
+        self.with(|n| n.position.pockets().cloned())
+    }
+
+    /// Returns the FEN of the position at the given node, with its move
+    /// counters. An en passant square is only given if the capture is
+    /// legal.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let game = sacrifice::read_pgn("1. e4 c5 2. Nf3").unwrap();
+    /// let nf3_node = game.root().mainline_iter().last().unwrap();
+    /// assert_eq!(
+    ///     nf3_node.fen(),
+    ///     "rnbqkbnr/pp1ppppp/8/2p5/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2"
+    /// );
+    /// assert_eq!(
+    ///     nf3_node.fen_before().unwrap(),
+    ///     "rnbqkbnr/pp1ppppp/8/2p5/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 2"
+    /// );
+    /// assert!(game.root().fen_before().is_none());
+    /// ```
+    pub fn fen(&self) -> String {
+        Fen::from_position(self.position(), EnPassantMode::Legal).to_string()
+    }
+
+    /// Returns the FEN of the position before the move leading to the
+    /// given node, or `None` for the root node.
+    pub fn fen_before(&self) -> Option<String> {
+        Some(self.parent()?.fen())
+    }
+
+    /// Returns the Zobrist hash of the position at the given node.
+    ///
+    /// The hash covers the pieces, side to move, castling rights and legal
+    /// en passant captures, but not the move counters, so that it is the
+    /// same for repeated positions and transpositions. It is stable across
+    /// releases.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let game = sacrifice::read_pgn("1. Nf3 Nf6 2. Ng1 Ng8").unwrap();
+    /// let ng8_node = game.root().mainline_iter().last().unwrap();
+    /// assert_eq!(game.root().zobrist(), 0x463b96181691fc9c);
+    /// assert_eq!(ng8_node.zobrist(), game.root().zobrist());
+    /// ```
+    pub fn zobrist(&self) -> u64 {
+        let hash: Zobrist64 = self.with(|n| n.position.zobrist_hash(EnPassantMode::Legal));
+        hash.0
+    }
+
+    /// Returns the key of the position at the given node in Polyglot
+    /// opening books.
+    ///
+    /// It differs from [`Node::zobrist`] only in counting en passant
+    /// captures whenever a pawn stands next to the pawn that advanced two
+    /// squares, even if the capture is illegal.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let game = sacrifice::read_pgn("1. e4 d5 2. e5 f5").unwrap();
+    /// let f5_node = game.root().mainline_iter().last().unwrap();
+    /// assert_eq!(f5_node.polyglot_key(), 0x22a48b5a8e47ff78);
+    /// ```
+    pub fn polyglot_key(&self) -> u64 {
+        let hash: Zobrist64 = self.with(|n| n.position.zobrist_hash(EnPassantMode::PseudoLegal));
+        hash.0
     }
 
     /// Remove all occurrences of the given node from the game tree.
@@ -535,7 +2084,7 @@ impl Node {
     /// # Examples
     ///
     /// ```
-    /// let mut game = sacrifice::read_pgn("1. d4");
+    /// let mut game = sacrifice::read_pgn("1. d4").unwrap();
     /// let mut mainline_node_1 = game.root().mainline().unwrap();
     /// assert!(mainline_node_1.remove_node().is_some()); // No child nodes left
     /// assert!(game.root().mainline().is_none());
@@ -558,4 +2107,20 @@ impl Node {
 
         None
     }
+
+    /// Removes every move after the given node, in all variations.
+    ///
+    /// Returns the removed children.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let game = sacrifice::read_pgn("1. e4 e5 (1... c5) 2. Nf3 Nc6 *").unwrap();
+    /// let mut e4_node = game.root().mainline().unwrap();
+    /// assert_eq!(e4_node.truncate().len(), 2);
+    /// assert_eq!(game.movetext(), "1. e4");
+    /// ```
+    pub fn truncate(&mut self) -> Vec<Node> {
+        self.set_variation_vec(Vec::new())
+    }
 }
@@ -0,0 +1,66 @@
+use super::{Comment, Game, Node};
+
+impl Game {
+    /// Comments the opening name where each line leaves the book, like
+    /// lichess analysis exports.
+    ///
+    /// `classify` returns the name of the opening reached at a node, such
+    /// as `C65 Ruy Lopez: Berlin Defense`, or `None` if the node is out of
+    /// book. The crate has no opening book of its own. The name is added
+    /// after the move of every book node none of whose variations are in
+    /// book, unless it already has that comment.
+    ///
+    /// Returns the number of comments added.
+    ///
+    /// # Arguments
+    ///
+    /// * `classify` - the opening name at a node
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let game = sacrifice::read_pgn("1. e4 e5 (1... c5 2. Nf3) 2. Nf3 Nc6 3. Bb5 Nf6 *").unwrap();
+    /// let count = game.annotate_openings(|node| match node.fen().split(' ').next()? {
+    ///     "rnbqkbnr/pp1ppppp/8/2p5/4P3/8/PPPP1PPP/RNBQKBNR" => Some("B20 Sicilian Defense".to_string()),
+    ///     "r1bqkbnr/pppp1ppp/2n5/1B2p3/4P3/5N2/PPPP1PPP/RNBQK2R" => Some("C60 Ruy Lopez".to_string()),
+    ///     "r1bqkb1r/pppp1ppp/2n2n2/1B2p3/4P3/5N2/PPPP1PPP/RNBQK2R" => {
+    ///         Some("C65 Ruy Lopez: Berlin Defense".to_string())
+    ///     }
+    ///     _ => None,
+    /// });
+    /// assert_eq!(count, 2);
+    /// assert_eq!(
+    ///     game.movetext(),
+    ///     "1. e4 e5 ( 1... c5 { B20 Sicilian Defense } 2. Nf3 ) 2. Nf3 Nc6 3. Bb5 Nf6 { C65 Ruy Lopez: Berlin Defense }"
+    /// );
+    /// ```
+    pub fn annotate_openings(&self, mut classify: impl FnMut(&Node) -> Option<String>) -> usize {
+        let mut count = 0;
+
+        // Nodes with their opening names, parents before children
+        let mut node_vec: Vec<(Node, Option<String>)> = vec![(self.root(), None)];
+        while let Some((mut node, name)) = node_vec.pop() {
+            let child_vec: Vec<(Node, Option<String>)> = node
+                .variation_vec()
+                .into_iter()
+                .map(|child| {
+                    let name = classify(&child);
+                    (child, name)
+                })
+                .collect();
+
+            let leaves_book = child_vec.iter().all(|(_, name)| name.is_none());
+            if let (Some(name), true) = (name, leaves_book) {
+                let comment = Comment::after_move(name);
+                if !node.comments().contains(&comment) {
+                    node.push_comment(comment);
+                    count += 1;
+                }
+            }
+
+            node_vec.extend(child_vec.into_iter().rev());
+        }
+
+        count
+    }
+}
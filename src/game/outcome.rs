@@ -0,0 +1,165 @@
+use super::{DrawReason, Game, GameResult};
+use crate::{Color, Outcome, Position};
+
+/// How a game ended, see [`GameOutcome`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Termination {
+    /// Checkmate on the board
+    Checkmate,
+    /// Won on the board by a rule of the variant, such as the king reaching
+    /// the center in King of the Hill
+    VariantWin,
+    /// Drawn on the board
+    Board(DrawReason),
+    /// Ended without further detail, such as by resignation or agreement
+    Normal,
+    /// A player ran out of time
+    TimeForfeit,
+    /// A player left the game
+    Abandoned,
+    /// Decided by a third party, such as an arbiter
+    Adjudication,
+    /// A player broke the rules
+    RulesInfraction,
+    /// Any other `Termination` tag
+    Other(String),
+}
+
+impl Termination {
+    /// Returns the termination given by a `Termination` tag, or `None` for
+    /// `unterminated`.
+    fn from_tag(value: &str) -> Option<Self> {
+        let termination = match value.to_ascii_lowercase().as_str() {
+            "unterminated" => return None,
+            "normal" => Termination::Normal,
+            "time forfeit" => Termination::TimeForfeit,
+            "abandoned" => Termination::Abandoned,
+            "adjudication" => Termination::Adjudication,
+            "rules infraction" => Termination::RulesInfraction,
+            _ => Termination::Other(value.to_string()),
+        };
+        Some(termination)
+    }
+}
+
+impl std::fmt::Display for Termination {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Termination::Checkmate => write!(f, "checkmate"),
+            Termination::VariantWin => write!(f, "variant win"),
+            Termination::Board(reason) => write!(f, "{}", reason),
+            Termination::Normal => write!(f, "normal"),
+            Termination::TimeForfeit => write!(f, "time forfeit"),
+            Termination::Abandoned => write!(f, "abandoned"),
+            Termination::Adjudication => write!(f, "adjudication"),
+            Termination::RulesInfraction => write!(f, "rules infraction"),
+            Termination::Other(val) => write!(f, "{}", val),
+        }
+    }
+}
+
+/// The outcome of a game, see [`Game::outcome`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum GameOutcome {
+    Decisive { winner: Color, reason: Termination },
+    Draw { reason: Termination },
+    Ongoing,
+}
+
+impl GameOutcome {
+    /// Returns the matching value of the `Result` tag.
+    pub fn result(&self) -> GameResult {
+        match self {
+            GameOutcome::Decisive {
+                winner: Color::White,
+                ..
+            } => GameResult::WhiteWins,
+            GameOutcome::Decisive {
+                winner: Color::Black,
+                ..
+            } => GameResult::BlackWins,
+            GameOutcome::Draw { .. } => GameResult::Draw,
+            GameOutcome::Ongoing => GameResult::Ongoing,
+        }
+    }
+}
+
+impl Game {
+    /// Returns the outcome of the game, reconciling the end of the main
+    /// line with the `Result` and `Termination` tags.
+    ///
+    /// A game that ended on the board, by checkmate or a draw that needs
+    /// no claim, ends that way whatever the tags say. Otherwise the
+    /// `Result` tag decides, with the reason from the `Termination` tag;
+    /// a drawn game that ends in a position where a draw can be claimed is
+    /// drawn for that reason unless the tag says otherwise. An
+    /// `unterminated` game is ongoing.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sacrifice::Color;
+    /// use sacrifice::game::{GameOutcome, Termination};
+    ///
+    /// let game = sacrifice::read_pgn("1. f3 e5 2. g4 Qh4# *").unwrap();
+    /// assert_eq!(
+    ///     game.outcome(),
+    ///     GameOutcome::Decisive { winner: Color::Black, reason: Termination::Checkmate }
+    /// );
+    ///
+    /// let game = sacrifice::read_pgn(
+    ///     "[Result \"1-0\"]\n[Termination \"Time forfeit\"]\n\n1. e4 e5 1-0"
+    /// ).unwrap();
+    /// assert_eq!(
+    ///     game.outcome(),
+    ///     GameOutcome::Decisive { winner: Color::White, reason: Termination::TimeForfeit }
+    /// );
+    ///
+    /// let game = sacrifice::read_pgn("1. e4 e5 *").unwrap();
+    /// assert_eq!(game.outcome(), GameOutcome::Ongoing);
+    /// ```
+    pub fn outcome(&self) -> GameOutcome {
+        let node = self.root().mainline_iter().last().unwrap_or(self.root());
+
+        if let Some(Outcome::Decisive { winner }) = node.outcome() {
+            let reason = if node.position().is_variant_end() {
+                Termination::VariantWin
+            } else {
+                Termination::Checkmate
+            };
+            return GameOutcome::Decisive { winner, reason };
+        }
+        let draw_reason = node.draw_reason();
+        if let Some(reason) = draw_reason.filter(|reason| !reason.is_claimable()) {
+            let reason = Termination::Board(reason);
+            return GameOutcome::Draw { reason };
+        }
+
+        let termination = match self.opt_headers.get("Termination") {
+            Some(val) => match Termination::from_tag(val) {
+                Some(val) => val,
+                None => return GameOutcome::Ongoing,
+            },
+            None => Termination::Normal,
+        };
+
+        match self.header.result {
+            GameResult::WhiteWins => GameOutcome::Decisive {
+                winner: Color::White,
+                reason: termination,
+            },
+            GameResult::BlackWins => GameOutcome::Decisive {
+                winner: Color::Black,
+                reason: termination,
+            },
+            GameResult::Draw => {
+                let reason = match (termination, draw_reason) {
+                    (Termination::Normal, Some(reason)) => Termination::Board(reason),
+                    (termination, _) => termination,
+                };
+                GameOutcome::Draw { reason }
+            }
+            GameResult::Ongoing => GameOutcome::Ongoing,
+        }
+    }
+}
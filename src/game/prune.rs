@@ -0,0 +1,115 @@
+use super::{Game, Node};
+
+/// What else to remove when pruning variations.
+#[derive(Debug, Clone, Default)]
+pub struct PruneOptions {
+    /// Remove all comments of the remaining moves, including the game
+    /// comment
+    pub strip_comments: bool,
+    /// Remove all NAGs of the remaining moves
+    pub strip_nags: bool,
+}
+
+impl Game {
+    /// Removes every variation, keeping only the main line.
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - whether to strip comments and NAGs as well
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sacrifice::game::PruneOptions;
+    ///
+    /// let game = sacrifice::read_pgn("1. e4 $1 { best } e5 (1... c5 2. Nf3) 2. Nf3 (2. f4) *").unwrap();
+    /// game.prune_variations(&PruneOptions::default());
+    /// assert_eq!(game.movetext(), "1. e4 $1 { best } 1... e5 2. Nf3");
+    ///
+    /// game.prune_variations(&PruneOptions { strip_comments: true, strip_nags: true });
+    /// assert_eq!(game.movetext(), "1. e4 e5 2. Nf3");
+    /// ```
+    pub fn prune_variations(&self, options: &PruneOptions) {
+        self.root().prune_variations(options);
+    }
+
+    /// Returns a copy of the game with every line cut after the given
+    /// number of plies from the start. Headers are kept as they are.
+    ///
+    /// # Arguments
+    ///
+    /// * `max_plies` - number of half-moves to keep
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let game = sacrifice::read_pgn(
+    ///     "[White \"Morphy\"]\n\n1. e4 e5 (1... c5 2. Nf3) 2. Nf3 Nc6 3. Bb5 *"
+    /// ).unwrap();
+    /// let opening = game.truncated(3);
+    /// assert_eq!(opening.header.white.as_deref(), Some("Morphy"));
+    /// assert_eq!(opening.movetext(), "1. e4 e5 ( 1... c5 2. Nf3 ) 2. Nf3");
+    /// assert_eq!(game.truncated(0).movetext(), "");
+    /// assert_eq!(game.movetext(), "1. e4 e5 ( 1... c5 2. Nf3 ) 2. Nf3 Nc6 3. Bb5");
+    /// ```
+    pub fn truncated(&self, max_plies: usize) -> Game {
+        let game = self.deep_clone();
+
+        let mut node_vec = vec![(game.root(), 0)];
+        while let Some((mut node, ply)) = node_vec.pop() {
+            if ply == max_plies {
+                node.truncate();
+                continue;
+            }
+            node_vec.extend(
+                node.variation_vec()
+                    .into_iter()
+                    .map(|child| (child, ply + 1)),
+            );
+        }
+
+        game
+    }
+}
+
+impl Node {
+    /// Removes every variation after the given node, keeping only the
+    /// mainline continuation. Variations that branch off before the node
+    /// are kept.
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - whether to strip comments and NAGs from the given
+    ///   node and its continuation as well
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sacrifice::game::PruneOptions;
+    ///
+    /// let game = sacrifice::read_pgn("1. e4 (1. d4) 1... e5 (1... c5) 2. Nf3 (2. f4) *").unwrap();
+    /// let mut e4_node = game.root().mainline().unwrap();
+    /// e4_node.prune_variations(&PruneOptions::default());
+    /// assert_eq!(game.movetext(), "1. e4 ( 1. d4 ) 1... e5 2. Nf3");
+    /// ```
+    pub fn prune_variations(&mut self, options: &PruneOptions) {
+        self.edit_group(|| {
+            let mut next = Some(self.clone());
+            while let Some(mut node) = next {
+                let mut variation_vec = node.variation_vec();
+                if variation_vec.len() > 1 {
+                    variation_vec.truncate(1);
+                    node.set_variation_vec(variation_vec);
+                }
+                if options.strip_comments && !node.comments().is_empty() {
+                    node.set_comments(Vec::new());
+                }
+                if options.strip_nags && node.nags().is_some_and(|nags| !nags.is_empty()) {
+                    node.clear_nags();
+                }
+
+                next = node.mainline();
+            }
+        });
+    }
+}
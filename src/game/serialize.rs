@@ -0,0 +1,262 @@
+use super::{Comment, EvalInfo, Game, Node, Shape, ShapeColor};
+use crate::{CastlingMode, Fen, Position, Square, Variant, VariantPosition};
+
+use serde::de::Error;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use shakmaty::uci::Uci;
+use std::collections::{BTreeMap, HashMap};
+use std::time::Duration;
+
+#[derive(Serialize, Deserialize)]
+struct ShapeData {
+    color: ShapeColor,
+    from: String,
+    to: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct NodeData {
+    id: u64,
+    /// Id of the parent; `None` only for the first node, the root
+    parent: Option<u64>,
+    /// The move from the parent, in UCI
+    uci: Option<String>,
+
+    nags: Vec<u8>,
+    comments: Vec<Comment>,
+    clock: Option<Duration>,
+    emt: Option<Duration>,
+    eval: Option<EvalInfo>,
+    /// The engine line of `eval`, in UCI
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pv: Vec<String>,
+    shapes: Vec<ShapeData>,
+    meta: BTreeMap<String, String>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct TreeData {
+    /// Variant of the game, by its UCI name; standard chess if missing
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    variant: Option<String>,
+    /// Position of the root
+    fen: String,
+    /// Every node, parents before children and variations in order
+    nodes: Vec<NodeData>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct GameData {
+    headers: BTreeMap<String, String>,
+    tree: TreeData,
+}
+
+impl NodeData {
+    fn from_node(node: &Node, is_root: bool) -> Self {
+        let mut nags: Vec<u8> = node.nags().unwrap_or_default().into_iter().collect();
+        nags.sort_unstable();
+
+        Self {
+            id: node.id(),
+            parent: node.parent().filter(|_| !is_root).map(|parent| parent.id()),
+            uci: node.uci().filter(|_| !is_root).map(|uci| uci.to_string()),
+
+            nags,
+            comments: node.comments(),
+            clock: node.clock(),
+            emt: node.emt(),
+            eval: node.eval_info(),
+            pv: node
+                .eval_info()
+                .map(|info| info.pv)
+                .unwrap_or_default()
+                .iter()
+                .map(|m| m.to_uci(node.position().castles().mode()).to_string())
+                .collect(),
+            shapes: node
+                .shapes()
+                .into_iter()
+                .map(|shape| ShapeData {
+                    color: shape.color,
+                    from: shape.from.to_string(),
+                    to: shape.to.to_string(),
+                })
+                .collect(),
+            meta: node.meta_map(),
+        }
+    }
+
+    fn apply_to<E: Error>(self, node: &mut Node) -> Result<(), E> {
+        let shape_vec = self
+            .shapes
+            .into_iter()
+            .map(|shape| {
+                let from = shape.from.parse::<Square>().map_err(E::custom)?;
+                let to = shape.to.parse::<Square>().map_err(E::custom)?;
+                Ok(Shape::arrow(shape.color, from, to))
+            })
+            .collect::<Result<Vec<Shape>, E>>()?;
+
+        if !self.nags.is_empty() {
+            node.set_nags(self.nags.into_iter().collect());
+        }
+        node.set_comments(self.comments);
+        node.set_clock(self.clock);
+        node.set_emt(self.emt);
+        let mut eval = self.eval;
+        if let Some(info) = eval.as_mut() {
+            let mut position = node.position();
+            for uci_str in self.pv {
+                let m = uci_str
+                    .parse::<Uci>()
+                    .map_err(E::custom)?
+                    .to_move(&position)
+                    .map_err(E::custom)?;
+                position.play_unchecked(&m);
+                info.pv.push(m);
+            }
+        }
+        node.set_eval_info(eval);
+        node.set_shapes(shape_vec);
+        for (key, value) in self.meta {
+            node.set_meta(&key, Some(value));
+        }
+
+        Ok(())
+    }
+}
+
+impl TreeData {
+    fn from_node(root: &Node) -> Self {
+        let mut nodes = Vec::new();
+
+        let mut node_vec = vec![root.clone()];
+        while let Some(node) = node_vec.pop() {
+            nodes.push(NodeData::from_node(&node, &node == root));
+            node_vec.extend(node.variation_vec().into_iter().rev());
+        }
+
+        let variant = root.position().variant();
+        Self {
+            variant: (variant != Variant::Chess).then(|| variant.uci().to_string()),
+            fen: root.fen(),
+            nodes,
+        }
+    }
+
+    fn into_root<E: Error>(self) -> Result<Node, E> {
+        let fen = self.fen.parse::<Fen>().map_err(E::custom)?;
+        let mode = CastlingMode::detect(fen.as_setup());
+        let variant = match self.variant {
+            Some(val) => Variant::from_uci(&val).map_err(E::custom)?,
+            None => Variant::Chess,
+        };
+        let position = VariantPosition::from_setup(variant, fen.into_setup(), mode);
+        let position = position.map_err(E::custom)?;
+        let root = Node::from_position(position);
+
+        let mut node_data = self.nodes.into_iter();
+        let root_data = node_data
+            .next()
+            .ok_or_else(|| E::custom("tree without a root"))?;
+        let root_id = root_data.id;
+        root_data.apply_to(&mut root.clone())?;
+
+        let mut node_map = HashMap::from([(root_id, root.clone())]);
+        for data in node_data {
+            let (parent_id, uci) = data
+                .parent
+                .zip(data.uci.as_deref())
+                .ok_or_else(|| E::custom(format!("node {} without a move", data.id)))?;
+            let mut parent = node_map
+                .get(&parent_id)
+                .cloned()
+                .ok_or_else(|| E::custom(format!("node {} before its parent", data.id)))?;
+
+            let m = uci
+                .parse::<Uci>()
+                .ok()
+                .and_then(|uci| uci.to_move(&parent.position()).ok())
+                .ok_or_else(|| E::custom(format!("illegal move {}", uci)))?;
+            let id = data.id;
+            let mut node = parent
+                .push_variation_with_id(m, id)
+                .ok_or_else(|| E::custom(format!("duplicate node id {}", id)))?;
+            data.apply_to(&mut node)?;
+            node_map.insert(id, node);
+        }
+
+        Ok(root)
+    }
+}
+
+/// Serializes the tree under the node, which becomes the root of the
+/// deserialized tree. The ids of its descendants are kept; the root of a
+/// deserialized tree always has id `0`.
+///
+/// # Examples
+///
+/// ```
+/// use sacrifice::game::Node;
+///
+/// let game = sacrifice::read_pgn("1. e4 e5 (1... c5 { Sicilian }) 2. Nf3 *").unwrap();
+/// let e4_node = game.root().mainline().unwrap();
+/// let json = serde_json::to_string(&e4_node).unwrap();
+/// let node: Node = serde_json::from_str(&json).unwrap();
+/// assert_eq!(node.position(), e4_node.position());
+/// assert_eq!(node.other_variations()[0].comment(), Some("Sicilian".to_string()));
+/// assert_eq!(node.mainline().unwrap().id(), e4_node.mainline().unwrap().id());
+/// ```
+impl Serialize for Node {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        TreeData::from_node(self).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Node {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        TreeData::deserialize(deserializer)?.into_root()
+    }
+}
+
+/// Serializes the headers and the whole tree, with every annotation and
+/// node id, to any serde format.
+///
+/// Unlike [`Game::to_json`], the layout is meant for storage rather than
+/// for other programs, and nodes keep their [ids](Node::id).
+///
+/// # Examples
+///
+/// ```
+/// use sacrifice::game::Game;
+///
+/// let game = sacrifice::read_pgn("[White \"Tal\"]\n\n1. e4 $1 (1. d4 { solid }) 1... e5 *").unwrap();
+/// let e5_node = game.root().mainline().unwrap().mainline().unwrap();
+/// let json = serde_json::to_string(&game).unwrap();
+/// let stored: Game = serde_json::from_str(&json).unwrap();
+/// assert_eq!(stored.header.white.as_deref(), Some("Tal"));
+/// assert_eq!(stored.to_string(), game.to_string());
+/// assert_eq!(stored.node_by_id(e5_node.id()).unwrap().position(), e5_node.position());
+/// ```
+impl Serialize for Game {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        GameData {
+            headers: self.header_map(),
+            tree: TreeData::from_node(&self.root),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Game {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let game_data = GameData::deserialize(deserializer)?;
+
+        let mut game = Game {
+            root: game_data.tree.into_root()?,
+            ..Game::default()
+        };
+        game.set_header_map(game_data.headers);
+        Ok(game)
+    }
+}
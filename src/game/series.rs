@@ -0,0 +1,69 @@
+use super::{Eval, Game};
+use crate::{Color, Position};
+
+/// The evaluation after one mainline move, as a point of an advantage
+/// graph.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EvalPoint {
+    /// Ply of the node, counted as by [`Node::ply`](super::Node::ply)
+    pub ply: u32,
+    /// Evaluation from White's point of view, capped at 1000 centipawns;
+    /// mates count as the cap
+    pub centipawns: i32,
+    /// Number of moves to mate, negative if Black mates, if the evaluation
+    /// was a mate; 0 if the node is checkmate
+    pub mate: Option<i32>,
+}
+
+impl EvalPoint {
+    /// Returns White's winning chances from -1 to 1, as
+    /// [`Eval::winning_chances`] does; a better scale for the graph than
+    /// centipawns.
+    pub fn winning_chances(&self) -> f64 {
+        Eval::Centipawns(self.centipawns).winning_chances()
+    }
+}
+
+impl Game {
+    /// Returns the evaluations along the mainline, from the root, for
+    /// drawing an advantage graph.
+    ///
+    /// Nodes without an evaluation are left out, so the plies may have
+    /// gaps; a checkmate without one counts as a mate for the side that
+    /// gave it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let game = sacrifice::read_pgn(
+    ///     "1. f3 { [%eval -0.5] } 1... e5 2. g4 { [%eval #-1] } 2... Qh4# 0-1"
+    /// ).unwrap();
+    /// let series = game.eval_series();
+    /// assert_eq!(series.len(), 3);
+    /// assert_eq!((series[0].ply, series[0].centipawns), (1, -50));
+    /// assert_eq!((series[1].ply, series[1].centipawns, series[1].mate), (3, -1000, Some(-1)));
+    /// assert_eq!((series[2].ply, series[2].mate), (4, Some(0)));
+    /// ```
+    pub fn eval_series(&self) -> Vec<EvalPoint> {
+        let root = self.root();
+        std::iter::once(root.clone())
+            .chain(root.mainline_iter())
+            .filter_map(|node| {
+                let (centipawns, mate) = match node.eval() {
+                    Some(eval @ Eval::Mate(moves)) => (eval.clamped_centipawns(), Some(moves)),
+                    Some(eval) => (eval.clamped_centipawns(), None),
+                    None if node.position().is_checkmate() => match node.turn() {
+                        Color::White => (-1000, Some(0)),
+                        Color::Black => (1000, Some(0)),
+                    },
+                    None => return None,
+                };
+                Some(EvalPoint {
+                    ply: node.ply(),
+                    centipawns,
+                    mate,
+                })
+            })
+            .collect()
+    }
+}
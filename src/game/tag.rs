@@ -0,0 +1,184 @@
+use super::writer::SEVEN_TAG_ROSTER;
+use super::{Game, GameResult};
+
+/// Optional tags written right after the Seven Tag Roster, in this order.
+const SETUP_TAGS: [&str; 2] = ["SetUp", "FEN"];
+
+impl Game {
+    /// Returns the value of any tag, standard or not, or `None` if it is
+    /// not set. `Result` is always set.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - name of the tag
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let game = sacrifice::read_pgn(
+    ///     "[White \"DrNykterstein\"]\n[WhiteTitle \"GM\"]\n[WhiteElo \"3250\"]\n\n*"
+    /// ).unwrap();
+    /// assert_eq!(game.tag("White").as_deref(), Some("DrNykterstein"));
+    /// assert_eq!(game.tag("WhiteTitle").as_deref(), Some("GM"));
+    /// assert_eq!(game.tag("WhiteElo").as_deref(), Some("3250"));
+    /// assert_eq!(game.tag("Result").as_deref(), Some("*"));
+    /// assert_eq!(game.tag("Event"), None);
+    /// ```
+    pub fn tag(&self, key: &str) -> Option<String> {
+        let header = &self.header;
+        let typed = match key {
+            "Event" => return header.event.clone(),
+            "Site" => return header.site.clone(),
+            "Date" => return header.date.clone(),
+            "Round" => return header.round.clone(),
+            "White" => return header.white.clone(),
+            "Black" => return header.black.clone(),
+            "Result" => return Some(header.result.to_string()),
+            "WhiteElo" => header.white_elo,
+            "BlackElo" => header.black_elo,
+            _ => None,
+        };
+
+        typed
+            .map(|elo| elo.to_string())
+            .or_else(|| self.opt_headers.get(key).cloned())
+    }
+
+    /// Sets any tag, standard or not.
+    ///
+    /// Returns the previous value, as by [`Game::tag`].
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - name of the tag
+    /// * `value` - new value of the tag
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut game = sacrifice::read_pgn("1. e4 *").unwrap();
+    /// game.set_tag("Opening", "King's Pawn Game");
+    /// game.set_tag("BlackElo", "2700");
+    /// assert_eq!(game.header.black_elo, Some(2700));
+    /// assert!(game.to_string().contains("[Opening \"King's Pawn Game\"]"));
+    /// ```
+    pub fn set_tag(&mut self, key: &str, value: impl Into<String>) -> Option<String> {
+        let prev = self.remove_tag(key);
+
+        let value = value.into();
+        if !self.header.parse(key, &value) {
+            self.opt_headers.insert(key.to_string(), value);
+        }
+        prev
+    }
+
+    /// Removes any tag, standard or not; `Result` becomes `*`.
+    ///
+    /// Returns the previous value, as by [`Game::tag`].
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - name of the tag
+    pub fn remove_tag(&mut self, key: &str) -> Option<String> {
+        let prev = self.tag(key);
+
+        let header = &mut self.header;
+        match key {
+            "Event" => header.event = None,
+            "Site" => header.site = None,
+            "Date" => header.date = None,
+            "Round" => header.round = None,
+            "White" => header.white = None,
+            "Black" => header.black = None,
+            "Result" => header.result = GameResult::Ongoing,
+            "WhiteElo" => header.white_elo = None,
+            "BlackElo" => header.black_elo = None,
+            _ => {}
+        }
+        self.opt_headers.remove(key);
+
+        prev
+    }
+
+    /// Sets the `Result` tag from the end of the main line, if the game
+    /// ended on the board: by checkmate, stalemate or insufficient
+    /// material.
+    ///
+    /// Returns the new result, or `None` if the game did not end on the
+    /// board, in which case the tag is left as it is.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sacrifice::game::GameResult;
+    ///
+    /// let mut game = sacrifice::read_pgn("1. f3 e5 2. g4 Qh4# *").unwrap();
+    /// assert_eq!(game.infer_result(), Some(GameResult::BlackWins));
+    /// assert_eq!(game.header.result, GameResult::BlackWins);
+    ///
+    /// let mut game = sacrifice::read_pgn("[Result \"1-0\"]\n\n1. e4 e5 1-0").unwrap(); // Resigned
+    /// assert_eq!(game.infer_result(), None);
+    /// assert_eq!(game.header.result, GameResult::WhiteWins);
+    /// ```
+    pub fn infer_result(&mut self) -> Option<GameResult> {
+        let result = self.mainline_result()?;
+        self.header.result = result;
+        Some(result)
+    }
+
+    /// Returns the result implied by the end of the main line, if the game
+    /// ended on the board.
+    pub(crate) fn mainline_result(&self) -> Option<GameResult> {
+        let node = self.root().mainline_iter().last().unwrap_or(self.root());
+        node.outcome().map(GameResult::from)
+    }
+
+    /// Returns every tag that is set, in the order they are exported: the
+    /// Seven Tag Roster, then `SetUp` and `FEN`, then the rest by name.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let game = sacrifice::read_pgn(
+    ///     "[Opening \"Sicilian\"]\n[FEN \"8/8/8/8/8/8/8/K6k w - - 0 1\"]\n[White \"Tal\"]\n\n*"
+    /// ).unwrap();
+    /// let key_vec: Vec<String> = game.tags().map(|(key, _)| key).collect();
+    /// assert_eq!(key_vec, ["White", "Result", "FEN", "Opening"]);
+    /// ```
+    pub fn tags(&self) -> impl Iterator<Item = (String, String)> + '_ {
+        SEVEN_TAG_ROSTER
+            .iter()
+            .filter_map(|&key| Some((key.to_string(), self.tag(key)?)))
+            .chain(self.optional_tags())
+    }
+
+    /// Returns the tags outside the Seven Tag Roster, in the order they
+    /// are exported.
+    pub(crate) fn optional_tags(&self) -> Vec<(String, String)> {
+        let extra_tag_vec = self.header.extra_tags();
+        let mut tag_vec: Vec<(String, String)> = self
+            .opt_headers
+            .iter()
+            .filter(|(key, _)| !SEVEN_TAG_ROSTER.contains(&key.as_str()))
+            // Typed tags of the header take the place of raw ones
+            .filter(|(key, _)| !extra_tag_vec.iter().any(|(extra, _)| extra == key))
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect();
+        tag_vec.extend(
+            extra_tag_vec
+                .into_iter()
+                .map(|(key, value)| (key.to_string(), value)),
+        );
+
+        // Optional tags are kept in a HashMap; order them so that the
+        // output is reproducible, with the setup tags first
+        tag_vec.sort_by(|(a, _), (b, _)| {
+            let setup_index = |key: &str| {
+                let index = SETUP_TAGS.iter().position(|&tag| tag == key);
+                index.unwrap_or(SETUP_TAGS.len())
+            };
+            (setup_index(a), a).cmp(&(setup_index(b), b))
+        });
+        tag_vec
+    }
+}
@@ -0,0 +1,42 @@
+use super::{Game, Node};
+
+use std::collections::HashMap;
+
+impl Game {
+    /// Returns the groups of nodes that share a position, such as
+    /// transpositions between variations or repetitions.
+    ///
+    /// Positions are compared by Zobrist hash, ignoring the halfmove clock
+    /// and fullmove number. Groups are in the order their first node
+    /// appears in the game, and nodes within a group in PGN order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let game = sacrifice::read_pgn("1. d4 d5 2. c4 e6 (2... Nf6 3. Nc3 e6) 3. Nc3 Nf6 *").unwrap();
+    /// let group_vec = game.transpositions();
+    /// assert_eq!(group_vec.len(), 1);
+    /// assert_eq!(group_vec[0][0].moves().len(), 6); // 3... Nf6
+    /// assert_eq!(group_vec[0][1].moves().len(), 6); // 3... e6
+    /// ```
+    pub fn transpositions(&self) -> Vec<Vec<Node>> {
+        let mut group_map: HashMap<u64, Vec<Node>> = HashMap::new();
+        let mut hash_vec = Vec::new();
+
+        let root = self.root();
+        for node in std::iter::once(root.clone()).chain(root.descendants()) {
+            let hash = node.zobrist();
+            let group = group_map.entry(hash).or_default();
+            if group.is_empty() {
+                hash_vec.push(hash);
+            }
+            group.push(node);
+        }
+
+        hash_vec
+            .into_iter()
+            .filter_map(|hash| group_map.remove(&hash))
+            .filter(|group| group.len() > 1)
+            .collect()
+    }
+}
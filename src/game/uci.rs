@@ -0,0 +1,145 @@
+use super::{Game, Node};
+use crate::{CastlingMode, Chess, Fen, Position, VariantPosition};
+
+/// Error from parsing a UCI `position` command.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UciPositionError {
+    /// Neither `startpos` nor `fen` is given
+    MissingPosition,
+    /// The FEN after `fen` is invalid or not a legal chess position
+    InvalidFen(String),
+    /// A token other than `moves` follows the position
+    UnexpectedToken(String),
+    /// The move at `index` of the move list is malformed or illegal
+    IllegalMove { index: usize, uci: String },
+}
+
+impl std::fmt::Display for UciPositionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            UciPositionError::MissingPosition => write!(f, "expected startpos or fen"),
+            UciPositionError::InvalidFen(fen) => write!(f, "invalid fen {}", fen),
+            UciPositionError::UnexpectedToken(token) => write!(f, "unexpected token {}", token),
+            UciPositionError::IllegalMove { index, uci } => {
+                write!(f, "illegal move {} at index {}", uci, index)
+            }
+        }
+    }
+}
+
+impl std::error::Error for UciPositionError {}
+
+impl Game {
+    /// Builds a game from a UCI `position` command.
+    ///
+    /// Accepts `[position] startpos [moves ...]` and
+    /// `[position] fen <fen> [moves ...]`; the moves become the mainline.
+    /// A FEN start is recorded in the `SetUp` and `FEN` headers.
+    ///
+    /// # Arguments
+    ///
+    /// * `command` - the UCI position command
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let game = sacrifice::game::Game::from_uci_position(
+    ///     "position startpos moves e2e4 c7c5 g1f3"
+    /// ).unwrap();
+    /// let nf3_node = game.root().mainline().unwrap().mainline().unwrap().mainline().unwrap();
+    /// assert_eq!(nf3_node.prev_move().unwrap().to(), sacrifice::Square::F3);
+    /// ```
+    pub fn from_uci_position(command: &str) -> Result<Self, UciPositionError> {
+        let mut tokens = command.split_whitespace().peekable();
+        if tokens.peek() == Some(&"position") {
+            tokens.next();
+        }
+
+        let mut game = Game::default();
+        match tokens.next() {
+            Some("startpos") => {}
+            Some("fen") => {
+                let fen_str = tokens
+                    .by_ref()
+                    .take_while(|&t| t != "moves")
+                    .collect::<Vec<&str>>()
+                    .join(" ");
+                let position: Chess = fen_str
+                    .parse::<Fen>()
+                    .ok()
+                    .and_then(|f| f.into_position(CastlingMode::Standard).ok())
+                    .ok_or_else(|| UciPositionError::InvalidFen(fen_str.clone()))?;
+
+                game.root = Node::from_position(position.into());
+                game.opt_headers
+                    .insert("SetUp".to_string(), "1".to_string());
+                game.opt_headers.insert("FEN".to_string(), fen_str);
+
+                // "moves" was consumed along with the FEN
+                return game.play_uci_moves(tokens).map(|_| game);
+            }
+            _ => return Err(UciPositionError::MissingPosition),
+        }
+
+        match tokens.next() {
+            None => Ok(game),
+            Some("moves") => game.play_uci_moves(tokens).map(|_| game),
+            Some(token) => Err(UciPositionError::UnexpectedToken(token.to_string())),
+        }
+    }
+
+    fn play_uci_moves<'a>(
+        &self,
+        uci_iter: impl Iterator<Item = &'a str>,
+    ) -> Result<(), UciPositionError> {
+        let mut node = self.root();
+        for (index, uci_str) in uci_iter.enumerate() {
+            let illegal_move = || UciPositionError::IllegalMove {
+                index,
+                uci: uci_str.to_string(),
+            };
+
+            node = node.play_uci(uci_str).ok_or_else(illegal_move)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Node {
+    /// Returns the UCI `position` command for the path from the root to
+    /// this node.
+    ///
+    /// The standard starting position is written as `startpos`, anything
+    /// else as `fen <fen>`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let game = sacrifice::read_pgn("1. e4 e5 2. Nf3").unwrap();
+    /// let e5_node = game.root().mainline().unwrap().mainline().unwrap();
+    /// assert_eq!(e5_node.uci_position_command(), "position startpos moves e2e4 e7e5");
+    /// assert_eq!(game.root().uci_position_command(), "position startpos");
+    /// ```
+    pub fn uci_position_command(&self) -> String {
+        let root = self.root();
+        let position = root.position();
+        let mut ret = if position == VariantPosition::new(position.variant()) {
+            "position startpos".to_string()
+        } else {
+            format!("position fen {}", root.fen())
+        };
+
+        // Chess960 castling is sent as the king taking its own rook
+        let mode = position.castles().mode();
+        let move_vec = self.moves();
+        if !move_vec.is_empty() {
+            ret.push_str(" moves");
+            for m in move_vec {
+                ret.push(' ');
+                ret.push_str(&m.to_uci(mode).to_string());
+            }
+        }
+        ret
+    }
+}
@@ -0,0 +1,133 @@
+use super::variant::{parse_variant, position_from_fen};
+use super::{Game, Node};
+use crate::{Position, VariantPosition};
+
+use std::collections::HashSet;
+
+/// An inconsistency in a game tree, found by [`Game::validate`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum Violation {
+    /// The root node has a parent
+    RootHasParent,
+    /// The root position differs from the `FEN` and `Variant` tags, or is
+    /// not the starting position of the variant without a `FEN` tag
+    InitialPositionMismatch,
+    /// The node is a variation of `listed_by`, but has another parent
+    WrongParent { node: Node, listed_by: Node },
+    /// The node is reached more than once, through a cycle or by being a
+    /// variation of several nodes
+    Revisited(Node),
+    /// The move of the node is illegal in the position of its parent
+    IllegalMove(Node),
+    /// The stored position of the node is not the position of its parent
+    /// after its move
+    PositionMismatch(Node),
+}
+
+impl std::fmt::Display for Violation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Violation::RootHasParent => write!(f, "root node has a parent"),
+            Violation::InitialPositionMismatch => {
+                write!(f, "root position does not match the FEN tag")
+            }
+            Violation::WrongParent { node, listed_by } => write!(
+                f,
+                "node {} is a variation of node {} but not its child",
+                node.id(),
+                listed_by.id()
+            ),
+            Violation::Revisited(node) => write!(f, "node {} is reached twice", node.id()),
+            Violation::IllegalMove(node) => write!(f, "node {} has an illegal move", node.id()),
+            Violation::PositionMismatch(node) => {
+                write!(f, "node {} has a wrong position", node.id())
+            }
+        }
+    }
+}
+
+impl Game {
+    /// Checks that the tree is consistent: the root position matches the
+    /// tags, every node is reached once, and every stored position follows
+    /// from its parent's position and move.
+    ///
+    /// Returns every violation found, or an empty list.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sacrifice::game::Violation;
+    ///
+    /// let game = sacrifice::read_pgn("1. e4 e5 (1... c5) 2. Nf3 *").unwrap();
+    /// assert!(game.validate().is_empty());
+    ///
+    /// // Make 1. e4 a variation of itself
+    /// let mut e4_node = game.root().mainline().unwrap();
+    /// e4_node.set_variation_vec(vec![e4_node.clone()]);
+    /// assert_eq!(
+    ///     game.validate(),
+    ///     vec![
+    ///         Violation::WrongParent { node: e4_node.clone(), listed_by: e4_node.clone() },
+    ///         Violation::Revisited(e4_node),
+    ///     ]
+    /// );
+    /// ```
+    pub fn validate(&self) -> Vec<Violation> {
+        let mut violation_vec = Vec::new();
+
+        if self.root.parent().is_some() {
+            violation_vec.push(Violation::RootHasParent);
+        }
+        if !self.initial_position_matches() {
+            violation_vec.push(Violation::InitialPositionMismatch);
+        }
+
+        let mut visited = HashSet::from([self.root.id()]);
+        let mut node_vec = vec![self.root()];
+        while let Some(node) = node_vec.pop() {
+            let position = node.position();
+            for child in node.variation_vec() {
+                if child.parent().as_ref() != Some(&node) {
+                    violation_vec.push(Violation::WrongParent {
+                        node: child.clone(),
+                        listed_by: node.clone(),
+                    });
+                }
+                if !visited.insert(child.id()) {
+                    violation_vec.push(Violation::Revisited(child));
+                    continue;
+                }
+
+                let position_next = child
+                    .prev_move()
+                    .and_then(|m| position.clone().play(&m).ok());
+                match position_next {
+                    None => violation_vec.push(Violation::IllegalMove(child.clone())),
+                    Some(val) if val != child.position() => {
+                        violation_vec.push(Violation::PositionMismatch(child.clone()))
+                    }
+                    Some(_) => {}
+                }
+                node_vec.push(child);
+            }
+        }
+
+        violation_vec
+    }
+
+    fn initial_position_matches(&self) -> bool {
+        let variant = self.opt_headers.get("Variant");
+        let variant = variant
+            .and_then(|val| parse_variant(val))
+            .unwrap_or_default();
+        let fen = if let Some(val) = self.opt_headers.get("FEN") {
+            val
+        } else {
+            return self.initial_position() == VariantPosition::new(variant);
+        };
+
+        // Castling rights the FEN cannot have are dropped, as when reading
+        let position = position_from_fen(variant, fen).map(|(position, _)| position);
+        position == Some(self.initial_position())
+    }
+}
@@ -0,0 +1,163 @@
+use super::{BuildError, Game, Node};
+use crate::{CastlingMode, Fen, Variant, VariantPosition};
+
+use shakmaty::EnPassantMode;
+
+/// Returns the value of the `Variant` tag for the given variant, as
+/// lichess writes it, or `None` for standard chess.
+pub(crate) fn variant_tag(variant: Variant) -> Option<&'static str> {
+    let tag = match variant {
+        Variant::Chess => return None,
+        Variant::Atomic => "Atomic",
+        Variant::Antichess => "Antichess",
+        Variant::KingOfTheHill => "King of the Hill",
+        Variant::ThreeCheck => "Three-check",
+        Variant::Crazyhouse => "Crazyhouse",
+        Variant::RacingKings => "Racing Kings",
+        Variant::Horde => "Horde",
+    };
+    Some(tag)
+}
+
+/// Returns the variant named by a `Variant` tag, or `None` if it is not
+/// supported. Chess960 and games from a position are standard chess.
+pub(crate) fn parse_variant(tag: &str) -> Option<Variant> {
+    Variant::from_ascii(tag.as_bytes())
+        .ok()
+        .or_else(|| Variant::from_ascii(tag.to_ascii_lowercase().as_bytes()).ok())
+}
+
+/// Returns the starting position given by a FEN in the given variant, or
+/// `None` if it is invalid or illegal.
+///
+/// Chess960 castling rights are recognized from the placement of the
+/// kings and rooks. Castling rights for a king or rook that is missing, as
+/// in odds games where a rook was removed, are dropped; the flag is `true`
+/// if that happened.
+pub(crate) fn position_from_fen(variant: Variant, fen: &str) -> Option<(VariantPosition, bool)> {
+    let setup = fen.parse::<Fen>().ok()?.into_setup();
+    let mode = CastlingMode::detect(&setup);
+    match VariantPosition::from_setup(variant, setup, mode) {
+        Ok(position) => Some((position, false)),
+        Err(err) => err
+            .ignore_invalid_castling_rights()
+            .ok()
+            .map(|position| (position, true)),
+    }
+}
+
+/// Returns the FEN of a starting position, as written in the `FEN` tag.
+pub(crate) fn position_fen(position: &VariantPosition) -> String {
+    Fen::from_position(position.clone(), EnPassantMode::Legal).to_string()
+}
+
+impl Game {
+    /// Returns a game without moves in the starting position of the given
+    /// variant, with the `Variant` tag set.
+    ///
+    /// # Arguments
+    ///
+    /// * `variant` - the variant of the game
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sacrifice::Variant;
+    ///
+    /// let game = sacrifice::game::Game::new_variant(Variant::KingOfTheHill);
+    /// assert_eq!(game.variant(), Variant::KingOfTheHill);
+    /// assert_eq!(game.tag("Variant").as_deref(), Some("King of the Hill"));
+    /// ```
+    pub fn new_variant(variant: Variant) -> Self {
+        let mut game = Game {
+            root: Node::from_position(VariantPosition::new(variant)),
+            ..Game::default()
+        };
+        game.set_position_tags(&game.initial_position());
+        game
+    }
+
+    /// Returns a game without moves that starts from the position given by
+    /// a FEN, such as an odds game, with the `SetUp` and `FEN` tags set.
+    ///
+    /// Castling rights for a king or rook that is missing or has moved are
+    /// dropped, while the `FEN` tag keeps the FEN as given, as when a game
+    /// is read. Moves are numbered from the FEN's move counter.
+    ///
+    /// Returns an error if the FEN is malformed or not a legal position.
+    ///
+    /// # Arguments
+    ///
+    /// * `fen` - the starting position
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sacrifice::game::{BuildError, Game};
+    ///
+    /// // Queen's rook odds
+    /// let fen = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/1NBQKBNR w KQkq - 0 1";
+    /// let game = Game::from_fen(fen).unwrap();
+    /// assert_eq!(game.tag("FEN").as_deref(), Some(fen));
+    /// assert!(game.validate().is_empty());
+    ///
+    /// let error = Game::from_fen("8/8/8/8/8/8/8/8 w - - 0 1").unwrap_err();
+    /// assert_eq!(error, BuildError::InvalidFen("8/8/8/8/8/8/8/8 w - - 0 1".to_string()));
+    /// ```
+    pub fn from_fen(fen: &str) -> Result<Self, BuildError> {
+        let (position, _) = position_from_fen(Variant::Chess, fen)
+            .ok_or_else(|| BuildError::InvalidFen(fen.to_string()))?;
+        let mut game = Game {
+            root: Node::from_position(position),
+            ..Game::default()
+        };
+        game.opt_headers
+            .insert("SetUp".to_string(), "1".to_string());
+        game.opt_headers.insert("FEN".to_string(), fen.to_string());
+        Ok(game)
+    }
+
+    /// Returns the variant of the game, decided by the `Variant` tag when
+    /// the game was read.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sacrifice::{Position, Variant};
+    ///
+    /// let game = sacrifice::read_pgn("[Variant \"Atomic\"]\n\n1. e4 d5 2. exd5 *").unwrap();
+    /// assert_eq!(game.variant(), Variant::Atomic);
+    /// // The capture blew up the pawns on both sides
+    /// assert_eq!(game.root().mainline_iter().last().unwrap().position().board().occupied().count(), 30);
+    /// ```
+    pub fn variant(&self) -> Variant {
+        self.root.position().variant()
+    }
+
+    /// Sets the `Variant`, `SetUp` and `FEN` tags for a game that starts
+    /// from the given position, removing those that are not needed.
+    pub(crate) fn set_position_tags(&mut self, position: &VariantPosition) {
+        let variant = position.variant();
+        match variant_tag(variant) {
+            Some(val) => self
+                .opt_headers
+                .insert("Variant".to_string(), val.to_string()),
+            // Keep tags such as Chess960 that are still standard chess
+            None => self
+                .opt_headers
+                .remove("Variant")
+                .filter(|val| parse_variant(val) == Some(Variant::Chess))
+                .and_then(|val| self.opt_headers.insert("Variant".to_string(), val)),
+        };
+
+        if *position == VariantPosition::new(variant) {
+            self.opt_headers.remove("SetUp");
+            self.opt_headers.remove("FEN");
+        } else {
+            self.opt_headers
+                .insert("SetUp".to_string(), "1".to_string());
+            self.opt_headers
+                .insert("FEN".to_string(), position_fen(position));
+        }
+    }
+}
@@ -1,10 +1,31 @@
+pub use shakmaty::variant::{Variant, VariantPosition};
 pub use shakmaty::{fen::Fen, CastlingMode};
 pub use shakmaty::{san::San, san::SanPlus};
+pub use shakmaty::{ByColor, ByRole, Color, File, Move, Outcome, Piece, Rank, Role, Square};
 pub use shakmaty::{Chess, Position};
-pub use shakmaty::{Color, File, Move, Piece, Rank, Role, Square};
 
+#[cfg(feature = "animation")]
+pub mod animation;
+pub mod book;
+#[cfg(feature = "engine")]
+pub mod engine;
+pub mod epd;
+pub mod explorer;
+pub mod export;
 pub mod game;
 mod pgn;
+#[cfg(feature = "render")]
+pub mod render;
+
+#[cfg(feature = "tokio")]
+pub use pgn::async_reader::AsyncPgnReader;
+pub use pgn::reader::{
+    Diagnostic, IllegalMove, IllegalMovePolicy, ReaderLimits, ReaderOptions, Severity, TextDecoding,
+};
+pub use pgn::writer::{
+    GameCommentPlacement, HeaderSelection, LineEnding, MoveNumberStyle, OpeningClassifier,
+    PgnDatabaseWriter, PgnWriter, PgnWriterOptions, Skip, VariationLayout, Visitor,
+};
 
 #[cfg(test)]
 mod tests;
@@ -24,3 +45,50 @@ mod tests;
 pub fn read_pgn(pgn: &str) -> std::io::Result<game::Game> {
     pgn::reader::read_pgn(pgn)
 }
+
+/// Parse one chess game from PGN string, collecting everything that
+/// could not be represented in the game tree.
+///
+/// Unlike [`read_pgn`], problems such as illegal moves or malformed tag
+/// values are reported as [`Diagnostic`]s instead of being silently ignored.
+///
+/// # Arguments
+///
+/// * `pgn_str` - the "import formatted" PGN string
+///
+/// # Examples
+///
+/// ```
+/// let (game, diagnostics) = sacrifice::validate_pgn("1. e4 e5 2. Ke3").unwrap();
+/// assert_eq!(diagnostics.len(), 1);
+/// assert_eq!(diagnostics[0].ply, 3);
+/// assert_eq!(diagnostics[0].severity, sacrifice::Severity::Error);
+/// ```
+pub fn validate_pgn(pgn: &str) -> std::io::Result<(game::Game, Vec<Diagnostic>)> {
+    pgn::reader::validate_pgn(pgn)
+}
+
+/// Parse one chess game from raw PGN bytes with the given options.
+///
+/// Use this for files that are not guaranteed to be UTF-8, such as
+/// Latin-1 exports from older database software.
+///
+/// # Arguments
+///
+/// * `pgn` - the "import formatted" PGN bytes
+/// * `options` - reader options, e.g. how to decode non-UTF-8 text
+///
+/// # Examples
+///
+/// ```
+/// let pgn = b"[White \"Bj\xf6rn\"]\n\n1. e4 *";
+/// let options = sacrifice::ReaderOptions::default(); // Latin-1 fallback
+/// let (game, _) = sacrifice::read_pgn_with(pgn, &options).unwrap();
+/// assert_eq!(game.header.white, Some("Bj\u{f6}rn".to_string()));
+/// ```
+pub fn read_pgn_with(
+    pgn: impl AsRef<[u8]>,
+    options: &ReaderOptions,
+) -> std::io::Result<(game::Game, Vec<Diagnostic>)> {
+    pgn::reader::read_pgn_with(pgn, options)
+}
@@ -0,0 +1,170 @@
+use super::reader::{read_games_with, Diagnostic, ReaderOptions};
+use crate::game::Game;
+
+use std::collections::VecDeque;
+
+use futures_util::Stream;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, BufReader};
+
+/// Reads games one by one from an asynchronous source of PGN text.
+///
+/// The input is split line by line without blocking the runtime, at tag
+/// sections and after game termination markers (`1-0`, `0-1`, `1/2-1/2`,
+/// `*`); the games in between are then parsed in memory. Set
+/// [`ReaderLimits::max_game_bytes`](crate::ReaderLimits::max_game_bytes)
+/// to bound how much is buffered for a single game.
+pub struct AsyncPgnReader<R> {
+    inner: BufReader<R>,
+    options: ReaderOptions,
+
+    /// First line of the next game, read while looking for the end of
+    /// the previous one
+    pending_line: Option<Vec<u8>>,
+    /// Games parsed but not yet returned
+    pending_games: VecDeque<(Game, Vec<Diagnostic>)>,
+}
+
+impl<R: AsyncRead + Unpin> AsyncPgnReader<R> {
+    pub fn new(inner: R) -> Self {
+        Self::with_options(inner, ReaderOptions::default())
+    }
+
+    pub fn with_options(inner: R, options: ReaderOptions) -> Self {
+        Self {
+            inner: BufReader::new(inner),
+            options,
+
+            pending_line: None,
+            pending_games: VecDeque::new(),
+        }
+    }
+
+    /// Reads the next game, along with its diagnostics.
+    ///
+    /// Returns `None` once the input is exhausted.
+    pub async fn read_game(&mut self) -> std::io::Result<Option<(Game, Vec<Diagnostic>)>> {
+        while self.pending_games.is_empty() {
+            let game_text = if let Some(val) = self.read_game_text().await? {
+                val
+            } else {
+                return Ok(None);
+            };
+
+            // Movetext without tags in between may still hold several games
+            self.pending_games = read_games_with(game_text, &self.options)?.into();
+        }
+
+        Ok(self.pending_games.pop_front())
+    }
+
+    /// Reads up to the tag section of the next game, or up to the end of
+    /// the line ending the current one.
+    async fn read_game_text(&mut self) -> std::io::Result<Option<Vec<u8>>> {
+        let max_game_bytes = self.options.limits.max_game_bytes;
+        let mut game_text: Vec<u8> = Vec::new();
+        let mut in_movetext = false;
+        let mut in_comment = false;
+
+        loop {
+            let line = if let Some(val) = self.pending_line.take() {
+                val
+            } else {
+                let mut line = Vec::new();
+                let read = match max_game_bytes {
+                    // Read one byte past the limit at most, to notice it
+                    Some(max) => {
+                        let remaining = max.saturating_sub(game_text.len()) as u64 + 1;
+                        (&mut self.inner)
+                            .take(remaining)
+                            .read_until(b'\n', &mut line)
+                            .await?
+                    }
+                    None => self.inner.read_until(b'\n', &mut line).await?,
+                };
+                if read == 0 {
+                    break; // End of input
+                }
+                line
+            };
+
+            let trimmed = line.trim_ascii_start();
+            if !in_comment && trimmed.starts_with(b"[") && in_movetext {
+                // Tag section of the next game
+                self.pending_line = Some(line);
+                break;
+            }
+            if !in_comment && !trimmed.is_empty() && !trimmed.starts_with(b"[") {
+                in_movetext = true;
+            }
+            let (line_in_comment, ends_game) = scan_line(&line, in_comment);
+            in_comment = line_in_comment;
+
+            game_text.extend_from_slice(&line);
+            if let Some(max) = max_game_bytes {
+                if game_text.len() > max {
+                    return Err(std::io::Error::new(
+                        std::io::ErrorKind::InvalidData,
+                        format!("game text exceeds the limit of {} bytes", max),
+                    ));
+                }
+            }
+            if in_movetext && ends_game {
+                break;
+            }
+        }
+
+        if game_text.iter().all(|b| b.is_ascii_whitespace()) {
+            return Ok(None);
+        }
+
+        Ok(Some(game_text))
+    }
+
+    /// Turns the reader into a stream of games.
+    ///
+    /// The stream ends after the input is exhausted or after the first error.
+    /// It is `Send`, e.g. for `tokio::spawn`, only with the `sync` feature;
+    /// otherwise games share their nodes through `Rc`.
+    pub fn into_stream(self) -> impl Stream<Item = std::io::Result<Game>> {
+        futures_util::stream::unfold(Some(self), |reader| async move {
+            let mut reader = reader?;
+            match reader.read_game().await {
+                Ok(Some((game, _))) => Some((Ok(game), Some(reader))),
+                Ok(None) => None,
+                Err(err) => Some((Err(err), None)),
+            }
+        })
+    }
+}
+
+/// Returns whether a `{ ... }` comment is still open after the given line,
+/// and whether the line has a game termination marker outside comments.
+fn scan_line(line: &[u8], mut in_comment: bool) -> (bool, bool) {
+    let is_marker = |token: &[u8]| matches!(token, b"1-0" | b"0-1" | b"1/2-1/2" | b"*");
+    let mut ends_game = false;
+    let mut token_start = None;
+
+    for (i, &b) in line.iter().enumerate() {
+        if in_comment {
+            in_comment = b != b'}';
+            continue;
+        }
+        if b.is_ascii_whitespace() || matches!(b, b'{' | b'(' | b')' | b';') {
+            if let Some(start) = token_start.take() {
+                ends_game |= is_marker(&line[start..i]);
+            }
+        } else if token_start.is_none() {
+            token_start = Some(i);
+        }
+        match b {
+            b'{' => in_comment = true,
+            b';' => return (false, ends_game), // Rest of line comment
+            _ => {}
+        }
+    }
+    if let Some(start) = token_start {
+        ends_game |= is_marker(&line[start..]);
+    }
+
+    (in_comment, ends_game)
+}
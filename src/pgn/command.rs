@@ -0,0 +1,162 @@
+//! Embedded commands in PGN comments, e.g. `{ [%clk 0:05:00] [%eval 0.17] }`.
+
+use crate::game::{Eval, EvalInfo, Shape, ShapeColor};
+use crate::Square;
+
+use std::time::Duration;
+
+/// Removes the `[%name args]` commands for which `consume` returns true
+/// from a comment, returning the remaining text.
+pub(crate) fn extract_commands(
+    comment: &str,
+    mut consume: impl FnMut(&str, &str) -> bool,
+) -> String {
+    let mut text = String::new();
+
+    let mut rest = comment;
+    while let Some(start) = rest.find("[%") {
+        let end = if let Some(val) = rest[start..].find(']') {
+            start + val
+        } else {
+            break; // Unterminated, keep as text
+        };
+
+        text.push_str(&rest[..start]);
+        let command = rest[start + 2..end].trim();
+        let (name, args) = command
+            .split_once(char::is_whitespace)
+            .unwrap_or((command, ""));
+        if !consume(name, args.trim()) {
+            text.push_str(&rest[start..=end]);
+        }
+
+        rest = &rest[end + 1..];
+    }
+    text.push_str(rest);
+
+    text.split_whitespace().collect::<Vec<&str>>().join(" ")
+}
+
+/// Parses `h:mm:ss` with optional fractional seconds.
+pub(crate) fn parse_clock(args: &str) -> Option<Duration> {
+    let mut parts = args.trim().rsplit(':');
+    let seconds = parts.next()?.parse::<f64>().ok()?;
+    let minutes = parts.next().map_or(Some(0), |s| s.parse::<u64>().ok())?;
+    let hours = parts.next().map_or(Some(0), |s| s.parse::<u64>().ok())?;
+    if parts.next().is_some() || !(0.0..60.0).contains(&seconds) {
+        return None;
+    }
+
+    let whole = Duration::from_secs(hours * 3600 + minutes * 60);
+    Some(whole + Duration::from_secs_f64(seconds))
+}
+
+/// Formats a clock as `h:mm:ss`, with the given digits after the decimal
+/// point of the seconds.
+///
+/// The clock is rounded before it is split up, so that seconds rounded up
+/// to 60 carry into the minutes as [`parse_clock`] expects.
+pub(crate) fn format_clock(clock: Duration, decimals: u8) -> String {
+    // Nanoseconds are the finest precision of a duration
+    let unit = 10u128.pow(9 - u32::from(decimals.min(9)));
+    let nanos = (clock.as_nanos() + unit / 2) / unit * unit;
+    let total_secs = (nanos / 1_000_000_000) as u64;
+    let (hours, minutes) = (total_secs / 3600, total_secs / 60 % 60);
+
+    if decimals == 0 {
+        return format!("{}:{:02}:{:02}", hours, minutes, total_secs % 60);
+    }
+
+    let seconds = (nanos % 60_000_000_000) as f64 / 1e9;
+    let width = 3 + decimals as usize;
+    format!(
+        "{}:{:02}:{:0width$.prec$}",
+        hours,
+        minutes,
+        seconds,
+        width = width,
+        prec = decimals as usize
+    )
+}
+
+/// Parses `0.17`, `-1.5`, `#3` or `#-2`, with an optional `,depth`; a
+/// malformed depth is ignored.
+pub(crate) fn parse_eval(args: &str) -> Option<EvalInfo> {
+    let mut parts = args.split(',');
+    let value = parts.next()?.trim();
+    let depth = parts
+        .next()
+        .and_then(|depth| depth.trim().parse::<u32>().ok());
+
+    let eval = if let Some(mate) = value.strip_prefix('#') {
+        Eval::Mate(mate.parse::<i32>().ok()?)
+    } else {
+        let pawns = value.parse::<f64>().ok()?;
+        Eval::Centipawns((pawns * 100.0).round() as i32)
+    };
+
+    Some(EvalInfo {
+        depth,
+        ..EvalInfo::from(eval)
+    })
+}
+
+pub(crate) fn format_eval(eval: Eval, decimals: u8) -> String {
+    match eval {
+        Eval::Mate(moves) => format!("#{}", moves),
+        Eval::Centipawns(cp) => format!("{:.*}", decimals as usize, f64::from(cp) / 100.0),
+    }
+}
+
+fn shape_color(c: char) -> Option<ShapeColor> {
+    match c {
+        'G' => Some(ShapeColor::Green),
+        'R' => Some(ShapeColor::Red),
+        'Y' => Some(ShapeColor::Yellow),
+        'B' => Some(ShapeColor::Blue),
+        _ => None,
+    }
+}
+
+fn shape_color_char(color: ShapeColor) -> char {
+    match color {
+        ShapeColor::Green => 'G',
+        ShapeColor::Red => 'R',
+        ShapeColor::Yellow => 'Y',
+        ShapeColor::Blue => 'B',
+    }
+}
+
+/// Parses the arguments of `[%csl Ga4,Rb5]` or `[%cal Gd2d4,Re7e5]`.
+pub(crate) fn parse_shapes(args: &str, arrows: bool) -> Option<Vec<Shape>> {
+    args.split(',')
+        .map(|token| {
+            let token = token.trim();
+            let color = shape_color(token.chars().next()?)?;
+            let squares = token.get(1..)?;
+            match (arrows, squares.len()) {
+                (false, 2) => Some(Shape::circle(color, squares.parse::<Square>().ok()?)),
+                (true, 4) => Some(Shape::arrow(
+                    color,
+                    squares.get(..2)?.parse::<Square>().ok()?,
+                    squares.get(2..)?.parse::<Square>().ok()?,
+                )),
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+/// Returns the arguments of a `[%csl]` or `[%cal]` command.
+pub(crate) fn format_shapes<'a>(shapes: impl Iterator<Item = &'a Shape>) -> String {
+    shapes
+        .map(|shape| {
+            let mut token = format!("{}{}", shape_color_char(shape.color), shape.from);
+            if !shape.is_circle() {
+                token.push_str(&shape.to.to_string());
+            }
+            token
+        })
+        .collect::<Vec<String>>()
+        .join(",")
+}
@@ -1,2 +1,5 @@
+#[cfg(feature = "tokio")]
+pub mod async_reader;
+pub(crate) mod command;
 pub mod reader;
 pub mod writer;
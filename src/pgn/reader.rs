@@ -1,10 +1,118 @@
-use crate::game::{Game, Header, Node};
+use super::command;
+use crate::game::{parse_variant, position_fen, position_from_fen};
+use crate::game::{Comment, Game, GameResult, Header, Node};
+use crate::{Color, Position, San, Variant, VariantPosition};
 
 use pgn_reader::{Nag, RawComment};
 use std::collections::HashMap;
 
+/// How serious a [`Diagnostic`] is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    /// The input is questionable, but nothing was lost.
+    Warning,
+    /// Part of the input could not be represented in the parsed game.
+    Error,
+}
+
+/// A problem found while reading a PGN game.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// Ply of the move the problem relates to (0 for headers and
+    /// anything before the first move)
+    pub ply: u32,
+    pub severity: Severity,
+    pub message: String,
+    /// Details of the offending move, if the problem is an illegal move
+    pub illegal_move: Option<IllegalMove>,
+}
+
+/// Where an illegal or unparseable move was found.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IllegalMove {
+    /// Move number, as it would be written in the movetext
+    pub move_number: u32,
+    /// Side to move
+    pub turn: Color,
+    /// The move as read
+    pub san: String,
+    /// Byte offset of the move in the input, if its token could be found
+    pub offset: Option<usize>,
+}
+
+/// How header values and comments that are not valid UTF-8 get decoded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TextDecoding {
+    /// Reject invalid text, dropping the header or comment with an error diagnostic.
+    Strict,
+    /// Replace invalid sequences with U+FFFD.
+    Lossy,
+    /// Decode invalid text as Latin-1 (ISO 8859-1), as written by older
+    /// ChessBase exports.
+    #[default]
+    Latin1,
+}
+
+/// Upper bounds on the size of a game, for reading untrusted input.
+///
+/// Reading fails with [`std::io::ErrorKind::InvalidData`] as soon as a limit
+/// is exceeded. `None` means unlimited.
+#[derive(Debug, Clone, Default)]
+pub struct ReaderLimits {
+    /// Total number of moves in the game, including all variations
+    pub max_plies: Option<u32>,
+    /// How deeply variations may be nested (0 allows the mainline only)
+    pub max_variation_depth: Option<usize>,
+    /// Length of a single comment, in bytes
+    pub max_comment_len: Option<usize>,
+    /// Number of tag pairs
+    pub max_headers: Option<usize>,
+    /// Length of the text of a single game, in bytes; checked by
+    /// [`AsyncPgnReader`](crate::AsyncPgnReader) before it buffers more
+    pub max_game_bytes: Option<usize>,
+}
+
+/// What to do with the moves that follow an illegal or unparseable move.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum IllegalMovePolicy {
+    /// Drop the rest of the variation containing the illegal move, including
+    /// comments, NAGs and variations nested in it.
+    #[default]
+    Discard,
+    /// Skip the illegal move only, and keep playing the following moves from
+    /// the same position.
+    Skip,
+}
+
+/// Options controlling how PGN input is read.
+#[derive(Debug, Clone, Default)]
+pub struct ReaderOptions {
+    pub decoding: TextDecoding,
+    pub limits: ReaderLimits,
+    pub illegal_move_policy: IllegalMovePolicy,
+    /// Names of comment commands read into node metadata, see
+    /// [`Node::meta`](crate::game::Node::meta), instead of being kept as
+    /// comment text
+    pub meta_commands: Vec<String>,
+}
+
 // Predecessor of Game struct
 struct PartialGame {
+    decoding: TextDecoding,
+    limits: ReaderLimits,
+    illegal_move_policy: IllegalMovePolicy,
+    meta_commands: Vec<String>,
+    /// Why reading was aborted, if a limit was exceeded
+    exceeded: Option<String>,
+    ply_count: u32,
+    header_count: usize,
+    /// Height of the variation stack when an illegal move was read, while
+    /// the rest of that variation is being discarded
+    discarding: Option<usize>,
+    /// Number of open variations that were skipped; they still end with a
+    /// call to `end_variation`
+    skipped_variations: usize,
+
     header: Header,
     opt_headers: HashMap<String, String>,
 
@@ -13,31 +121,248 @@ struct PartialGame {
     variation_stack: Vec<Node>,
     in_variation: bool,
 
-    starting_comment: Option<String>,
+    starting_comment_vec: Vec<String>,
+
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl PartialGame {
+    fn diagnose(&mut self, severity: Severity, message: String) {
+        let ply = if let Some(node) = self.variation_stack.last() {
+            node.depth()
+        } else {
+            0
+        };
+
+        self.diagnostics.push(Diagnostic {
+            ply,
+            severity,
+            message,
+            illegal_move: None,
+        });
+    }
+
+    fn exceed(&mut self, limit: &str, max: impl std::fmt::Display) {
+        self.exceeded = Some(format!("{} exceeds the limit of {}", limit, max));
+    }
+
+    fn decode(&mut self, bytes: &[u8]) -> Option<String> {
+        if let Ok(val) = std::str::from_utf8(bytes) {
+            return Some(val.to_string());
+        }
+
+        match self.decoding {
+            TextDecoding::Strict => {
+                self.diagnose(Severity::Error, "text is not valid UTF-8".to_string());
+                None
+            }
+            TextDecoding::Lossy => Some(String::from_utf8_lossy(bytes).into_owned()),
+            TextDecoding::Latin1 => Some(bytes.iter().map(|&b| b as char).collect()),
+        }
+    }
 }
 
-enum GameVisitor {
-    None,
-    InGame { inner: PartialGame },
+/// A token of the input that the visitor needs to keep its place.
+enum SourceToken {
+    Move { offset: usize, san: San },
+    BeginVariation,
+    EndVariation,
+}
+
+struct GameVisitor<'a> {
+    options: ReaderOptions,
+    inner: Option<Box<PartialGame>>,
+
+    /// The whole input, for locating moves
+    source: &'a [u8],
+    /// Where the next token starts in `source`, following the tokens
+    /// visited so far
+    source_cursor: usize,
 }
 
-impl GameVisitor {
-    fn new() -> Self {
-        Self::None
+impl<'a> GameVisitor<'a> {
+    fn new(options: ReaderOptions, source: &'a [u8]) -> Self {
+        Self {
+            options,
+            inner: None,
+
+            source,
+            source_cursor: 0,
+        }
     }
 }
 
-impl GameVisitor {
-    fn try_get_inner(&mut self) -> Option<&mut PartialGame> {
-        match self {
-            GameVisitor::None => None,
-            GameVisitor::InGame { inner: tree } => Some(tree),
+impl GameVisitor<'_> {
+    /// Reads the next move or parenthesis from the input, skipping tags,
+    /// comments, move numbers, NAGs and results as `pgn_reader` does, and
+    /// moves the cursor past it.
+    fn next_token(&mut self) -> Option<SourceToken> {
+        let source = self.source;
+        let find_from = |start: usize, needle: u8| {
+            source
+                .get(start..)
+                .and_then(|rest| rest.iter().position(|&b| b == needle))
+                .map_or(source.len(), |len| start + len)
+        };
+        let token_end = |start: usize| {
+            let len = source[start..].iter().position(|b| {
+                matches!(
+                    b,
+                    b' ' | b'\t'
+                        | b'\n'
+                        | b'\r'
+                        | b'{'
+                        | b'}'
+                        | b'('
+                        | b')'
+                        | b'!'
+                        | b'?'
+                        | b'$'
+                        | b';'
+                        | b'.'
+                )
+            });
+            len.map_or(source.len(), |len| start + len)
+        };
+
+        let mut index = self.source_cursor;
+        while let Some(&ch) = source.get(index) {
+            let line_start = index == 0 || source[index - 1] == b'\n';
+            match ch {
+                // Tag pairs of this or the next game
+                b'[' => index = find_from(index, b'\n'),
+                b'%' if line_start => index = find_from(index, b'\n'),
+                b';' => index = find_from(index, b'\n'),
+                b'{' => index = (find_from(index, b'}') + 1).min(source.len()),
+                b'(' | b')' => {
+                    self.source_cursor = index + 1;
+                    return Some(if ch == b'(' {
+                        SourceToken::BeginVariation
+                    } else {
+                        SourceToken::EndVariation
+                    });
+                }
+                b'0' if source[index..].starts_with(b"0-0") => {
+                    let (end, side) = if source[index..].starts_with(b"0-0-0") {
+                        (index + 5, shakmaty::CastlingSide::QueenSide)
+                    } else {
+                        (index + 3, shakmaty::CastlingSide::KingSide)
+                    };
+                    self.source_cursor = end;
+                    return Some(SourceToken::Move {
+                        offset: index,
+                        san: San::Castle(side),
+                    });
+                }
+                b'$' => index = token_end(index + 1),
+                b' ' | b'\t' | b'\r' | b'\n' | b'!' | b'?' | b'*' | b'.' | b'P' => index += 1,
+                _ => {
+                    let end = token_end(index + 1);
+                    // Move numbers and results are not moves
+                    let san = (ch > b'9' || ch == b'-')
+                        .then(|| San::from_ascii(&source[index..end]).ok())
+                        .flatten();
+                    if let Some(san) = san {
+                        self.source_cursor = end;
+                        return Some(SourceToken::Move { offset: index, san });
+                    }
+                    index = end;
+                }
+            }
+        }
+
+        self.source_cursor = source.len();
+        None
+    }
+
+    /// Returns the offset of the next move in the input, if it is the
+    /// given move.
+    fn locate(&mut self, san: &San) -> Option<usize> {
+        loop {
+            if let SourceToken::Move { offset, san: token } = self.next_token()? {
+                return (token == *san).then_some(offset);
+            }
+        }
+    }
+
+    /// Moves the cursor past a variation that `pgn_reader` skips.
+    fn skip_source_variation(&mut self) {
+        let mut depth = 0usize;
+        while let Some(token) = self.next_token() {
+            match token {
+                SourceToken::BeginVariation => depth += 1,
+                SourceToken::EndVariation => {
+                    depth = depth.saturating_sub(1);
+                    if depth == 0 {
+                        return;
+                    }
+                }
+                SourceToken::Move { .. } => {}
+            }
+        }
+    }
+
+    /// Starts reading a variation, or returns `Skip(true)` if it is left
+    /// out.
+    fn open_variation(&mut self) -> pgn_reader::Skip {
+        let inner = if let Some(val) = self.try_get_inner() {
+            val
+        } else {
+            return pgn_reader::Skip(true);
+        };
+        if inner.discarding.is_some() {
+            inner.skipped_variations += 1;
+            return pgn_reader::Skip(true);
+        }
+
+        let cur_node = if let Some(val) = inner.variation_stack.last_mut() {
+            val
+        } else {
+            inner.skipped_variations += 1;
+            return pgn_reader::Skip(true);
+        };
+        let variation_node = if let Some(val) = cur_node.parent() {
+            val
+        } else {
+            inner.diagnose(
+                Severity::Error,
+                "variation does not follow any move".to_string(),
+            );
+            inner.skipped_variations += 1;
+            return pgn_reader::Skip(true);
+        };
+
+        if let Some(max) = inner.limits.max_variation_depth {
+            // The mainline is always on the bottom of the stack
+            if inner.variation_stack.len() > max {
+                inner.exceed("variation depth", max);
+                return pgn_reader::Skip(true);
+            }
         }
+
+        inner.variation_stack.push(variation_node);
+        inner.in_variation = false;
+
+        pgn_reader::Skip(false)
+    }
+
+    /// Returns the game being read, unless reading was aborted.
+    fn try_get_inner(&mut self) -> Option<&mut PartialGame> {
+        self.inner
+            .as_deref_mut()
+            .filter(|inner| inner.exceeded.is_none())
+    }
+
+    /// Returns the game being read, unless the current variation is being
+    /// discarded or reading was aborted.
+    fn try_get_movetext_inner(&mut self) -> Option<&mut PartialGame> {
+        self.try_get_inner()
+            .filter(|inner| inner.discarding.is_none())
     }
 }
 
-impl pgn_reader::Visitor for GameVisitor {
-    type Result = Game;
+impl pgn_reader::Visitor for GameVisitor<'_> {
+    type Result = std::io::Result<(Game, Vec<Diagnostic>)>;
 
     fn begin_game(&mut self) {
         let root = Node::default();
@@ -45,6 +370,16 @@ impl pgn_reader::Visitor for GameVisitor {
         let variation_stack = vec![root.clone()];
 
         let inner = PartialGame {
+            decoding: self.options.decoding,
+            limits: self.options.limits.clone(),
+            illegal_move_policy: self.options.illegal_move_policy,
+            meta_commands: self.options.meta_commands.clone(),
+            exceeded: None,
+            ply_count: 0,
+            header_count: 0,
+            discarding: None,
+            skipped_variations: 0,
+
             header: Header::default(),
             opt_headers: HashMap::new(),
 
@@ -53,10 +388,12 @@ impl pgn_reader::Visitor for GameVisitor {
             variation_stack,
             in_variation: false,
 
-            starting_comment: None,
+            starting_comment_vec: Vec::new(),
+
+            diagnostics: Vec::new(),
         };
 
-        *self = GameVisitor::InGame { inner }
+        self.inner = Some(Box::new(inner));
     }
 
     fn header(&mut self, key: &[u8], value: pgn_reader::RawHeader<'_>) {
@@ -66,56 +403,149 @@ impl pgn_reader::Visitor for GameVisitor {
             return;
         };
 
-        if key == b"FEN" {
-            let pos = shakmaty::fen::Fen::from_ascii(value.as_bytes())
-                .ok()
-                .and_then(|f| f.into_position(shakmaty::CastlingMode::Standard).ok());
-
-            if let Some(pos) = pos {
-                inner.root = Node::from_position(pos);
+        inner.header_count += 1;
+        if let Some(max) = inner.limits.max_headers {
+            if inner.header_count > max {
+                inner.exceed("number of headers", max);
+                return;
             }
         }
 
-        let key = std::str::from_utf8(key).unwrap();
-        let value = std::str::from_utf8(value.as_bytes()).unwrap();
+        let key = if let Some(val) = inner.decode(key) {
+            val
+        } else {
+            return;
+        };
+        // Unescape \" and \\
+        let value = if let Some(val) = inner.decode(&value.decode()) {
+            val
+        } else {
+            return;
+        };
+        let (key, value) = (key.as_str(), value.as_str());
+
+        if key == "Result" && value != "*" {
+            if let GameResult::Ongoing = GameResult::from(value) {
+                inner.diagnose(
+                    Severity::Warning,
+                    format!("unrecognized result \"{}\"", value),
+                );
+            }
+        }
 
         if !inner.header.parse(key, value) {
             inner.opt_headers.insert(key.to_string(), value.to_string());
         }
     }
 
+    fn end_headers(&mut self) -> pgn_reader::Skip {
+        let inner = if let Some(val) = self.try_get_inner() {
+            val
+        } else {
+            return pgn_reader::Skip(false);
+        };
+
+        // The Variant tag may come after the FEN tag
+        let variant = if let Some(val) = inner.opt_headers.get("Variant") {
+            let variant = parse_variant(val);
+            if variant.is_none() {
+                let message = format!("unsupported variant \"{}\"", val);
+                inner.diagnose(Severity::Warning, message);
+            }
+            variant.unwrap_or_default()
+        } else {
+            Variant::Chess
+        };
+
+        let position = if let Some(fen) = inner.opt_headers.get("FEN") {
+            match position_from_fen(variant, fen) {
+                Some((position, true)) => {
+                    let message = format!(
+                        "castling rights for missing pieces in FEN header, playing from {}",
+                        position_fen(&position)
+                    );
+                    inner.diagnose(Severity::Warning, message);
+                    Some(position)
+                }
+                Some((position, false)) => Some(position),
+                None => {
+                    inner.diagnose(Severity::Error, "invalid FEN header".to_string());
+                    None
+                }
+            }
+        } else {
+            None
+        };
+        if position.is_some() || variant != Variant::Chess {
+            let position = position.unwrap_or_else(|| VariantPosition::new(variant));
+            inner.root = Node::from_position(position);
+            inner.variation_stack = vec![inner.root.clone()];
+        }
+
+        pgn_reader::Skip(false)
+    }
+
     fn san(&mut self, san_plus: shakmaty::san::SanPlus) {
-        let inner = if let Some(inner) = self.try_get_inner() {
+        let san_str = san_plus.to_string();
+        let offset = self.locate(&san_plus.san);
+
+        let inner = if let Some(inner) = self.try_get_movetext_inner() {
             inner
         } else {
             return;
         };
 
+        if let Some(max) = inner.limits.max_plies {
+            if inner.ply_count >= max {
+                inner.exceed("number of moves", max);
+                return;
+            }
+        }
+
         let cur_node = if let Some(inner) = inner.variation_stack.last_mut() {
             inner
         } else {
             return;
         };
 
-        let move_next = if let Ok(inner) = san_plus.san.to_move(&cur_node.position()) {
-            inner
+        let position = cur_node.position();
+        let move_next = if let Ok(val) = san_plus.san.to_move(&position) {
+            val
         } else {
+            let ply = cur_node.depth() + 1;
+            inner.diagnostics.push(Diagnostic {
+                ply,
+                severity: Severity::Error,
+                message: format!("illegal move {}", san_str),
+                illegal_move: Some(IllegalMove {
+                    move_number: position.fullmoves().get(),
+                    turn: position.turn(),
+                    san: san_str,
+                    offset,
+                }),
+            });
+            if inner.illegal_move_policy == IllegalMovePolicy::Discard {
+                inner.discarding = Some(inner.variation_stack.len());
+            }
             return;
         };
 
         // TODO:
-        let mut node_next = if let Some(inner) = cur_node.new_variation(move_next) {
+        let mut node_next = if let Some(inner) = cur_node.push_variation(move_next) {
             inner
         } else { return; };
-        node_next.set_starting_comment(inner.starting_comment.clone());
+        for text in std::mem::take(&mut inner.starting_comment_vec) {
+            node_next.push_comment(Comment::before_move(text));
+        }
         *cur_node = node_next;
 
-        inner.starting_comment = None;
+        inner.ply_count += 1;
+
         inner.in_variation = true;
     }
 
     fn nag(&mut self, nag: Nag) {
-        let inner = if let Some(val) = self.try_get_inner() {
+        let inner = if let Some(val) = self.try_get_movetext_inner() {
             val
         } else {
             return;
@@ -131,16 +561,24 @@ impl pgn_reader::Visitor for GameVisitor {
     }
 
     fn comment(&mut self, comment: RawComment<'_>) {
-        let inner = if let Some(val) = self.try_get_inner() {
+        let inner = if let Some(val) = self.try_get_movetext_inner() {
             val
         } else {
             return;
         };
 
-        let comment = std::str::from_utf8(comment.as_bytes())
-            .unwrap()
-            .trim()
-            .to_string();
+        if let Some(max) = inner.limits.max_comment_len {
+            if comment.as_bytes().len() > max {
+                inner.exceed("comment length", max);
+                return;
+            }
+        }
+
+        let comment = if let Some(val) = inner.decode(comment.as_bytes()) {
+            val.trim().to_string()
+        } else {
+            return;
+        };
 
         let cur_node = if let Some(val) = inner.variation_stack.last_mut() {
             val
@@ -151,47 +589,74 @@ impl pgn_reader::Visitor for GameVisitor {
         if inner.in_variation // Regular comment
             || (cur_node.parent().is_none() && cur_node.variation_vec().is_empty())
         {
+            if cur_node.parent().is_some() {
+                let (mut clock, mut emt, mut eval) = (None, None, None);
+                let mut meta_vec = Vec::new();
+                let mut shape_vec = Vec::new();
+                let comment = command::extract_commands(&comment, |name, args| match name {
+                    "clk" => {
+                        clock = command::parse_clock(args);
+                        clock.is_some()
+                    }
+                    "emt" => {
+                        emt = command::parse_clock(args);
+                        emt.is_some()
+                    }
+                    "eval" => {
+                        eval = command::parse_eval(args);
+                        eval.is_some()
+                    }
+                    "csl" | "cal" => match command::parse_shapes(args, name == "cal") {
+                        Some(shapes) => {
+                            shape_vec.extend(shapes);
+                            true
+                        }
+                        None => false,
+                    },
+                    _ if inner.meta_commands.iter().any(|k| k == name) => {
+                        meta_vec.push((name.to_string(), args.to_string()));
+                        true
+                    }
+                    _ => false,
+                });
+
+                if clock.is_some() {
+                    cur_node.set_clock(clock);
+                }
+                if emt.is_some() {
+                    cur_node.set_emt(emt);
+                }
+                if eval.is_some() {
+                    cur_node.set_eval_info(eval);
+                }
+                if !shape_vec.is_empty() {
+                    let mut shapes = cur_node.shapes();
+                    shapes.extend(shape_vec);
+                    cur_node.set_shapes(shapes);
+                }
+                for (key, value) in meta_vec {
+                    cur_node.set_meta(&key, Some(value));
+                }
+                if !comment.is_empty() {
+                    cur_node.push_comment(Comment::after_move(comment));
+                }
+                return;
+            }
+
             // Comment is before any move
-            let new_comment = if let Some(val) = cur_node.comment() {
-                format!("{} {}", val, comment)
-            } else {
-                comment
-            };
-            cur_node.set_comment(Some(new_comment));
+            cur_node.push_comment(Comment::after_move(comment));
             return;
         }
 
-        let starting_comment = if let Some(val) = &inner.starting_comment {
-            format!("{} {}", val, comment)
-        } else {
-            comment
-        };
-        inner.starting_comment = Some(starting_comment);
+        inner.starting_comment_vec.push(comment);
     }
 
     fn begin_variation(&mut self) -> pgn_reader::Skip {
-        let inner = if let Some(val) = self.try_get_inner() {
-            val
-        } else {
-            return pgn_reader::Skip(true);
-        };
-
-        let cur_node = if let Some(val) = inner.variation_stack.last_mut() {
-            val
-        } else {
-            return pgn_reader::Skip(true);
-        };
-        let variation_node = if let Some(val) = cur_node.parent() {
-            val
-        } else {
-            println!("begin_variation called, but root node on top of stack");
-            return pgn_reader::Skip(true);
-        };
-
-        inner.variation_stack.push(variation_node);
-        inner.in_variation = false;
-
-        pgn_reader::Skip(false)
+        let skip = self.open_variation();
+        if skip.0 {
+            self.skip_source_variation();
+        }
+        skip
     }
 
     fn end_variation(&mut self) {
@@ -200,38 +665,84 @@ impl pgn_reader::Visitor for GameVisitor {
         } else {
             return;
         };
+        if inner.skipped_variations > 0 {
+            inner.skipped_variations -= 1;
+            return;
+        }
+
+        if inner.discarding == Some(inner.variation_stack.len()) {
+            // End of the variation with the illegal move
+            inner.discarding = None;
+        }
 
         inner.variation_stack.pop();
     }
 
     fn end_game(&mut self) -> Self::Result {
-        let inner = if let Some(val) = self.try_get_inner() {
-            val
+        let inner = if let Some(val) = self.inner.take() {
+            *val
         } else {
-            return Game::default();
+            return Ok((Game::default(), Vec::new()));
         };
 
-        let header = inner.header.clone();
-        let opt_headers = inner.opt_headers.clone();
-
-        let root = inner.root.clone();
+        if let Some(message) = inner.exceeded {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                message,
+            ));
+        }
 
-        *self = Self::None;
+        let game = Game {
+            header: inner.header,
+            opt_headers: inner.opt_headers,
 
-        Game {
-            header,
-            opt_headers,
+            root: inner.root,
+        };
 
-            root,
-        }
+        Ok((game, inner.diagnostics))
     }
 }
 
 pub fn read_pgn(pgn: &str) -> std::io::Result<Game> {
+    let (game, _) = validate_pgn(pgn)?;
+    Ok(game)
+}
+
+pub fn validate_pgn(pgn: &str) -> std::io::Result<(Game, Vec<Diagnostic>)> {
+    read_pgn_with(pgn, &ReaderOptions::default())
+}
+
+pub fn read_pgn_with(
+    pgn: impl AsRef<[u8]>,
+    options: &ReaderOptions,
+) -> std::io::Result<(Game, Vec<Diagnostic>)> {
+    let pgn = pgn.as_ref();
     let mut reader = pgn_reader::BufferedReader::new_cursor(pgn);
 
-    let mut visitor = GameVisitor::new();
-    let visited_game = reader.read_game(&mut visitor)?.unwrap();
+    let mut visitor = GameVisitor::new(options.clone(), pgn);
+    match reader.read_game(&mut visitor)? {
+        Some(visited_game) => visited_game,
+        None => Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            "no game",
+        )),
+    }
+}
+
+/// Reads every game in the given PGN text.
+#[cfg(feature = "tokio")]
+pub(crate) fn read_games_with(
+    pgn: impl AsRef<[u8]>,
+    options: &ReaderOptions,
+) -> std::io::Result<Vec<(Game, Vec<Diagnostic>)>> {
+    let pgn = pgn.as_ref();
+    let mut reader = pgn_reader::BufferedReader::new_cursor(pgn);
+
+    let mut visitor = GameVisitor::new(options.clone(), pgn);
+    let mut game_vec = Vec::new();
+    while let Some(visited_game) = reader.read_game(&mut visitor)? {
+        game_vec.push(visited_game?);
+    }
 
-    Ok(visited_game)
+    Ok(game_vec)
 }
@@ -1,68 +1,279 @@
-use crate::game::{Game, Node};
-use crate::{Chess, Color, Move, Position};
+use super::command;
+use crate::game::{
+    nag_gloss, Comment, CommentPlacement, Eval, EvalInfo, Game, GameResult, Node, Shape,
+};
+use crate::{Color, Move, Position, Role, VariantPosition};
+
+use std::io::Write;
+use std::time::Duration;
 
 pub(crate) trait PartialAcceptor {
     fn accept<V: Visitor>(&self, visitor: &mut V);
 }
 
 pub(crate) trait FullAcceptor {
-    fn accept<V: Visitor>(&self, visitor: &mut V) -> V::Result;
+    fn accept<V: Visitor>(&self, visitor: &mut V, layout: &CommentLayout) -> V::Result;
+}
+
+/// Where each kind of comment is visited, see
+/// [`PgnWriterOptions::starting_comments`] and its siblings.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct CommentLayout {
+    starting: Option<CommentPlacement>,
+    moves: Option<CommentPlacement>,
+    game: GameCommentPlacement,
+}
+
+impl Default for CommentLayout {
+    fn default() -> Self {
+        Self {
+            starting: Some(CommentPlacement::BeforeMove),
+            moves: Some(CommentPlacement::AfterMove),
+            game: GameCommentPlacement::BeforeMoves,
+        }
+    }
+}
+
+impl From<&PgnWriterOptions> for CommentLayout {
+    fn from(options: &PgnWriterOptions) -> Self {
+        Self {
+            starting: options.starting_comments,
+            moves: options.move_comments,
+            game: options.game_comments,
+        }
+    }
 }
 
 impl FullAcceptor for Game {
-    fn accept<V: Visitor>(&self, visitor: &mut V) -> V::Result {
+    fn accept<V: Visitor>(&self, visitor: &mut V, layout: &CommentLayout) -> V::Result {
         visitor.begin_game();
 
         visitor.begin_headers();
         {
+            // The Seven Tag Roster always comes first, in this order
             self.header.accept(visitor);
 
-            for (key, value) in &self.opt_headers {
-                visitor.visit_header(key, value);
+            for (key, value) in self.optional_tags() {
+                visitor.visit_header(&key, &value);
             }
         }
         visitor.end_headers();
 
-        if let Some(comment) = self.root.comment() {
-            // Game comment
-            visitor.visit_comment(comment);
+        if layout.game == GameCommentPlacement::BeforeMoves {
+            for comment in self.root.comments() {
+                visitor.visit_comment(comment.text);
+            }
         }
 
-        self.root.accept(&self.initial_position(), visitor);
+        self.root.accept(&self.initial_position(), visitor, layout);
 
         let result = self.header.result.to_string();
         visitor.visit_result(result.as_str());
 
+        if layout.game == GameCommentPlacement::AfterResult {
+            for comment in self.root.comments() {
+                visitor.visit_comment(comment.text);
+            }
+        }
+
         visitor.end_game()
     }
 }
 
+impl Game {
+    /// Walks the game with the given visitor, in PGN order.
+    ///
+    /// # Arguments
+    ///
+    /// * `visitor` - e.g. a [`PgnWriter`] or an [`HtmlWriter`](crate::export::HtmlWriter)
+    pub fn visit<V: Visitor>(&self, visitor: &mut V) -> V::Result {
+        self.accept(visitor, &CommentLayout::default())
+    }
+
+    /// Writes the game as PGN into the given sink, line by line.
+    ///
+    /// Unlike formatting the game with `Display`, this never holds more than
+    /// one line of the output in memory.
+    ///
+    /// # Arguments
+    ///
+    /// * `sink` - where to write the PGN
+    /// * `options` - writer options, e.g. the maximum line width
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let game = sacrifice::read_pgn("1. e4 e5").unwrap();
+    /// let mut buffer: Vec<u8> = Vec::new();
+    /// game.write_pgn(&mut buffer, &sacrifice::PgnWriterOptions::default()).unwrap();
+    /// assert!(String::from_utf8(buffer).unwrap().ends_with("1. e4 e5 *\n"));
+    /// ```
+    pub fn write_pgn<W: Write>(&self, sink: W, options: &PgnWriterOptions) -> std::io::Result<()> {
+        let mut visitor = PgnWriter::new(sink, options);
+        if options.fill_result {
+            visitor.inferred_result = self.mainline_result();
+        }
+
+        if options.fill_ply_count || options.fill_opening.is_some() {
+            // Shallow; only the headers are copied
+            let mut game = self.clone();
+            if options.fill_ply_count {
+                let ply_count = self.root().mainline_iter().count();
+                game.set_tag("PlyCount", ply_count.to_string());
+            }
+            let opening = options.fill_opening.and_then(|classify| {
+                self.root()
+                    .mainline_iter()
+                    .filter_map(|n| classify(&n))
+                    .last()
+            });
+            if let Some((eco, name)) = opening {
+                game.set_tag("ECO", eco);
+                game.set_tag("Opening", name);
+            }
+            return game.accept(&mut visitor, &CommentLayout::from(options));
+        }
+        self.accept(&mut visitor, &CommentLayout::from(options))
+    }
+
+    /// Returns the game as PGN, written with the given options.
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - writer options
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sacrifice::{HeaderSelection, PgnWriterOptions};
+    ///
+    /// let game = sacrifice::read_pgn("1. e4 $1 { best by test } (1. d4) 1... e5").unwrap();
+    /// let options = PgnWriterOptions {
+    ///     variations: false,
+    ///     comments: false,
+    ///     headers: HeaderSelection::None,
+    ///     ..PgnWriterOptions::default()
+    /// };
+    /// assert_eq!(game.to_pgn_with(&options), "1. e4 $1 e5 *\n");
+    /// ```
+    pub fn to_pgn_with(&self, options: &PgnWriterOptions) -> String {
+        let mut buffer: Vec<u8> = Vec::new();
+        self.write_pgn(&mut buffer, options)
+            .expect("writing to a Vec never fails");
+
+        // Everything written is valid UTF-8
+        String::from_utf8(buffer).unwrap()
+    }
+
+    /// Returns the game as PGN with only the main line, i.e. without
+    /// variations, comments, NAGs or commands.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let game = sacrifice::read_pgn("1. e4 $1 { best by test } (1. d4) 1... e5 *").unwrap();
+    /// assert!(game.mainline_pgn().ends_with("\n\n1. e4 e5 *\n"));
+    /// ```
+    pub fn mainline_pgn(&self) -> String {
+        self.to_pgn_with(&PgnWriterOptions::mainline_only())
+    }
+
+    /// Returns the movetext alone on one line, without tag pairs or the
+    /// result, as used for opening lines in books and training sites.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let game = sacrifice::read_pgn("[White \"Kasparov\"]\n\n1. e4 c5 2. Nf3 *").unwrap();
+    /// assert_eq!(game.movetext(), "1. e4 c5 2. Nf3");
+    /// assert_eq!(sacrifice::game::Game::default().movetext(), "");
+    /// ```
+    pub fn movetext(&self) -> String {
+        let pgn = self.to_pgn_with(&PgnWriterOptions::movetext_only());
+        pgn.trim_end().to_string()
+    }
+}
+
 pub(crate) trait NodeAcceptor {
-    fn accept_inner<V: Visitor>(&self, prev_position: &Chess, visitor: &mut V);
-    fn accept<V: Visitor>(&self, initial_position: &Chess, visitor: &mut V);
+    fn accept_inner<V: Visitor>(
+        &self,
+        prev_position: &VariantPosition,
+        visitor: &mut V,
+        layout: &CommentLayout,
+    );
+    fn accept<V: Visitor>(
+        &self,
+        initial_position: &VariantPosition,
+        visitor: &mut V,
+        layout: &CommentLayout,
+    );
 }
 
 impl NodeAcceptor for Node {
-    fn accept_inner<V: Visitor>(&self, prev_position: &Chess, visitor: &mut V) {
-        if let Some(starting_comment) = self.starting_comment() {
-            visitor.visit_comment(starting_comment);
+    fn accept_inner<V: Visitor>(
+        &self,
+        prev_position: &VariantPosition,
+        visitor: &mut V,
+        layout: &CommentLayout,
+    ) {
+        // Starting comments first, then the move's own comments
+        let (starting_vec, move_vec): (Vec<Comment>, Vec<Comment>) = self
+            .comments()
+            .into_iter()
+            .partition(|c| c.placement == CommentPlacement::BeforeMove);
+        let comment_vec = |placement: CommentPlacement| {
+            let starting_vec = starting_vec
+                .iter()
+                .filter(move |_| layout.starting == Some(placement));
+            let move_vec = move_vec
+                .iter()
+                .filter(move |_| layout.moves == Some(placement));
+            starting_vec.chain(move_vec).map(|c| c.text.clone())
+        };
+
+        for comment in comment_vec(CommentPlacement::BeforeMove) {
+            visitor.visit_comment(comment);
         }
 
         // Visit the mainline node first
         visitor.visit_move(prev_position.clone(), self.prev_move().unwrap());
 
         if let Some(nags) = self.nags() {
-            for nag in nags {
+            let mut nag_vec: Vec<u8> = nags.into_iter().collect();
+            nag_vec.sort_unstable();
+            for nag in nag_vec {
                 visitor.visit_nag(nag);
             }
         }
 
-        if let Some(comment) = self.comment() {
+        if let Some(info) = self.eval_info() {
+            visitor.visit_eval_info(&info);
+        }
+        if let Some(clock) = self.clock() {
+            visitor.visit_clock(clock);
+        }
+        if let Some(emt) = self.emt() {
+            visitor.visit_emt(emt);
+        }
+        let shape_vec = self.shapes();
+        if !shape_vec.is_empty() {
+            visitor.visit_shapes(&shape_vec);
+        }
+        for (key, value) in self.meta_map() {
+            visitor.visit_meta(&key, &value);
+        }
+
+        for comment in comment_vec(CommentPlacement::AfterMove) {
             visitor.visit_comment(comment);
         }
     }
 
-    fn accept<V: Visitor>(&self, initial_position: &Chess, visitor: &mut V) {
+    fn accept<V: Visitor>(
+        &self,
+        initial_position: &VariantPosition,
+        visitor: &mut V,
+        layout: &CommentLayout,
+    ) {
         // Return if there's no child nodes
         let main_node = if let Some(val) = self.mainline() {
             val
@@ -70,7 +281,7 @@ impl NodeAcceptor for Node {
             return;
         };
 
-        main_node.accept_inner(&self.position(), visitor);
+        main_node.accept_inner(&self.position(), visitor, layout);
 
         // Visit variation nodes after
         let mut variation_node_vec = self.variation_vec();
@@ -80,16 +291,16 @@ impl NodeAcceptor for Node {
                 continue; // Skip this variation
             }
 
-            variation_node.accept_inner(&self.position(), visitor);
+            variation_node.accept_inner(&self.position(), visitor, layout);
 
             // Recursively visiting variation node
-            variation_node.accept(initial_position, visitor);
+            variation_node.accept(initial_position, visitor, layout);
 
             visitor.end_variation();
         }
 
         // Visit mainline recursively last
-        main_node.accept(initial_position, visitor);
+        main_node.accept(initial_position, visitor, layout);
     }
 }
 
@@ -104,9 +315,23 @@ pub trait Visitor {
     fn visit_header(&mut self, tag_name: &str, tag_value: &str);
     fn end_headers(&mut self);
 
-    fn visit_move(&mut self, board: Chess, next_move: Move);
+    fn visit_move(&mut self, board: VariantPosition, next_move: Move);
     fn visit_comment(&mut self, comment: String);
     fn visit_nag(&mut self, nag: u8);
+    fn visit_eval(&mut self, eval: Eval);
+    /// Visits the evaluation with its depth and engine; passes it on to
+    /// [`Visitor::visit_eval`] unless overridden.
+    fn visit_eval_info(&mut self, info: &EvalInfo) {
+        self.visit_eval(info.eval);
+    }
+    fn visit_clock(&mut self, clock: Duration);
+    /// Visits the time spent on the move; ignored unless overridden.
+    fn visit_emt(&mut self, _emt: Duration) {}
+    /// Visits the arrows and highlighted squares of the move; ignored
+    /// unless overridden.
+    fn visit_shapes(&mut self, _shapes: &[Shape]) {}
+    /// Visits an entry of the move's metadata; ignored unless overridden.
+    fn visit_meta(&mut self, _key: &str, _value: &str) {}
 
     fn begin_variation(&mut self) -> Skip;
     fn end_variation(&mut self);
@@ -116,78 +341,490 @@ pub trait Visitor {
     fn end_game(&mut self) -> Self::Result;
 }
 
-pub struct PgnWriter {
-    max_width: Option<u32>,
+/// When moves are preceded by their move number.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MoveNumberStyle {
+    /// Before White's moves, and before Black's moves after an interruption
+    /// such as a comment or variation (`1. e4 e5 2. Nf3 { ok } 2... Nc6`)
+    #[default]
+    Standard,
+    /// Before every move (`1. e4 1... e5`)
+    Always,
+    /// Never, as accepted by most PGN readers (`e4 e5 Nf3`)
+    Never,
+}
 
-    line_vec: Vec<String>,
-    cur_line: String,
+/// Where the comments before the first move are written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum GameCommentPlacement {
+    #[default]
+    BeforeMoves,
+    /// After the game termination marker, as some databases expect
+    AfterResult,
+    /// Not at all
+    Omit,
+}
 
-    force_move_number: bool,
+/// How variations are laid out in the movetext.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum VariationLayout {
+    /// In the same paragraph as the moves around them
+    #[default]
+    Inline,
+    /// Each variation on its own line, indented by this many spaces per
+    /// nesting level, with the main line resuming on a new line
+    Indented(usize),
+}
+
+pub(crate) const SEVEN_TAG_ROSTER: [&str; 7] =
+    ["Event", "Site", "Date", "Round", "White", "Black", "Result"];
+
+/// Which tag pairs are written.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum HeaderSelection {
+    #[default]
+    All,
+    /// Only the Seven Tag Roster (Event, Site, Date, Round, White, Black, Result)
+    SevenTagRoster,
+    /// Only the tags with these names
+    Only(Vec<String>),
+    /// No tag section at all
+    None,
 }
 
-impl PgnWriter {
-    pub fn new() -> Self {
+impl HeaderSelection {
+    fn includes(&self, tag_name: &str) -> bool {
+        match self {
+            HeaderSelection::All => true,
+            HeaderSelection::SevenTagRoster => SEVEN_TAG_ROSTER.contains(&tag_name),
+            HeaderSelection::Only(name_vec) => name_vec.iter().any(|name| name == tag_name),
+            HeaderSelection::None => false,
+        }
+    }
+}
+
+/// Line terminator of the output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LineEnding {
+    #[default]
+    Lf,
+    CrLf,
+}
+
+/// Returns the ECO code and name of the opening of a position, or `None`
+/// for a position it does not know, see [`PgnWriterOptions::fill_opening`].
+pub type OpeningClassifier = fn(&Node) -> Option<(String, String)>;
+
+/// Options controlling how PGN is written.
+#[derive(Debug, Clone)]
+pub struct PgnWriterOptions {
+    /// Wrap movetext lines at this many columns, e.g. `Some(80)` for the
+    /// export format. Moves are never split; comments wrap between words.
+    pub max_width: Option<u32>,
+
+    pub variations: bool,
+    pub comments: bool,
+    /// Where comments that start a move (`{ idea } 1. e4`) are written,
+    /// or `None` to leave them out
+    pub starting_comments: Option<CommentPlacement>,
+    /// Where comments that follow a move (`1. e4 { idea }`) are written,
+    /// or `None` to leave them out
+    pub move_comments: Option<CommentPlacement>,
+    pub game_comments: GameCommentPlacement,
+    pub nags: bool,
+    /// Write NAGs 1 to 6 as move suffixes (`!`, `?`, `!!`, `??`, `!?`, `?!`)
+    pub symbolic_nags: bool,
+    /// Write the other standard NAGs as comments describing them
+    /// (`$14` as `{ White has a slight advantage }`), see [`nag_gloss`]
+    pub nag_glosses: bool,
+
+    /// Write clocks, move times, evaluations and shapes as `[%clk]`,
+    /// `[%emt]`, `[%eval]`, `[%csl]` and `[%cal]` commands
+    pub commands: bool,
+    /// Digits after the decimal point of `[%clk]` and `[%emt]` seconds
+    pub clock_decimals: u8,
+    /// Digits after the decimal point of `[%eval]` pawn values
+    pub eval_decimals: u8,
+    /// Metadata keys written as `[%key value]` commands, see
+    /// [`Node::meta`](crate::game::Node::meta); other metadata is not written
+    pub meta_commands: Vec<String>,
+
+    pub move_numbers: MoveNumberStyle,
+    pub variation_layout: VariationLayout,
+    pub headers: HeaderSelection,
+    /// Write today's date instead of an unknown `Date`
+    pub fill_date: bool,
+    /// Write the result of a checkmate or draw at the end of the main line
+    /// instead of an unknown (`*`) `Result`
+    pub fill_result: bool,
+    /// Write a `PlyCount` tag with the number of half-moves in the main
+    /// line, replacing any existing one
+    pub fill_ply_count: bool,
+    /// Write `ECO` and `Opening` tags, replacing any existing ones, for
+    /// the last main line position the classifier knows; see
+    /// [`Game::annotate_openings`] for naming lines instead
+    pub fill_opening: Option<OpeningClassifier>,
+    /// Write the game termination marker (`1-0`, `*`, ...)
+    pub result: bool,
+    pub line_ending: LineEnding,
+}
+
+impl PgnWriterOptions {
+    /// Returns options that write only the main line, without variations,
+    /// comments, NAGs or commands, e.g. to feed engines or book builders.
+    pub fn mainline_only() -> Self {
+        Self {
+            variations: false,
+            comments: false,
+            nags: false,
+            commands: false,
+            ..Self::default()
+        }
+    }
+
+    /// Returns options that write only the movetext on a single line,
+    /// without the tag section or the result.
+    pub fn movetext_only() -> Self {
+        Self {
+            max_width: None,
+            headers: HeaderSelection::None,
+            result: false,
+            ..Self::default()
+        }
+    }
+}
+
+impl Default for PgnWriterOptions {
+    fn default() -> Self {
         Self {
             max_width: None,
 
-            line_vec: Vec::new(),
-            cur_line: String::new(),
+            variations: true,
+            comments: true,
+            starting_comments: Some(CommentPlacement::BeforeMove),
+            move_comments: Some(CommentPlacement::AfterMove),
+            game_comments: GameCommentPlacement::default(),
+            nags: true,
+            symbolic_nags: false,
+            nag_glosses: false,
 
-            force_move_number: false,
+            commands: true,
+            clock_decimals: 0,
+            eval_decimals: 2,
+            meta_commands: Vec::new(),
+
+            move_numbers: MoveNumberStyle::default(),
+            variation_layout: VariationLayout::default(),
+            headers: HeaderSelection::default(),
+            fill_date: false,
+            fill_result: false,
+            fill_ply_count: false,
+            fill_opening: None,
+            result: true,
+            line_ending: LineEnding::default(),
         }
     }
+}
+
+pub struct PgnWriter<W: Write> {
+    sink: W,
+    /// The first error from writing to `sink`
+    error: Option<std::io::Error>,
+
+    options: PgnWriterOptions,
+
+    cur_line: String,
+    /// Byte offset of the last token in `cur_line`
+    last_token_start: usize,
+    header_count: usize,
+    /// Commands waiting to be written in the next comment
+    pending_command_vec: Vec<String>,
+
+    /// Replaces an unknown result, see [`PgnWriterOptions::fill_result`]
+    inferred_result: Option<GameResult>,
+
+    force_move_number: bool,
+    /// Whether the last move can still take a symbolic NAG
+    move_suffix_free: bool,
 
-    pub fn with_max_width(max_width: u32) -> Self {
+    variation_depth: usize,
+    /// Start a new line before the next token
+    line_break_pending: bool,
+}
+
+impl<W: Write> PgnWriter<W> {
+    pub fn new(sink: W, options: &PgnWriterOptions) -> Self {
         Self {
-            max_width: Some(max_width),
+            sink,
+            error: None,
+
+            options: options.clone(),
 
-            line_vec: Vec::new(),
             cur_line: String::new(),
+            last_token_start: 0,
+            header_count: 0,
+            pending_command_vec: Vec::new(),
+
+            inferred_result: None,
 
             force_move_number: false,
+            move_suffix_free: false,
+
+            variation_depth: 0,
+            line_break_pending: false,
         }
     }
 }
 
-impl PgnWriter {
+impl<W: Write> PgnWriter<W> {
+    fn emit_line(&mut self, line: &str) {
+        if self.error.is_some() {
+            return;
+        }
+
+        let line_ending = match self.options.line_ending {
+            LineEnding::Lf => "\n",
+            LineEnding::CrLf => "\r\n",
+        };
+        if let Err(err) = write!(self.sink, "{}{}", line, line_ending) {
+            self.error = Some(err);
+        }
+    }
+
     fn flush(&mut self) {
-        let cur_line = self.cur_line.trim();
+        let cur_line = std::mem::take(&mut self.cur_line);
+        // Keep the indentation of variations
+        let cur_line = cur_line.trim_end();
         if cur_line.is_empty() {
             // Nothing to write
             return;
         }
 
-        self.line_vec.push(cur_line.to_string());
-        self.cur_line = String::new();
+        self.emit_line(cur_line);
+    }
+
+    fn overflows(&self, extra_width: usize) -> bool {
+        match self.options.max_width {
+            Some(max_width) => self.cur_line.chars().count() + extra_width > max_width as usize,
+            None => false,
+        }
     }
 
+    /// Returns the leading spaces of a line at the current variation depth.
+    fn indentation(&self) -> String {
+        match self.options.variation_layout {
+            VariationLayout::Inline => String::new(),
+            VariationLayout::Indented(width) => " ".repeat(width * self.variation_depth),
+        }
+    }
+
+    /// Writes a token that is never split across lines.
     fn write_token(&mut self, token: impl AsRef<str>) {
         let token = token.as_ref();
 
-        if let Some(max_width) = self.max_width {
-            if ((max_width as usize) < self.cur_line.len())
-                || (max_width as usize - self.cur_line.len() < token.len())
-            {
+        if self.line_break_pending {
+            self.line_break_pending = false;
+            self.flush();
+        }
+
+        if !self.cur_line.is_empty() {
+            if self.overflows(1 + token.chars().count()) {
                 self.flush();
+            } else {
+                self.cur_line.push(' ');
+            }
+        }
+        if self.cur_line.is_empty() {
+            self.cur_line = self.indentation();
+        }
+
+        self.last_token_start = self.cur_line.len();
+        self.cur_line.push_str(token);
+        self.move_suffix_free = false;
+    }
+
+    /// Appends to the last token, moving it to a new line if needed.
+    fn extend_token(&mut self, suffix: &str) {
+        let first_on_line = self.cur_line[..self.last_token_start].trim().is_empty();
+        if !first_on_line && self.overflows(suffix.chars().count()) {
+            let token = self.cur_line.split_off(self.last_token_start);
+            self.flush();
+            self.cur_line = self.indentation();
+            self.last_token_start = self.cur_line.len();
+            self.cur_line.push_str(&token);
+        }
+
+        self.cur_line.push_str(suffix);
+    }
+
+    /// Writes the pending commands and the comment, wrapping between words.
+    fn write_comment(&mut self, comment: &str) {
+        let mut word_vec = std::mem::take(&mut self.pending_command_vec);
+        // A comment cannot contain its own terminator
+        word_vec.extend(
+            comment
+                .split_whitespace()
+                .map(|word| word.replace('}', ""))
+                .filter(|word| !word.is_empty()),
+        );
+
+        let last = if let Some(val) = word_vec.pop() {
+            val
+        } else {
+            return;
+        };
+
+        if word_vec.is_empty() {
+            self.write_token(format!("{{ {} }}", last));
+        } else {
+            self.write_token(format!("{{ {}", word_vec[0]));
+            for word in &word_vec[1..] {
+                self.write_token(word);
             }
+            self.write_token(format!("{} }}", last));
         }
 
-        self.cur_line = format!("{}{}", self.cur_line, token)
+        self.force_move_number = true;
+    }
+
+    fn flush_commands(&mut self) {
+        if !self.pending_command_vec.is_empty() {
+            self.write_comment("");
+        }
     }
 
     fn write_line(&mut self, new_line: String) {
         self.flush();
-        self.line_vec.push(new_line.trim().to_string())
+        self.emit_line(new_line.trim())
     }
 }
 
-impl Visitor for PgnWriter {
-    type Result = Vec<String>;
+/// Writes many games into one PGN file, separated by blank lines.
+///
+/// # Examples
+///
+/// ```
+/// use sacrifice::{PgnDatabaseWriter, PgnWriterOptions};
+///
+/// let mut writer = PgnDatabaseWriter::new(Vec::new(), &PgnWriterOptions::default());
+/// writer.write_game(&sacrifice::read_pgn("1. e4 e5 *").unwrap()).unwrap();
+/// writer.write_game(&sacrifice::read_pgn("1. d4 d5 *").unwrap()).unwrap();
+/// assert_eq!(writer.game_count(), 2);
+///
+/// let pgn = String::from_utf8(writer.into_inner()).unwrap();
+/// assert!(pgn.contains("1. e4 e5 *\n\n[Event \"?\"]"));
+/// assert_eq!(sacrifice::read_pgn(&pgn).unwrap().movetext(), "1. e4 e5");
+/// ```
+pub struct PgnDatabaseWriter<W: Write> {
+    sink: W,
+    options: PgnWriterOptions,
+
+    game_count: usize,
+}
+
+impl<W: Write> PgnDatabaseWriter<W> {
+    pub fn new(sink: W, options: &PgnWriterOptions) -> Self {
+        Self {
+            sink,
+            options: options.clone(),
+
+            game_count: 0,
+        }
+    }
+
+    /// Appends a game, preceded by a blank line unless it is the first one.
+    ///
+    /// # Arguments
+    ///
+    /// * `game` - the game to write
+    pub fn write_game(&mut self, game: &Game) -> std::io::Result<()> {
+        if self.game_count > 0 {
+            let line_ending = match self.options.line_ending {
+                LineEnding::Lf => "\n",
+                LineEnding::CrLf => "\r\n",
+            };
+            self.sink.write_all(line_ending.as_bytes())?;
+        }
+
+        game.write_pgn(&mut self.sink, &self.options)?;
+        self.game_count += 1;
+
+        Ok(())
+    }
+
+    /// Returns the number of games written so far.
+    pub fn game_count(&self) -> usize {
+        self.game_count
+    }
+
+    /// Returns the underlying sink.
+    pub fn into_inner(self) -> W {
+        self.sink
+    }
+}
+
+/// Escapes quotes and backslashes, and replaces line breaks which cannot
+/// appear in a tag pair.
+fn escape_tag_value(value: &str) -> String {
+    let mut ret = String::with_capacity(value.len());
+    for ch in value.chars() {
+        match ch {
+            '"' | '\\' => {
+                ret.push('\\');
+                ret.push(ch);
+            }
+            '\r' | '\n' | '\t' => ret.push(' '),
+            _ => ret.push(ch),
+        }
+    }
+    ret
+}
+
+/// Returns the current UTC date as a PGN date (`YYYY.MM.DD`).
+fn today() -> String {
+    let secs = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+
+    // Civil date from days since 1970-01-01 (Howard Hinnant's algorithm)
+    let days = (secs / 86400) as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    format!("{:04}.{:02}.{:02}", year, month, day)
+}
+
+pub(crate) fn nag_suffix(nag: u8) -> Option<&'static str> {
+    match nag {
+        1 => Some("!"),
+        2 => Some("?"),
+        3 => Some("!!"),
+        4 => Some("??"),
+        5 => Some("!?"),
+        6 => Some("?!"),
+        _ => None,
+    }
+}
+
+impl<W: Write> Visitor for PgnWriter<W> {
+    type Result = std::io::Result<()>;
 
     fn begin_game(&mut self) {
-        self.line_vec = Vec::new();
         self.cur_line = String::new();
-        self.force_move_number = false;
+        self.last_token_start = 0;
+        self.header_count = 0;
+        self.pending_command_vec.clear();
+        self.variation_depth = 0;
+        self.line_break_pending = false;
+        // The first move is numbered even if Black plays it
+        self.force_move_number = true;
     }
 
     fn begin_headers(&mut self) {
@@ -195,55 +832,204 @@ impl Visitor for PgnWriter {
     }
 
     fn visit_header(&mut self, tag_name: &str, tag_value: &str) {
-        self.write_line(format!("[{} \"{}\"]", tag_name, tag_value));
+        if !self.options.headers.includes(tag_name) {
+            return;
+        }
+
+        let tag_value = match (tag_name, tag_value) {
+            ("Date", "????.??.??") if self.options.fill_date => today(),
+            ("Result", "*") => self
+                .inferred_result
+                .map_or_else(|| tag_value.to_string(), |result| result.to_string()),
+            _ => tag_value.to_string(),
+        };
+
+        self.write_line(format!(
+            "[{} \"{}\"]",
+            tag_name,
+            escape_tag_value(&tag_value)
+        ));
+        self.header_count += 1;
     }
 
     fn end_headers(&mut self) {
-        self.write_line(String::new());
+        if self.header_count > 0 {
+            self.write_line(String::new());
+        }
     }
 
-    fn visit_move(&mut self, board: Chess, next_move: Move) {
-        let move_prefix = if board.turn() == Color::White {
-            format!("{}. ", board.fullmoves())
-        } else if self.force_move_number {
-            format!("{}... ", board.fullmoves())
-        } else {
-            String::new()
+    fn visit_move(&mut self, board: VariantPosition, next_move: Move) {
+        self.flush_commands();
+
+        let move_prefix = match self.options.move_numbers {
+            MoveNumberStyle::Never => String::new(),
+            _ if board.turn() == Color::White => format!("{}. ", board.fullmoves()),
+            MoveNumberStyle::Always => format!("{}... ", board.fullmoves()),
+            _ if self.force_move_number => format!("{}... ", board.fullmoves()),
+            _ => String::new(),
         };
 
         let san = shakmaty::san::SanPlus::from_move(board, &next_move);
-        self.write_token(format!("{}{} ", move_prefix, san));
+        // Pawn drops are written with the pawn, as lichess does
+        let drop_prefix = match san.san {
+            shakmaty::san::San::Put {
+                role: Role::Pawn, ..
+            } => "P",
+            _ => "",
+        };
+        self.write_token(format!("{}{}{}", move_prefix, drop_prefix, san));
 
         self.force_move_number = false;
+        self.move_suffix_free = true;
     }
 
     fn visit_comment(&mut self, comment: String) {
-        self.write_token(format!("{{ {} }} ", comment.trim()));
-        self.force_move_number = true;
+        if !self.options.comments {
+            return;
+        }
+
+        // Commands of the move share its first comment
+        self.write_comment(comment.trim());
     }
 
     fn visit_nag(&mut self, nag: u8) {
-        self.write_token(format!("${} ", nag));
+        if !self.options.nags {
+            return;
+        }
+
+        if self.options.symbolic_nags && self.move_suffix_free {
+            if let Some(suffix) = nag_suffix(nag) {
+                // Attach to the move just written
+                self.extend_token(suffix);
+                self.move_suffix_free = false;
+                return;
+            }
+        }
+
+        if self.options.nag_glosses {
+            if let Some(gloss) = nag_gloss(nag) {
+                self.write_comment(gloss);
+                return;
+            }
+        }
+
+        self.write_token(format!("${}", nag));
+    }
+
+    fn visit_eval(&mut self, eval: Eval) {
+        if !self.options.commands {
+            return;
+        }
+
+        let eval = command::format_eval(eval, self.options.eval_decimals);
+        self.pending_command_vec.push(format!("[%eval {}]", eval));
+    }
+
+    fn visit_eval_info(&mut self, info: &EvalInfo) {
+        if !self.options.commands {
+            return;
+        }
+
+        let eval = command::format_eval(info.eval, self.options.eval_decimals);
+        let command = match info.depth {
+            Some(depth) => format!("[%eval {},{}]", eval, depth),
+            None => format!("[%eval {}]", eval),
+        };
+        self.pending_command_vec.push(command);
+    }
+
+    fn visit_clock(&mut self, clock: Duration) {
+        if !self.options.commands {
+            return;
+        }
+
+        let clock = command::format_clock(clock, self.options.clock_decimals);
+        self.pending_command_vec.push(format!("[%clk {}]", clock));
+    }
+
+    fn visit_emt(&mut self, emt: Duration) {
+        if !self.options.commands {
+            return;
+        }
+
+        let emt = command::format_clock(emt, self.options.clock_decimals);
+        self.pending_command_vec.push(format!("[%emt {}]", emt));
+    }
+
+    fn visit_shapes(&mut self, shapes: &[Shape]) {
+        if !self.options.commands {
+            return;
+        }
+
+        let (circle_vec, arrow_vec): (Vec<&Shape>, Vec<&Shape>) =
+            shapes.iter().partition(|shape| shape.is_circle());
+        if !circle_vec.is_empty() {
+            let circles = command::format_shapes(circle_vec.into_iter());
+            self.pending_command_vec.push(format!("[%csl {}]", circles));
+        }
+        if !arrow_vec.is_empty() {
+            let arrows = command::format_shapes(arrow_vec.into_iter());
+            self.pending_command_vec.push(format!("[%cal {}]", arrows));
+        }
+    }
+
+    fn visit_meta(&mut self, key: &str, value: &str) {
+        if !self.options.commands || !self.options.meta_commands.iter().any(|k| k == key) {
+            return;
+        }
+
+        self.pending_command_vec
+            .push(format!("[%{} {}]", key, value));
     }
 
     fn begin_variation(&mut self) -> Skip {
+        self.flush_commands();
+
+        if !self.options.variations {
+            return Skip(true);
+        }
+
         self.force_move_number = true;
-        self.write_token("( ");
+        self.variation_depth += 1;
+        if let VariationLayout::Indented(_) = self.options.variation_layout {
+            self.line_break_pending = true;
+        }
+        self.write_token("(");
 
         Skip(false)
     }
 
     fn end_variation(&mut self) {
+        self.flush_commands();
         self.force_move_number = true;
-        self.write_token(") ");
+        // Closing parentheses of nested variations share a line
+        self.line_break_pending = false;
+        self.write_token(")");
+        self.variation_depth -= 1;
+        if let VariationLayout::Indented(_) = self.options.variation_layout {
+            self.line_break_pending = true;
+        }
     }
 
     fn visit_result(&mut self, result: &str) {
-        self.write_token(format!("{} ", result));
+        self.flush_commands();
+
+        if !self.options.result {
+            return;
+        }
+
+        match (result, self.inferred_result) {
+            ("*", Some(inferred_result)) => self.write_token(inferred_result.to_string()),
+            _ => self.write_token(result),
+        }
     }
 
     fn end_game(&mut self) -> Self::Result {
         self.flush(); // Or write a new line?
-        std::mem::take(&mut self.line_vec)
+
+        if let Some(err) = self.error.take() {
+            return Err(err);
+        }
+        self.sink.flush()
     }
 }
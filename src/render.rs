@@ -0,0 +1,237 @@
+//! Board diagrams of positions as SVG.
+
+use crate::game::{unicode_symbol, Node};
+use crate::{Move, Position, Square};
+
+use std::fmt::Write;
+
+pub use crate::game::ShapeColor;
+
+impl ShapeColor {
+    fn rgb(self) -> &'static str {
+        match self {
+            ShapeColor::Green => "#15781b",
+            ShapeColor::Red => "#882020",
+            ShapeColor::Yellow => "#e68f00",
+            ShapeColor::Blue => "#003088",
+        }
+    }
+}
+
+/// An arrow between two squares.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Arrow {
+    pub from: Square,
+    pub to: Square,
+    pub color: ShapeColor,
+}
+
+/// Options controlling how a board is drawn.
+#[derive(Debug, Clone)]
+pub struct SvgOptions {
+    /// Width and height of the board in pixels
+    pub size: u32,
+    /// Draw the board from Black's side
+    pub flipped: bool,
+    /// Draw file and rank labels inside the edge squares
+    pub coordinates: bool,
+    /// Highlight the squares of this move
+    pub last_move: Option<Move>,
+    pub arrows: Vec<Arrow>,
+}
+
+impl Default for SvgOptions {
+    fn default() -> Self {
+        Self {
+            size: 360,
+            flipped: false,
+            coordinates: true,
+            last_move: None,
+            arrows: Vec::new(),
+        }
+    }
+}
+
+const LIGHT_SQUARE: &str = "#f0d9b5";
+const DARK_SQUARE: &str = "#b58863";
+const LAST_MOVE: &str = "#cdd26a";
+
+/// Returns the top-left corner of a square, in square units.
+fn square_origin(square: Square, flipped: bool) -> (u32, u32) {
+    let (file, rank) = (u32::from(square.file()), u32::from(square.rank()));
+    if flipped {
+        (7 - file, rank)
+    } else {
+        (file, 7 - rank)
+    }
+}
+
+/// Returns the position as an SVG board diagram.
+///
+/// # Arguments
+///
+/// * `position` - the position to draw
+/// * `options` - size, orientation, highlights and arrows
+///
+/// # Examples
+///
+/// ```
+/// use sacrifice::render::{svg, SvgOptions};
+///
+/// let svg = svg(&sacrifice::Chess::default(), &SvgOptions::default());
+/// assert!(svg.starts_with("<svg"));
+/// assert_eq!(svg.matches("<text").count(), 32 + 16); // pieces and coordinates
+/// ```
+pub fn svg(position: &impl Position, options: &SvgOptions) -> String {
+    let square_size = options.size as f64 / 8.0;
+    let mut ret = String::new();
+
+    // Writing to a String never fails
+    let _ = write!(
+        ret,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{0}" height="{0}" viewBox="0 0 {0} {0}">"#,
+        options.size
+    );
+
+    let highlight_vec = options.last_move.as_ref().map_or_else(Vec::new, |m| {
+        [m.from(), Some(m.to())].into_iter().flatten().collect()
+    });
+
+    for square in Square::ALL {
+        let (x, y) = square_origin(square, options.flipped);
+        let fill = if highlight_vec.contains(&square) {
+            LAST_MOVE
+        } else if square.is_light() {
+            LIGHT_SQUARE
+        } else {
+            DARK_SQUARE
+        };
+        let _ = write!(
+            ret,
+            r#"<rect x="{}" y="{}" width="{2}" height="{2}" fill="{3}"/>"#,
+            x as f64 * square_size,
+            y as f64 * square_size,
+            square_size,
+            fill
+        );
+    }
+
+    if options.coordinates {
+        let font_size = square_size * 0.2;
+        for i in 0..8u32 {
+            let (file, rank) = if options.flipped {
+                (7 - i, i)
+            } else {
+                (i, 7 - i)
+            };
+            let _ = write!(
+                ret,
+                r#"<text x="{}" y="{}" font-size="{}" fill="{}">{}</text>"#,
+                (i as f64 + 0.82) * square_size,
+                options.size as f64 - font_size * 0.4,
+                font_size,
+                if i % 2 == 0 {
+                    LIGHT_SQUARE
+                } else {
+                    DARK_SQUARE
+                },
+                (b'a' + file as u8) as char
+            );
+            let _ = write!(
+                ret,
+                r#"<text x="{}" y="{}" font-size="{}" fill="{}">{}</text>"#,
+                font_size * 0.3,
+                (i as f64) * square_size + font_size * 1.1,
+                font_size,
+                if i % 2 == 0 {
+                    DARK_SQUARE
+                } else {
+                    LIGHT_SQUARE
+                },
+                rank + 1
+            );
+        }
+    }
+
+    for (square, piece) in position.board().clone() {
+        let (x, y) = square_origin(square, options.flipped);
+        let _ = write!(
+            ret,
+            r#"<text x="{}" y="{}" font-size="{}" text-anchor="middle" dominant-baseline="central">{}</text>"#,
+            (x as f64 + 0.5) * square_size,
+            (y as f64 + 0.5) * square_size,
+            square_size * 0.8,
+            unicode_symbol(piece)
+        );
+    }
+
+    for arrow in &options.arrows {
+        let center = |square: Square| {
+            let (x, y) = square_origin(square, options.flipped);
+            (
+                (x as f64 + 0.5) * square_size,
+                (y as f64 + 0.5) * square_size,
+            )
+        };
+        let ((x1, y1), (x2, y2)) = (center(arrow.from), center(arrow.to));
+
+        // Stop the shaft short of the target center, where the head begins
+        let (dx, dy) = (x2 - x1, y2 - y1);
+        let len = (dx * dx + dy * dy).sqrt().max(1.0);
+        let head = square_size * 0.4;
+        let (ux, uy) = (dx / len, dy / len);
+        let (bx, by) = (x2 - ux * head, y2 - uy * head);
+        let (px, py) = (-uy * head * 0.5, ux * head * 0.5);
+
+        let _ = write!(
+            ret,
+            r#"<line x1="{}" y1="{}" x2="{}" y2="{}" stroke="{}" stroke-width="{}" stroke-linecap="round" opacity="0.8"/>"#,
+            x1,
+            y1,
+            bx,
+            by,
+            arrow.color.rgb(),
+            square_size * 0.15
+        );
+        let _ = write!(
+            ret,
+            r#"<polygon points="{},{} {},{} {},{}" fill="{}" opacity="0.8"/>"#,
+            x2,
+            y2,
+            bx + px,
+            by + py,
+            bx - px,
+            by - py,
+            arrow.color.rgb()
+        );
+    }
+
+    ret.push_str("</svg>");
+    ret
+}
+
+impl Node {
+    /// Returns the node's position as an SVG board diagram, highlighting
+    /// the move that reached it.
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - size, orientation and arrows; `last_move` is ignored
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sacrifice::render::SvgOptions;
+    ///
+    /// let game = sacrifice::read_pgn("1. e4").unwrap();
+    /// let svg = game.root().mainline().unwrap().to_svg(&SvgOptions::default());
+    /// assert_eq!(svg.matches("#cdd26a").count(), 2); // e2 and e4
+    /// ```
+    pub fn to_svg(&self, options: &SvgOptions) -> String {
+        let options = SvgOptions {
+            last_move: self.prev_move(),
+            ..options.clone()
+        };
+        svg(&self.position(), &options)
+    }
+}
@@ -39,8 +39,2476 @@ Kg1 g3 46. Kh1 g2+ 47. Kg1 h2+ 48. Kxh2 Kf2 49. Kh3 g1=Q 50. Kh4 Kf3 51. Kh5 Kf4
 
 #[test]
 fn pgn() {
-    let game = crate::read_pgn(GAME_0);
+    let game = crate::read_pgn(GAME_0).unwrap();
     println!("----Begin PGN----");
     println!("{:64}", game);
     println!("----End PGN----");
 }
+
+#[test]
+fn validate() {
+    let (_, diagnostics) = crate::validate_pgn(GAME_0).unwrap();
+    assert!(diagnostics.is_empty());
+
+    let (game, diagnostics) =
+        crate::validate_pgn("[Result \"2-1-0\"]\n[FEN \"bad\"]\n\n1. e4 e5 2. Ke3 *").unwrap();
+    assert_eq!(diagnostics.len(), 3);
+    assert_eq!(diagnostics[0].severity, crate::Severity::Warning);
+    assert_eq!(diagnostics[1].severity, crate::Severity::Error);
+    assert_eq!(diagnostics[2].ply, 3);
+    assert_eq!(game.root().mainline().unwrap().depth(), 1);
+}
+
+#[test]
+fn decoding() {
+    use crate::{ReaderOptions, TextDecoding};
+
+    let pgn = b"[Event \"Caf\xe9\"]\n\n1. e4 { \xab Ouch \xbb } *";
+
+    let options = ReaderOptions {
+        decoding: TextDecoding::Strict,
+        ..ReaderOptions::default()
+    };
+    let (game, diagnostics) = crate::read_pgn_with(pgn, &options).unwrap();
+    assert_eq!(diagnostics.len(), 2);
+    assert!(game.header.event.is_none());
+
+    let options = ReaderOptions {
+        decoding: TextDecoding::Lossy,
+        ..ReaderOptions::default()
+    };
+    let (game, _) = crate::read_pgn_with(pgn, &options).unwrap();
+    assert_eq!(game.header.event, Some("Caf\u{fffd}".to_string()));
+
+    let (game, _) = crate::read_pgn_with(pgn, &ReaderOptions::default()).unwrap();
+    assert_eq!(game.header.event, Some("Caf\u{e9}".to_string()));
+    let node = game.root().mainline().unwrap();
+    assert_eq!(node.comment(), Some("\u{ab} Ouch \u{bb}".to_string()));
+}
+
+#[test]
+fn comments_round_trip() {
+    let game = crate::read_pgn("{a} {b} 1. e4 ({c} {d} 1. d4 {e} {f}) 1... e5 *").unwrap();
+    let pgn = game.to_string();
+    assert!(pgn.contains("{ a } { b } 1. e4 ( { c } { d } 1. d4 { e } { f } ) 1... e5"));
+
+    let d4_node = game.root().other_variations()[0].clone();
+    assert_eq!(d4_node.comments().len(), 4);
+    assert_eq!(d4_node.starting_comment(), Some("c d".to_string()));
+    assert_eq!(d4_node.comment(), Some("e f".to_string()));
+}
+
+#[test]
+fn limits() {
+    use crate::{ReaderLimits, ReaderOptions};
+
+    let read = |pgn: &str, limits: ReaderLimits| {
+        let options = ReaderOptions {
+            limits,
+            ..ReaderOptions::default()
+        };
+        crate::read_pgn_with(pgn, &options)
+    };
+
+    let nested = format!("1. e4 {}e5{} *", "( 1. d4 ".repeat(64), " )".repeat(64));
+    let limits = ReaderLimits {
+        max_variation_depth: Some(8),
+        ..ReaderLimits::default()
+    };
+    let err = read(&nested, limits.clone()).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    assert!(read("1. e4 (1. d4 (1. c4)) e5 *", limits).is_ok());
+
+    let limits = ReaderLimits {
+        max_plies: Some(200),
+        max_headers: Some(18),
+        max_comment_len: Some(80),
+        ..ReaderLimits::default()
+    };
+    assert!(read(GAME_0, limits.clone()).is_ok());
+    assert!(read(&"1. Nf3 Nf6 2. Ng1 Ng8 ".repeat(60), limits.clone()).is_err());
+    assert!(read(&format!("{{ {} }} *", "x".repeat(100)), limits).is_err());
+
+    for empty in ["", "  \n"] {
+        let err = crate::read_pgn(empty).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        assert!(crate::validate_pgn(empty).is_err());
+    }
+}
+
+#[cfg(feature = "tokio")]
+#[tokio::test]
+async fn async_reader() {
+    use futures_util::StreamExt;
+
+    let pgn = format!(
+        "{}\n[Event \"Second\"]\n\n{{ [not a tag]\n[still not a tag] }} 1. d4 *\n\n1. c4 *\n",
+        GAME_0
+    );
+    let reader = crate::AsyncPgnReader::new(pgn.as_bytes());
+    let game_vec = reader
+        .into_stream()
+        .collect::<Vec<_>>()
+        .await
+        .into_iter()
+        .collect::<std::io::Result<Vec<_>>>()
+        .unwrap();
+
+    assert_eq!(game_vec.len(), 3);
+    assert_eq!(game_vec[0].header.white, Some("maia1".to_string()));
+    assert_eq!(game_vec[1].header.event, Some("Second".to_string()));
+    assert!(game_vec[1]
+        .root()
+        .comment()
+        .unwrap()
+        .contains("[still not a tag]"));
+    assert!(game_vec[2].root().mainline().is_some());
+}
+
+#[cfg(feature = "tokio")]
+#[tokio::test]
+async fn async_reader_movetext_only() {
+    use crate::{AsyncPgnReader, ReaderLimits, ReaderOptions};
+
+    // Games without tags are split at their termination markers, so only
+    // one game at a time is buffered
+    let options = ReaderOptions {
+        limits: ReaderLimits {
+            max_game_bytes: Some(64),
+            ..ReaderLimits::default()
+        },
+        ..ReaderOptions::default()
+    };
+    let pgn = "1. e4 { 1-0 * } e5\n1-0\n1. d4 d5 0-1\n1. c4 *\n".repeat(50);
+    let mut reader = AsyncPgnReader::with_options(pgn.as_bytes(), options.clone());
+    let mut count = 0;
+    while let Some((game, _)) = reader.read_game().await.unwrap() {
+        assert_eq!(game.root().mainline_iter().count(), [2, 2, 1][count % 3]);
+        count += 1;
+    }
+    assert_eq!(count, 150);
+
+    let pgn = format!("1. e4 {{ {} }} *\n", "x".repeat(100));
+    let mut reader = AsyncPgnReader::with_options(pgn.as_bytes(), options);
+    let err = reader.read_game().await.unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn illegal_move_location() {
+    let pgn = "[Event \"Nf6 opening\"]\n\n1. e4 e5 2. Nf3 Nf6 3. Bc4 Nf6 4. Ke2 *";
+    let (_, diagnostics) = crate::validate_pgn(pgn).unwrap();
+    assert_eq!(diagnostics.len(), 1); // 4. Ke2 is discarded
+
+    let illegal_move = diagnostics[0].illegal_move.clone().unwrap();
+    assert_eq!(diagnostics[0].ply, 6);
+    assert_eq!(illegal_move.move_number, 3);
+    assert_eq!(illegal_move.turn, crate::Color::Black);
+    assert_eq!(illegal_move.san, "Nf6");
+    assert_eq!(illegal_move.offset, pgn.rfind("Nf6"));
+
+    // Moves in comments and skipped variations are not the move, and
+    // castling may be written with zeros
+    for (pgn, token) in [
+        ("1. e4 e5 { 2. Ke3 loses } 2. Ke3 *", "Ke3"),
+        ("( 1. e4 Ke2 ) 1. e4 Ke2 *", "Ke2"),
+        ("1. e4 e5 2. Ke2 Ke7 3. 0-0 *", "0-0"),
+    ] {
+        let (_, diagnostics) = crate::validate_pgn(pgn).unwrap();
+        let illegal_move = diagnostics.last().unwrap().illegal_move.clone().unwrap();
+        assert_eq!(illegal_move.offset, pgn.rfind(token), "{}", pgn);
+    }
+}
+
+#[test]
+fn illegal_move_policy() {
+    use crate::{IllegalMovePolicy, ReaderOptions};
+
+    let pgn = "1. e4 e5 2. Nf3 Qh5 Nc6 (2... d6 {ok}) 3. Bb5 (3. Bc4 Bc5) {x} 3... a6 *";
+    let count = |node: crate::game::Node| {
+        let mut count = 0;
+        let mut node = node;
+        while let Some(next) = node.mainline() {
+            count += 1;
+            node = next;
+        }
+        count
+    };
+
+    let (game, _) = crate::read_pgn_with(pgn, &ReaderOptions::default()).unwrap();
+    assert_eq!(count(game.root()), 3);
+    let nf3_node = game
+        .root()
+        .mainline()
+        .unwrap()
+        .mainline()
+        .unwrap()
+        .mainline()
+        .unwrap();
+    assert!(nf3_node.variation_vec().is_empty());
+    assert!(nf3_node.comment().is_none());
+
+    let options = ReaderOptions {
+        illegal_move_policy: IllegalMovePolicy::Skip,
+        ..ReaderOptions::default()
+    };
+    let (game, _) = crate::read_pgn_with(pgn, &options).unwrap();
+    assert_eq!(count(game.root()), 6);
+}
+
+#[test]
+fn skipped_variations() {
+    // A variation inside a discarded line is skipped with it
+    let (game, diagnostics) =
+        crate::validate_pgn("1. e4 (1. d4 d5 2. Ke3 (2. c4) 2... Nf6) 1... e5 2. Nf3 *").unwrap();
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(game.movetext(), "1. e4 ( 1. d4 d5 ) 1... e5 2. Nf3");
+
+    // A variation before any move has nothing to branch from
+    let (game, diagnostics) =
+        crate::validate_pgn("(1. d4 d5) 1. e4 e5 (1... c5) 2. Nf3 *").unwrap();
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(game.movetext(), "1. e4 e5 ( 1... c5 ) 2. Nf3");
+}
+
+#[test]
+fn uci_position() {
+    use crate::game::{Game, UciPositionError};
+
+    let game = Game::from_uci_position(
+        "position fen r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1 moves e1g1 e8c8",
+    )
+    .unwrap();
+    let pgn = game.to_string();
+    assert!(pgn.contains("[FEN \"r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1\"]"));
+    assert!(pgn.contains("1. O-O O-O-O"));
+
+    // Castling is exported as the king's two-square move, not king-takes-rook
+    let node = game.node_at(&["e1g1", "O-O-O"]).unwrap();
+    assert_eq!(game.node_at(&["O-O", "e8c8"]), Some(node.clone()));
+    assert_eq!(
+        node.uci_position_command(),
+        "position fen r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1 moves e1g1 e8c8"
+    );
+    assert_eq!(node.uci().unwrap().to_string(), "e8c8");
+
+    // Chess960 castling is king takes rook
+    let position: crate::Chess = "rk5r/8/8/8/8/8/8/RK5R w KQkq - 0 1"
+        .parse::<crate::Fen>()
+        .unwrap()
+        .into_position(crate::CastlingMode::Chess960)
+        .unwrap();
+    let mut root = crate::game::Node::from_position(position.into());
+    let castle = crate::Move::Castle {
+        king: crate::Square::B1,
+        rook: crate::Square::H1,
+    };
+    let castle_node = root.new_variation(castle).unwrap();
+    assert_eq!(castle_node.uci().unwrap().to_string(), "b1h1");
+    assert_eq!(
+        castle_node.uci_position_command(),
+        "position fen rk5r/8/8/8/8/8/8/RK5R w KQkq - 0 1 moves b1h1"
+    );
+
+    assert!(Game::from_uci_position("startpos").is_ok());
+    assert_eq!(
+        Game::from_uci_position("position startpos moves e2e4 e2e4").unwrap_err(),
+        UciPositionError::IllegalMove {
+            index: 1,
+            uci: "e2e4".to_string()
+        }
+    );
+    assert_eq!(
+        Game::from_uci_position("position moves e2e4").unwrap_err(),
+        UciPositionError::MissingPosition
+    );
+}
+
+#[test]
+fn epd() {
+    let epd = crate::epd::read_epd(
+        "r1bqkb1r/pppp1ppp/2n2n2/4p2Q/2B1P3/8/PPPP1PPP/RNB1K1NR w KQkq - \
+         bm Qxf7#; am Qxe5+ Nf3; ce +32000; dm 1; pv Qxf7#; c0 \"Scholar's \\\"mate\\\"\"; fmvn 4",
+    )
+    .unwrap();
+    assert_eq!(epd.best_moves.len(), 1);
+    assert_eq!(epd.avoid_moves.len(), 2);
+    assert_eq!(epd.centipawns, Some(32000));
+    assert_eq!(epd.direct_mate, Some(1));
+    assert_eq!(epd.predicted_variation.len(), 1);
+    assert_eq!(epd.operands("c0").unwrap()[0], "Scholar's \"mate\"");
+    assert_eq!(crate::Position::fullmoves(&epd.position()).get(), 4);
+
+    assert!(crate::epd::read_epd("8/8/8/8 w").is_err());
+    assert!(crate::epd::read_epd("8/8/8/8/8/8/8/8 w - - bm Nf3;").is_err());
+}
+
+#[test]
+fn writer_options() {
+    use crate::{HeaderSelection, LineEnding, MoveNumberStyle, PgnWriterOptions};
+
+    let game = crate::read_pgn(GAME_0).unwrap();
+
+    let options = PgnWriterOptions {
+        headers: HeaderSelection::Only(vec!["White".to_string(), "ECO".to_string()]),
+        move_numbers: MoveNumberStyle::Always,
+        nags: false,
+        line_ending: LineEnding::CrLf,
+        ..PgnWriterOptions::default()
+    };
+    let pgn = game.to_pgn_with(&options);
+    assert!(pgn.contains("[White \"maia1\"]\r\n"));
+    assert!(pgn.contains("[ECO \"D00\"]\r\n"));
+    assert_eq!(pgn.lines().filter(|l| l.starts_with('[')).count(), 2);
+    assert!(pgn.contains("1. d4 { The best opening move } ( 1. e4"));
+    assert!(pgn.contains("4. dxc5 4... e5"));
+    assert!(!pgn.contains('$'));
+
+    let options = PgnWriterOptions {
+        headers: HeaderSelection::SevenTagRoster,
+        move_numbers: MoveNumberStyle::Never,
+        variations: false,
+        ..PgnWriterOptions::default()
+    };
+    let pgn = game.to_pgn_with(&options);
+    assert_eq!(pgn.lines().filter(|l| l.starts_with('[')).count(), 7);
+    assert!(pgn.contains("d4 { The best opening move } d5 Bf4"));
+    assert!(!pgn.contains('('));
+}
+
+#[test]
+fn clock_and_eval_commands() {
+    use crate::game::Eval;
+    use crate::PgnWriterOptions;
+    use std::time::Duration;
+
+    let game = crate::read_pgn(
+        "1. e4 { [%eval 0.17] [%clk 0:10:00] } 1... e5 { Solid [%clk 0:09:57.4] [%csl Gd4] } *",
+    )
+    .unwrap();
+    let node_1 = game.root().mainline().unwrap();
+    let node_2 = node_1.mainline().unwrap();
+    assert_eq!(node_1.eval(), Some(Eval::Centipawns(17)));
+    assert_eq!(node_2.clock(), Some(Duration::from_millis(597_400)));
+    assert_eq!(node_2.comment().as_deref(), Some("Solid"));
+    assert_eq!(node_2.shapes().len(), 1);
+
+    let pgn = game.to_string();
+    assert!(pgn.contains(
+        "1. e4 { [%eval 0.17] [%clk 0:10:00] } 1... e5 { [%clk 0:09:57] [%csl Gd4] Solid } *"
+    ));
+
+    let options = PgnWriterOptions {
+        clock_decimals: 1,
+        eval_decimals: 1,
+        comments: false,
+        ..PgnWriterOptions::default()
+    };
+    let pgn = game.to_pgn_with(&options);
+    assert!(pgn.contains(
+        "1. e4 { [%eval 0.2] [%clk 0:10:00.0] } 1... e5 { [%clk 0:09:57.4] [%csl Gd4] } *"
+    ));
+
+    let round_trip = crate::read_pgn(&pgn).unwrap();
+    let node_2 = round_trip.root().mainline().unwrap().mainline().unwrap();
+    assert_eq!(node_2.clock(), Some(Duration::from_millis(597_400)));
+    assert!(node_2.comment().is_none());
+
+    // Seconds rounded up to a whole minute carry into the minutes
+    let mut node = game.root().mainline().unwrap();
+    node.set_clock(Some(Duration::from_millis(59_960)));
+    let pgn = game.to_pgn_with(&options);
+    assert!(pgn.contains("[%clk 0:01:00.0]"));
+    let round_trip = crate::read_pgn(&pgn).unwrap();
+    let node = round_trip.root().mainline().unwrap();
+    assert_eq!(node.clock(), Some(Duration::from_secs(60)));
+}
+
+#[test]
+fn mainline_only() {
+    let game = crate::read_pgn(GAME_0).unwrap();
+    let pgn = game.mainline_pgn();
+    assert!(pgn.contains("[Event \"Casual Rapid game\"]"));
+    assert!(pgn.contains("\n\n1. d4 d5 2. Bf4 c5 3. e3"));
+    assert!(pgn.contains("8. Nf3 Bg4 9. h3 Bxf3"));
+    assert!(!pgn.contains(['{', '(', '$']));
+    assert!(pgn.ends_with("54. Kh6 Qg6# 0-1\n"));
+}
+
+#[test]
+fn subtree_to_game() {
+    let game = crate::read_pgn(GAME_0).unwrap();
+    let mut node = game.root();
+    for _ in 0..15 {
+        node = node.mainline().unwrap(); // 8. Nf3
+    }
+    let subtree = node.to_game();
+    assert_eq!(subtree.opt_headers.get("SetUp").unwrap(), "1");
+    assert_eq!(subtree.initial_position(), node.position());
+
+    let pgn = subtree.to_string();
+    assert!(pgn.contains("\n\n8... Bg4 $2 ( { Apparently this is best } 8... Nxe5"));
+
+    let round_trip = crate::read_pgn(&pgn).unwrap();
+    assert_eq!(round_trip.initial_position(), node.position());
+    assert_eq!(round_trip.to_string(), pgn);
+}
+
+#[test]
+fn node_handles_share_the_tree() {
+    let game = crate::read_pgn("1. e4 e5 2. Nf3 *").unwrap();
+    let node = game.root().mainline().unwrap().mainline().unwrap();
+    assert!(node.parent().is_some());
+
+    // Any handle keeps the whole tree alive
+    drop(game);
+    assert_eq!(
+        node.root().mainline().unwrap().mainline(),
+        Some(node.clone())
+    );
+    assert_eq!(
+        node.mainline().unwrap().prev_move().unwrap().to(),
+        crate::Square::F3
+    );
+
+    // Removed nodes are detached but still usable
+    let mut nf3_node = node.mainline().unwrap();
+    assert_eq!(nf3_node.remove_node(), Some(nf3_node.clone()));
+    assert!(node.mainline().is_none());
+    assert_eq!(nf3_node.depth(), 3);
+
+    // Clones share the tree unless deep
+    let game = crate::read_pgn("1. e4 e5 *").unwrap();
+    let (shallow, deep) = (game.clone(), game.deep_clone());
+    game.root()
+        .mainline()
+        .unwrap()
+        .set_comment(Some("best by test".to_string()));
+    assert_eq!(shallow.movetext(), "1. e4 { best by test } 1... e5");
+    assert_eq!(deep.movetext(), "1. e4 e5");
+    assert_ne!(deep.root(), game.root());
+
+    // Ids survive deep clones, and are never reused
+    let e5_node = game.node_at(&["e4", "e5"]).unwrap();
+    assert_eq!(
+        deep.node_by_id(e5_node.id()).unwrap().moves(),
+        e5_node.moves()
+    );
+    e5_node.parent().unwrap().remove_node();
+    let d4_node = game.root().new_variation(crate::Move::Normal {
+        role: crate::Role::Pawn,
+        from: crate::Square::D2,
+        to: crate::Square::D4,
+        capture: None,
+        promotion: None,
+    });
+    assert!(d4_node.unwrap().id() > e5_node.id());
+    assert!(game.node_by_id(e5_node.id()).is_none());
+    assert!(game.node_by_id(u64::MAX).is_none());
+
+    // Nodes of another tree are never adopted
+    let other = crate::read_pgn("1. d4 *")
+        .unwrap()
+        .root()
+        .mainline()
+        .unwrap();
+    let mut root = node.root();
+    root.set_variation_vec(vec![other, node.parent().unwrap()]);
+    assert_eq!(root.variation_vec(), vec![node.parent().unwrap()]);
+}
+
+#[cfg(feature = "sync")]
+#[test]
+fn game_across_threads() {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<crate::game::Game>();
+    assert_send_sync::<crate::game::Node>();
+
+    let game = crate::read_pgn("1. e4 e5 *").unwrap();
+    let root = game.root();
+
+    let handle = std::thread::spawn(move || {
+        let mut node = game.root().mainline().unwrap().mainline().unwrap();
+        node.new_variation(crate::Move::Normal {
+            role: crate::Role::Knight,
+            from: crate::Square::G1,
+            to: crate::Square::F3,
+            capture: None,
+            promotion: None,
+        });
+        game
+    });
+    let game = handle.join().unwrap();
+    assert_eq!(game.movetext(), "1. e4 e5 2. Nf3");
+    assert_eq!(
+        root.mainline()
+            .unwrap()
+            .mainline()
+            .unwrap()
+            .mainline()
+            .unwrap()
+            .depth(),
+        3
+    );
+}
+
+#[test]
+fn detach_and_graft() {
+    let game =
+        crate::read_pgn("1. d4 d5 2. c4 (2. Nf3 $1 { solid } Nf6 (2... c5) 3. c4) 2... e6 *")
+            .unwrap();
+    let d5_node = game.root().mainline().unwrap().mainline().unwrap();
+    let mut nf3_node = d5_node.other_variations()[0].clone();
+    let branch = nf3_node.detach().unwrap();
+    assert_eq!(game.movetext(), "1. d4 d5 2. c4 e6");
+    assert_eq!(
+        branch.movetext(),
+        "2. Nf3 $1 { solid } 2... Nf6 ( 2... c5 ) 3. c4"
+    );
+
+    // White to move, but c4 is already taken
+    let mut e6_node = d5_node.mainline_iter().last().unwrap();
+    assert!(e6_node.graft(&branch).is_none());
+    // Black to move
+    assert!(d5_node.mainline().unwrap().graft(&branch).is_none());
+    assert_eq!(game.movetext(), "1. d4 d5 2. c4 e6");
+
+    let mut d5_node = d5_node;
+    let new_node_vec = d5_node.graft(&branch).unwrap();
+    assert_eq!(new_node_vec.len(), 1);
+    assert_eq!(new_node_vec[0].parent(), Some(d5_node.clone()));
+    assert_eq!(
+        game.movetext(),
+        "1. d4 d5 2. c4 ( 2. Nf3 $1 { solid } 2... Nf6 ( 2... c5 ) 3. c4 ) 2... e6"
+    );
+
+    // The first move is already there, so only the rest is added
+    let game = crate::read_pgn("1. e4 e5 2. Bc4 { classical } Nf6 *").unwrap();
+    let mut e5_node = game.root().mainline().unwrap().mainline().unwrap();
+    let branch = crate::read_pgn(
+        "[SetUp \"1\"]\n[FEN \"rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 2\"]\n\n\
+         2. Bc4 $1 { Italian } Nc6 *",
+    )
+    .unwrap();
+    let new_node_vec = e5_node.graft(&branch).unwrap();
+    assert_eq!(new_node_vec, vec![e5_node.mainline().unwrap()]);
+    assert_eq!(
+        game.movetext(),
+        "1. e4 e5 2. Bc4 $1 { classical } { Italian } 2... Nf6 ( 2... Nc6 )"
+    );
+    assert!(game.validate().is_empty());
+
+    // Castling with the king away from the e-file, in Chess960 mode
+    let fen = "rk5r/8/8/8/8/8/8/RK5R w KQkq - 0 1";
+    let position: crate::Chess = fen
+        .parse::<crate::Fen>()
+        .unwrap()
+        .into_position(crate::CastlingMode::Chess960)
+        .unwrap();
+    let mut root = crate::game::Node::from_position(position.into());
+    let branch = crate::read_pgn(&format!(
+        "[SetUp \"1\"]\n[FEN \"{}\"]\n\n1. O-O O-O-O *",
+        fen
+    ))
+    .unwrap();
+    let new_node_vec = root.graft(&branch).unwrap();
+    assert_eq!(new_node_vec[0].uci().unwrap().to_string(), "b1h1");
+    assert_eq!(
+        new_node_vec[0]
+            .mainline()
+            .unwrap()
+            .uci()
+            .unwrap()
+            .to_string(),
+        "b8a8"
+    );
+}
+
+#[test]
+fn merge_games() {
+    use crate::game::MergePolicy;
+
+    let pgn = "{ Open games } 1. e4 $1 { [%eval 0.3] best by test } e5 2. Nf3 *";
+    let other_pgn = "{ Open games } 1. e4 $3 { [%eval 0.2] popular } c5 (1... e5 2. Bc4) *";
+
+    let game = crate::read_pgn(pgn).unwrap();
+    assert!(game.merge(&crate::read_pgn(other_pgn).unwrap(), MergePolicy::Combine));
+    assert_eq!(
+        game.movetext(),
+        "{ Open games } 1. e4 $1 $3 { [%eval 0.30] best by test } { popular } \
+         1... e5 ( 1... c5 ) 2. Nf3 ( 2. Bc4 )"
+    );
+
+    let game = crate::read_pgn(pgn).unwrap();
+    game.merge(&crate::read_pgn(other_pgn).unwrap(), MergePolicy::KeepOurs);
+    assert!(game
+        .movetext()
+        .starts_with("{ Open games } 1. e4 $1 { [%eval 0.30] best by test } 1... e5"));
+
+    let game = crate::read_pgn(pgn).unwrap();
+    game.merge(
+        &crate::read_pgn(other_pgn).unwrap(),
+        MergePolicy::KeepTheirs,
+    );
+    assert!(game
+        .movetext()
+        .starts_with("{ Open games } 1. e4 $3 { [%eval 0.20] popular } 1... e5"));
+
+    let other =
+        crate::read_pgn("[SetUp \"1\"]\n[FEN \"4k3/8/8/8/8/8/8/4K3 w - - 0 1\"]\n\n*").unwrap();
+    assert!(!game.merge(&other, MergePolicy::Combine));
+}
+
+#[test]
+fn node_metadata() {
+    use crate::game::MergePolicy;
+    use crate::{PgnWriterOptions, ReaderOptions};
+
+    let pgn = "1. e4 { [%score 7] [%clk 0:05:00] good } 1... e5 { [%ui open] } *";
+    let options = ReaderOptions {
+        meta_commands: vec!["score".to_string()],
+        ..ReaderOptions::default()
+    };
+    let (game, _) = crate::read_pgn_with(pgn, &options).unwrap();
+    let e4_node = game.root().mainline().unwrap();
+    assert_eq!(e4_node.meta("score"), Some("7".to_string()));
+    assert_eq!(e4_node.comment(), Some("good".to_string()));
+    assert_eq!(
+        e4_node.mainline().unwrap().comment(),
+        Some("[%ui open]".to_string())
+    );
+
+    let write_options = PgnWriterOptions {
+        meta_commands: vec!["score".to_string()],
+        ..PgnWriterOptions::movetext_only()
+    };
+    assert_eq!(
+        game.to_pgn_with(&write_options),
+        "1. e4 { [%clk 0:05:00] [%score 7] good } 1... e5 { [%ui open] }\n"
+    );
+    assert_eq!(
+        game.movetext(),
+        "1. e4 { [%clk 0:05:00] good } 1... e5 { [%ui open] }"
+    );
+
+    // Kept by copies and merges
+    assert_eq!(
+        game.root()
+            .to_game()
+            .root()
+            .mainline()
+            .unwrap()
+            .meta("score"),
+        Some("7".to_string())
+    );
+    let other = crate::read_pgn("1. e4 e5 *").unwrap();
+    other
+        .root()
+        .mainline()
+        .unwrap()
+        .set_meta("score", Some("3".to_string()));
+    other
+        .root()
+        .mainline()
+        .unwrap()
+        .set_meta("seen", Some("1".to_string()));
+    game.merge(&other, MergePolicy::Combine);
+    assert_eq!(e4_node.meta("score"), Some("7".to_string()));
+    assert_eq!(e4_node.meta("seen"), Some("1".to_string()));
+    game.merge(&other, MergePolicy::KeepTheirs);
+    assert_eq!(e4_node.meta("score"), Some("3".to_string()));
+}
+
+#[test]
+fn shapes() {
+    use crate::game::{Shape, ShapeColor};
+    use crate::Square;
+
+    let pgn = "1. e4 { [%csl Ge4] good [%cal Re4d5,Yg1f3] [%csl Xa1] } 1... d5 { [%cal Gd7] } *";
+    let game = crate::read_pgn(pgn).unwrap();
+    let e4_node = game.root().mainline().unwrap();
+    assert_eq!(
+        e4_node.shapes(),
+        vec![
+            Shape::circle(ShapeColor::Green, Square::E4),
+            Shape::arrow(ShapeColor::Red, Square::E4, Square::D5),
+            Shape::arrow(ShapeColor::Yellow, Square::G1, Square::F3),
+        ]
+    );
+    // Malformed commands stay in the comment
+    assert_eq!(e4_node.comment(), Some("good [%csl Xa1]".to_string()));
+    assert!(e4_node.mainline().unwrap().shapes().is_empty());
+    assert_eq!(
+        game.movetext(),
+        "1. e4 { [%csl Ge4] [%cal Re4d5,Yg1f3] good [%csl Xa1] } 1... d5 { [%cal Gd7] }"
+    );
+}
+
+#[test]
+fn node_outcome() {
+    use crate::Outcome;
+
+    let from_fen = |fen: &str, movetext: &str| {
+        crate::read_pgn(&format!("[SetUp \"1\"]\n[FEN \"{}\"]\n\n{}", fen, movetext)).unwrap()
+    };
+
+    let game = from_fen("7k/8/8/5Q2/8/8/8/K7 w - - 0 1", "1. Qf7 (1. Qg5) *");
+    assert_eq!(
+        game.node_at(&["Qf7"]).unwrap().outcome(),
+        Some(Outcome::Draw)
+    ); // stalemate
+    assert_eq!(game.node_at(&["Qg5"]).unwrap().outcome(), None);
+
+    let game = from_fen("7k/8/8/8/8/5N2/3q4/K7 w - - 0 1", "1. Nxd2 *");
+    assert_eq!(
+        game.node_at(&["Nxd2"]).unwrap().outcome(),
+        Some(Outcome::Draw)
+    ); // bare knight
+}
+
+#[test]
+fn undo_redo() {
+    use crate::game::MergePolicy;
+
+    let game = crate::read_pgn("1. e4 e5 (1... c5) 2. Nf3 *").unwrap();
+    assert!(!game.can_undo());
+    game.root().mainline().unwrap().push_nag(1); // not recorded
+    game.set_history_enabled(true);
+
+    let mut e4_node = game.root().mainline().unwrap();
+    let sicilian_node = e4_node.other_variations()[0].clone();
+    e4_node.promote_variation(sicilian_node);
+    assert_eq!(game.movetext(), "1. e4 $1 c5 ( 1... e5 2. Nf3 )");
+
+    // A merge is undone in one step
+    game.merge(
+        &crate::read_pgn("1. d4 d5 2. c4 *").unwrap(),
+        MergePolicy::Combine,
+    );
+    assert!(game.undo());
+    assert_eq!(game.movetext(), "1. e4 $1 c5 ( 1... e5 2. Nf3 )");
+    assert!(game.can_redo());
+
+    // A new edit drops the undone ones
+    e4_node.clear_nags();
+    assert!(!game.can_redo());
+    assert!(!game.redo());
+    assert!(game.undo());
+    assert!(game.undo());
+    assert_eq!(game.movetext(), "1. e4 $1 e5 ( 1... c5 ) 2. Nf3");
+    assert!(!game.can_undo());
+
+    // Deep clones start without history
+    assert!(game.redo());
+    assert!(!game.deep_clone().can_redo());
+
+    game.set_history_enabled(false);
+    assert!(!game.can_redo());
+
+    // A promotion across several moves is one edit
+    let game = crate::read_pgn("1. d4 (1. e4 e5 (1... c5)) 1... d5 *").unwrap();
+    game.set_history_enabled(true);
+    assert!(game.node_at(&["e4", "c5"]).unwrap().promote_to_mainline());
+    assert_eq!(game.movetext(), "1. e4 ( 1. d4 d5 ) 1... c5 ( 1... e5 )");
+    assert!(game.undo());
+    assert_eq!(game.movetext(), "1. d4 ( 1. e4 e5 ( 1... c5 ) ) 1... d5");
+}
+
+#[test]
+fn repetition() {
+    // The repetitions in the variation do not count for the main line
+    let game = crate::read_pgn(
+        "1. e4 e5 2. Nf3 Nf6 3. Ng1 (3. Nc3 Nc6 4. Nb1 Nb8) 3... Ng8 4. Nf3 Nf6 5. Ng1 Ng8 *",
+    )
+    .unwrap();
+    let last_node = game.root().mainline_iter().last().unwrap();
+    assert_eq!(last_node.repetition_count(), 3);
+    let nb8_node = game
+        .node_at(&["e4", "e5", "Nf3", "Nf6", "Nc3", "Nc6", "Nb1", "Nb8"])
+        .unwrap();
+    assert_eq!(nb8_node.repetition_count(), 2);
+    assert!(!nb8_node.is_threefold_repetition());
+
+    // Castling rights differ after the king has moved
+    let game = crate::read_pgn("1. e4 e5 2. Ke2 Ke7 3. Ke1 Ke8 4. Ke2 Ke7 5. Ke1 Ke8 *").unwrap();
+    let node_vec: Vec<_> = game.root().mainline_iter().collect();
+    assert_eq!(node_vec[5].repetition_count(), 1); // 3... Ke8 vs. 1... e5
+    assert_eq!(node_vec[9].repetition_count(), 2); // 5... Ke8
+}
+
+#[test]
+fn fifty_move_rule() {
+    use crate::game::GameBuilder;
+
+    let fen = "6k1/5ppp/8/8/8/8/8/KR6 w - - 99 80";
+    let game = GameBuilder::new().fen(fen).moves(["Rb8#"]).build().unwrap();
+    let mate_node = game.root().mainline().unwrap();
+    assert_eq!(mate_node.halfmove_clock(), 100);
+    assert!(!mate_node.is_fifty_move_draw());
+
+    let game = GameBuilder::new()
+        .fen(fen)
+        .moves(["Rb2", "h6"])
+        .build()
+        .unwrap();
+    let node_vec: Vec<_> = game.root().mainline_iter().collect();
+    assert!(node_vec[0].is_fifty_move_draw());
+    assert_eq!(node_vec[1].halfmove_clock(), 0);
+    assert!(!node_vec[1].is_fifty_move_draw());
+}
+
+#[test]
+fn draw_reason() {
+    use crate::game::{DrawReason, GameBuilder};
+
+    let game = GameBuilder::new()
+        .fen("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1")
+        .build()
+        .unwrap();
+    assert_eq!(game.root().draw_reason(), Some(DrawReason::Stalemate));
+
+    let game = GameBuilder::new()
+        .fen("8/8/4k3/8/3b4/8/4K3/8 w - - 0 1")
+        .build()
+        .unwrap();
+    assert!(game.root().is_insufficient_material());
+    assert_eq!(
+        game.root().draw_reason(),
+        Some(DrawReason::InsufficientMaterial)
+    );
+
+    let game = GameBuilder::new()
+        .fen("8/8/4k3/8/3p4/8/4K3/8 w - - 0 1")
+        .build()
+        .unwrap();
+    assert!(!game.root().is_insufficient_material());
+
+    let fen = "6k1/5ppp/8/8/8/8/8/KR6 w - - 149 120";
+    let game = GameBuilder::new()
+        .fen(fen)
+        .moves(["Rb2", "Kh8"])
+        .build()
+        .unwrap();
+    let node_vec: Vec<_> = game.root().mainline_iter().collect();
+    assert_eq!(game.root().draw_reason(), Some(DrawReason::FiftyMoveRule));
+    assert_eq!(
+        node_vec[0].draw_reason(),
+        Some(DrawReason::SeventyFiveMoveRule)
+    );
+    assert!(!node_vec[0].draw_reason().unwrap().is_claimable());
+
+    let game = GameBuilder::new().fen(fen).moves(["Rb8#"]).build().unwrap();
+    assert_eq!(game.root().mainline().unwrap().draw_reason(), None);
+
+    let knight_moves = ["Nf3", "Nf6", "Ng1", "Ng8"].repeat(4);
+    let game = GameBuilder::new().moves(knight_moves).build().unwrap();
+    let node_vec: Vec<_> = game.root().mainline_iter().collect();
+    assert_eq!(
+        node_vec[7].draw_reason(),
+        Some(DrawReason::ThreefoldRepetition)
+    );
+    assert_eq!(
+        node_vec[15].draw_reason(),
+        Some(DrawReason::FivefoldRepetition)
+    );
+}
+
+#[test]
+fn zobrist() {
+    use crate::game::GameBuilder;
+
+    let game = crate::read_pgn(GAME_0).unwrap();
+    for node in game.root().descendants() {
+        assert_eq!(node.zobrist(), node.polyglot_key());
+    }
+
+    // 2. dxe6 is pseudo-legal, but leaves the king in check
+    let fen = "4k3/4p3/8/K2P3r/8/8/8/8 b - - 0 1";
+    let game = GameBuilder::new().fen(fen).moves(["e5"]).build().unwrap();
+    let e5_node = game.root().mainline().unwrap();
+    assert_ne!(e5_node.zobrist(), e5_node.polyglot_key());
+    let fen = "4k3/8/8/K2Pp2r/8/8/8/8 w - - 0 2";
+    let game = GameBuilder::new().fen(fen).build().unwrap();
+    assert_eq!(e5_node.zobrist(), game.root().zobrist());
+}
+
+#[test]
+fn move_predicates() {
+    use crate::game::{GameBuilder, Node};
+
+    let pgn = "1. e4 d5 2. exd5 e5 3. dxe6 Nc6 4. exf7+ Ke7 5. fxg8=Q Rxg8 6. Nf3 *";
+    let game = crate::read_pgn(pgn).unwrap();
+    let node_vec: Vec<_> = game.root().mainline_iter().collect();
+    let flags = |node: &Node| (node.is_capture(), node.gives_check(), node.is_promotion());
+    assert_eq!(flags(&game.root()), (false, false, false));
+    assert_eq!(flags(&node_vec[2]), (true, false, false)); // 2. exd5
+    assert_eq!(flags(&node_vec[4]), (true, false, false)); // 3. dxe6 e.p.
+    assert_eq!(flags(&node_vec[6]), (true, true, false)); // 4. exf7+
+    assert_eq!(flags(&node_vec[8]), (true, false, true)); // 5. fxg8=Q
+    assert_eq!(flags(&node_vec[10]), (false, false, false)); // 6. Nf3
+    assert!(node_vec[6].is_check());
+
+    let fen = "4k3/8/8/8/8/8/4r3/4K3 w - - 0 1";
+    let game = GameBuilder::new().fen(fen).build().unwrap();
+    assert!(game.root().is_check());
+    assert!(!game.root().gives_check());
+}
+
+#[test]
+fn variants() {
+    use crate::game::{Game, GameBuilder, GameOutcome, Termination};
+    use crate::{Color, Variant};
+
+    let pgn = "[Variant \"King of the Hill\"]\n\n1. e4 e5 2. Ke2 a6 3. Kd3 a5 4. Kc4 a4 5. Kd5 *";
+    let game = crate::read_pgn(pgn).unwrap();
+    assert_eq!(game.variant(), Variant::KingOfTheHill);
+    assert_eq!(game.root().mainline_iter().count(), 9);
+    assert_eq!(
+        game.outcome(),
+        GameOutcome::Decisive {
+            winner: Color::White,
+            reason: Termination::VariantWin
+        }
+    );
+    let round_trip = crate::read_pgn(&game.to_string()).unwrap();
+    assert_eq!(round_trip.variant(), Variant::KingOfTheHill);
+    assert!(round_trip.validate().is_empty());
+
+    let pgn =
+        "[Variant \"Three-check\"]\n\n1. e4 e5 2. Bc4 Nc6 3. Bxf7+ Kxf7 4. Qh5+ g6 5. Qxg6+ *";
+    let game = crate::read_pgn(pgn).unwrap();
+    let last_node = game.root().mainline_iter().last().unwrap();
+    assert_eq!(
+        last_node.outcome(),
+        Some(crate::Outcome::Decisive {
+            winner: Color::White
+        })
+    );
+
+    // A move that only exists in the variant
+    let game = crate::read_pgn("[Variant \"Racing Kings\"]\n\n1. Kh3 *").unwrap();
+    assert_eq!(game.movetext(), "1. Kh3");
+    let game = crate::read_pgn("1. Kh3 *").unwrap();
+    assert_eq!(game.movetext(), "");
+
+    // The subtree of a variant game keeps its variant
+    let game = crate::read_pgn("[Variant \"Horde\"]\n\n1. e5 d6 2. exd6 *").unwrap();
+    let subtree = game.root().mainline().unwrap().to_game();
+    assert_eq!(subtree.variant(), Variant::Horde);
+    assert!(subtree.validate().is_empty());
+
+    let game = GameBuilder::new()
+        .variant(Variant::Antichess)
+        .moves(["e3", "b5", "Bxb5"])
+        .build()
+        .unwrap();
+    assert_eq!(game.tag("Variant").as_deref(), Some("Antichess"));
+    assert!(GameBuilder::new()
+        .variant(Variant::Antichess)
+        .moves(["e3", "b5", "Nf3"])
+        .build()
+        .is_err());
+
+    let mut game = Game::new_variant(Variant::Atomic);
+    game.set_initial_position(crate::Chess::default());
+    assert_eq!(game.variant(), Variant::Chess);
+    assert_eq!(game.tag("Variant"), None);
+
+    let game = crate::read_pgn("[Variant \"Chess960\"]\n\n1. e4 *").unwrap();
+    assert_eq!(game.variant(), Variant::Chess);
+    let pgn = "[Variant \"Shogi\"]\n\n1. e4 *";
+    let (_, diagnostics) = crate::read_pgn_with(pgn, &Default::default()).unwrap();
+    assert_eq!(diagnostics.len(), 1);
+}
+
+#[test]
+fn crazyhouse() {
+    use crate::{Color, Role, Variant};
+
+    let pgn = "[Variant \"Crazyhouse\"]\n\n1. e4 d5 2. exd5 Qxd5 3. Nc3 Qa5 4. P@d4 Nf6 5. Bb5 c6 6. Bxc6 Nxc6 7. Nf3 B@g4 *";
+    let game = crate::read_pgn(pgn).unwrap();
+    assert_eq!(game.variant(), Variant::Crazyhouse);
+    let node_vec: Vec<_> = game.root().mainline_iter().collect();
+    assert_eq!(node_vec.len(), 14);
+    let pockets = node_vec[11].pockets().unwrap();
+    assert_eq!(*pockets.get(Color::White).get(Role::Pawn), 1);
+    assert_eq!(*pockets.get(Color::White).get(Role::Bishop), 0);
+    assert_eq!(*pockets.get(Color::Black).get(Role::Pawn), 1);
+    assert_eq!(*pockets.get(Color::Black).get(Role::Bishop), 1);
+    assert!(node_vec[12].legal_moves_san().contains(&"B@h3".to_string()));
+
+    let pgn_out = game.to_string();
+    assert!(pgn_out.contains("4. P@d4 Nf6"));
+    assert!(pgn_out.contains("7. Nf3 B@g4"));
+    let round_trip = crate::read_pgn(&pgn_out).unwrap();
+    assert_eq!(round_trip.root().mainline_iter().count(), 14);
+    assert_eq!(round_trip.to_string(), pgn_out);
+
+    // Pawn drops may also be written without the pawn
+    let pgn = "[Variant \"Crazyhouse\"]\n\n1. e4 d5 2. exd5 Qxd5 3. Nc3 Qa5 4. @d4 *";
+    let game = crate::read_pgn(pgn).unwrap();
+    assert_eq!(game.root().mainline_iter().count(), 7);
+    let fen = game.root().mainline_iter().last().unwrap().fen();
+    assert!(fen.starts_with("rnb1kbnr/ppp1pppp/8/q7/3P4/2N5/PPPP1PPP/R1BQKBNR"));
+}
+
+#[test]
+fn game_outcome() {
+    use crate::game::{DrawReason, GameOutcome, GameResult, Termination};
+    use crate::Color;
+
+    let game = crate::read_pgn(GAME_0).unwrap();
+    let outcome = game.outcome();
+    assert_eq!(
+        outcome,
+        GameOutcome::Decisive {
+            winner: Color::Black,
+            reason: Termination::Checkmate
+        }
+    );
+    assert_eq!(outcome.result(), GameResult::BlackWins);
+
+    // The board wins over a wrong Result tag
+    let pgn = "[Result \"1-0\"]\n\n1. f3 e5 2. g4 Qh4# 1-0";
+    let game = crate::read_pgn(pgn).unwrap();
+    assert_eq!(game.outcome().result(), GameResult::BlackWins);
+
+    let pgn = "[Result \"1/2-1/2\"]\n\n1. Nf3 Nf6 2. Ng1 Ng8 3. Nf3 Nf6 4. Ng1 Ng8 1/2-1/2";
+    let game = crate::read_pgn(pgn).unwrap();
+    let reason = Termination::Board(DrawReason::ThreefoldRepetition);
+    assert_eq!(game.outcome(), GameOutcome::Draw { reason });
+    assert_eq!(game.outcome().result(), GameResult::Draw);
+
+    // Unclaimed repetition
+    let game = crate::read_pgn("1. Nf3 Nf6 2. Ng1 Ng8 3. Nf3 Nf6 4. Ng1 Ng8 *").unwrap();
+    assert_eq!(game.outcome(), GameOutcome::Ongoing);
+
+    let pgn = "[Result \"0-1\"]\n[Termination \"Abandoned\"]\n\n1. e4 0-1";
+    let game = crate::read_pgn(pgn).unwrap();
+    let reason = Termination::Abandoned;
+    assert_eq!(
+        game.outcome(),
+        GameOutcome::Decisive {
+            winner: Color::Black,
+            reason
+        }
+    );
+
+    let pgn = "[Result \"1-0\"]\n[Termination \"unterminated\"]\n\n1. e4 1-0";
+    let game = crate::read_pgn(pgn).unwrap();
+    assert_eq!(game.outcome(), GameOutcome::Ongoing);
+}
+
+#[test]
+fn validate_tree() {
+    use crate::game::Violation;
+
+    let game = crate::read_pgn(GAME_0).unwrap();
+    assert!(game.validate().is_empty());
+    let node = game.root().mainline().unwrap().mainline().unwrap();
+    assert!(node.to_game().validate().is_empty());
+
+    // 2. Nf3 listed as a move from the start
+    let game = crate::read_pgn("1. e4 e5 2. Nf3 *").unwrap();
+    let nf3_node = game.node_at(&["e4", "e5", "Nf3"]).unwrap();
+    let mut root = game.root();
+    root.set_variation_vec(vec![nf3_node.clone()]);
+    assert_eq!(
+        game.validate(),
+        vec![
+            Violation::WrongParent {
+                node: nf3_node.clone(),
+                listed_by: game.root()
+            },
+            Violation::PositionMismatch(nf3_node),
+        ]
+    );
+
+    let mut game = crate::read_pgn("1. e4 *").unwrap();
+    game.opt_headers
+        .insert("FEN".to_string(), "8/8/8/8/8/8/8/K6k w - - 0 1".to_string());
+    assert_eq!(game.validate(), vec![Violation::InitialPositionMismatch]);
+}
+
+#[test]
+fn typed_headers() {
+    use crate::game::{Date, Round};
+
+    let game = crate::read_pgn(GAME_0).unwrap();
+    assert_eq!(game.header.white_elo, Some(1537));
+    assert!(!game.opt_headers.contains_key("WhiteElo"));
+    assert!(game.to_string().contains("[WhiteElo \"1537\"]"));
+
+    let game = crate::read_pgn(
+        "[Date \"2023.??.??\"]\n[Round \"-\"]\n[WhiteElo \"-\"]\n[BlackElo \"2850\"]\n\n*",
+    )
+    .unwrap();
+    assert_eq!(game.header.white_elo, None);
+    assert_eq!(
+        game.opt_headers.get("WhiteElo").map(String::as_str),
+        Some("-")
+    );
+    assert_eq!(game.header.black_elo, Some(2850));
+    assert_eq!(game.header.parsed_round(), None);
+    assert_eq!(game.header.round.as_deref(), Some("-"));
+    let pgn = game.to_string();
+    assert!(pgn.contains("[BlackElo \"2850\"]\n[WhiteElo \"-\"]"));
+
+    let mut date_vec: Vec<Date> = ["2023.??.??", "2022.12.31", "2023.01.05"]
+        .iter()
+        .map(|s| s.parse().unwrap())
+        .collect();
+    date_vec.sort();
+    assert_eq!(date_vec[0].to_string(), "2022.12.31");
+    assert_eq!(date_vec[1], game.header.parsed_date().unwrap());
+    assert!("2023.1.05".parse::<Date>().is_err());
+    assert_eq!(Round(vec![12, 3]).to_string(), "12.3");
+}
+
+#[test]
+fn game_result() {
+    use crate::game::GameResult;
+
+    let game = crate::read_pgn("[Result \"1/2-1/2\"]\n\n1. e4 e5 1/2-1/2").unwrap();
+    assert_eq!(game.header.result, GameResult::Draw);
+    assert!(game.to_string().contains("[Result \"1/2-1/2\"]"));
+    assert!(game.to_string().trim_end().ends_with("1. e4 e5 1/2-1/2"));
+
+    for result in [
+        GameResult::WhiteWins,
+        GameResult::BlackWins,
+        GameResult::Draw,
+        GameResult::Ongoing,
+    ] {
+        assert_eq!(GameResult::from(result.to_string().as_str()), result);
+    }
+    let (_, diagnostics) = crate::validate_pgn("[Result \"1/2-1/2\"]\n\n*").unwrap();
+    assert!(diagnostics.is_empty());
+
+    // Stalemate, and a bare king capturing the last piece
+    for (fen, san) in [
+        ("7k/8/4Q3/8/8/8/8/K7 w - - 0 1", "Qf7"),
+        ("7k/6Q1/8/8/8/8/8/K7 b - - 0 1", "Kxg7"),
+    ] {
+        let mut game = crate::game::GameBuilder::new()
+            .fen(fen)
+            .moves([san])
+            .build()
+            .unwrap();
+        assert_eq!(game.infer_result(), Some(GameResult::Draw));
+        assert_eq!(game.tag("Result").as_deref(), Some("1/2-1/2"));
+    }
+}
+
+#[test]
+fn tag_api() {
+    let mut game = crate::read_pgn(GAME_0).unwrap();
+    let pgn = game.to_string();
+    let tag_lines: Vec<String> = game
+        .tags()
+        .map(|(key, value)| format!("[{} \"{}\"]", key, value))
+        .collect();
+    for line in &tag_lines {
+        assert!(pgn.contains(line.as_str()), "{}", line);
+    }
+
+    assert_eq!(game.set_tag("WhiteElo", "?"), Some("1537".to_string()));
+    assert_eq!(game.header.white_elo, None);
+    assert_eq!(game.tag("WhiteElo").as_deref(), Some("?"));
+    assert_eq!(game.set_tag("WhiteElo", "1600").as_deref(), Some("?"));
+    assert!(!game.opt_headers.contains_key("WhiteElo"));
+
+    assert_eq!(game.set_tag("Result", "1-0").as_deref(), Some("0-1"));
+    assert_eq!(game.remove_tag("Result").as_deref(), Some("1-0"));
+    assert_eq!(game.tag("Result").as_deref(), Some("*"));
+    assert_eq!(game.remove_tag("WhiteElo").as_deref(), Some("1600"));
+    assert_eq!(game.remove_tag("NoSuchTag"), None);
+    assert!(game.tags().all(|(key, _)| key != "WhiteElo"));
+}
+
+#[test]
+fn game_builder() {
+    use crate::game::{BuildError, GameBuilder};
+
+    let fen = "4k3/8/8/8/8/8/4P3/4K3 w - - 0 1";
+    let game = GameBuilder::new()
+        .site("Online")
+        .round("3")
+        .tag("White", "Fischer")
+        .fen(fen)
+        .moves(vec!["e2e4".to_string(), "Kd7".to_string()])
+        .moves(["e5"])
+        .build()
+        .unwrap();
+    assert_eq!(game.header.white.as_deref(), Some("Fischer"));
+    assert_eq!(game.header.site.as_deref(), Some("Online"));
+    assert!(game.validate().is_empty());
+
+    let round_trip = crate::read_pgn(&game.to_string()).unwrap();
+    assert_eq!(round_trip.header.round.as_deref(), Some("3"));
+    assert_eq!(round_trip.initial_position(), game.initial_position());
+    assert_eq!(round_trip.movetext(), "1. e4 Kd7 2. e5");
+
+    assert_eq!(
+        GameBuilder::new()
+            .fen("8/8/8/8/8/8/8/8 w - - 0 1")
+            .build()
+            .unwrap_err(),
+        BuildError::InvalidFen("8/8/8/8/8/8/8/8 w - - 0 1".to_string())
+    );
+}
+
+#[test]
+fn set_initial_position() {
+    use crate::{CastlingMode, Chess, Fen};
+
+    let mut game = crate::read_pgn("1. e4 { open } d5 2. exd5 (2. Nc3) 2... Qxd5 *").unwrap();
+    game.set_history_enabled(true);
+    // Without the knight on b1, 2. Nc3 is illegal
+    let fen: Fen = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/R1BQKBNR w KQkq - 0 1"
+        .parse()
+        .unwrap();
+    let position: Chess = fen.into_position(CastlingMode::Standard).unwrap();
+    let removed = game.set_initial_position(position);
+    assert_eq!(
+        removed
+            .iter()
+            .map(|n| n.san().unwrap().to_string())
+            .collect::<Vec<_>>(),
+        vec!["Nc3"]
+    );
+    assert_eq!(game.movetext(), "1. e4 { open } 1... d5 2. exd5 Qxd5");
+    assert!(game.validate().is_empty());
+
+    assert!(game.undo());
+    assert_eq!(
+        game.movetext(),
+        "1. e4 { open } 1... d5 2. exd5 ( 2. Nc3 ) 2... Qxd5"
+    );
+
+    game.set_initial_position(Chess::default());
+    assert!(!game.opt_headers.contains_key("FEN"));
+    assert!(game.validate().is_empty());
+}
+
+#[test]
+fn tree_events() {
+    use crate::game::TreeEvent;
+    use std::sync::mpsc;
+
+    let game = crate::read_pgn("1. e4 e5 (1... c5) 2. Nf3 *").unwrap();
+    game.set_history_enabled(true);
+    let (sender, receiver) = mpsc::channel();
+    let id = game.subscribe(move |event: &TreeEvent| {
+        // Listeners can read the tree
+        let san = match event {
+            TreeEvent::NodeAdded(node) | TreeEvent::NodeRemoved(node) => node.san(),
+            _ => None,
+        };
+        sender
+            .send((event.clone(), san.map(|san| san.to_string())))
+            .unwrap();
+    });
+
+    let e4_node = game.root().mainline().unwrap();
+    let c5_node = e4_node.other_variations()[0].clone();
+    e4_node.clone().promote_variation(c5_node.clone());
+    e4_node.mainline().unwrap().clone().set_nags([1].into());
+    c5_node.clone().remove_node();
+    assert_eq!(
+        receiver.try_iter().collect::<Vec<_>>(),
+        vec![
+            (TreeEvent::NodePromoted(c5_node.clone()), None),
+            (TreeEvent::NagsChanged(c5_node.clone()), None),
+            (
+                TreeEvent::NodeRemoved(c5_node.clone()),
+                Some("c5".to_string())
+            ),
+        ]
+    );
+
+    assert!(game.undo());
+    assert_eq!(
+        receiver.try_iter().collect::<Vec<_>>(),
+        vec![(
+            TreeEvent::NodeAdded(c5_node.clone()),
+            Some("c5".to_string())
+        )]
+    );
+
+    let d5_node = e4_node.clone().play_san("d5").unwrap();
+    assert!(e4_node.clone().move_variation(2, 1));
+    assert_eq!(
+        receiver.try_iter().collect::<Vec<_>>(),
+        vec![
+            (TreeEvent::NodeAdded(d5_node), Some("d5".to_string())),
+            (TreeEvent::VariationsReordered(e4_node.clone()), None),
+        ]
+    );
+
+    assert!(game.unsubscribe(id));
+    assert!(!game.unsubscribe(id));
+    game.root().play_san("d4");
+    assert_eq!(receiver.try_iter().count(), 0);
+}
+
+#[test]
+fn transpositions() {
+    let game = crate::read_pgn("1. Nf3 Nf6 2. Ng1 Ng8 (2... d5 3. d4 Ng8) 3. d4 d5 *").unwrap();
+    let group_vec = game.transpositions();
+    let ply_vec = |group: &Vec<crate::game::Node>| {
+        group
+            .iter()
+            .map(|node| node.moves().len())
+            .collect::<Vec<usize>>()
+    };
+    assert_eq!(group_vec.len(), 2);
+    assert_eq!(ply_vec(&group_vec[0]), [0, 4]); // Start position repeated
+    assert_eq!(ply_vec(&group_vec[1]), [6, 6]); // 3. d4 d5 and 2... d5 3. d4 Ng8
+
+    assert!(crate::read_pgn("1. e4 e5 *")
+        .unwrap()
+        .transpositions()
+        .is_empty());
+}
+
+#[test]
+fn deep_tree_traversal() {
+    use crate::{Move, Role, Square};
+
+    // Shuffle the knights back and forth, far deeper than the call stack allows
+    let shuffle = [
+        (Square::G1, Square::F3),
+        (Square::G8, Square::F6),
+        (Square::F3, Square::G1),
+        (Square::F6, Square::G8),
+    ];
+    let game = crate::game::Game::default();
+    let mut node = game.root();
+    for i in 0..100_000 {
+        let (from, to) = shuffle[i % 4];
+        node = node
+            .new_variation(Move::Normal {
+                role: Role::Knight,
+                from,
+                to,
+                capture: None,
+                promotion: None,
+            })
+            .unwrap();
+    }
+    assert_eq!(game.root().descendants().count(), 100_000);
+    assert_eq!(game.root().descendants_bfs().count(), 100_000);
+    assert_eq!(game.root().mainline_iter().last(), Some(node));
+}
+
+#[test]
+fn symbolic_nags() {
+    use crate::PgnWriterOptions;
+
+    let game = crate::read_pgn("1. e4 $1 e5 $4 $18 2. Nf3 $146 $5 *").unwrap();
+    let options = PgnWriterOptions {
+        symbolic_nags: true,
+        ..PgnWriterOptions::default()
+    };
+    assert!(game
+        .to_pgn_with(&options)
+        .ends_with("\n\n1. e4! e5?? $18 2. Nf3!? $146 *\n"));
+    assert!(game
+        .to_string()
+        .ends_with("1. e4 $1 e5 $4 $18 2. Nf3 $5 $146 *\n"));
+
+    let options = PgnWriterOptions {
+        nag_glosses: true,
+        ..options
+    };
+    assert!(game
+        .to_pgn_with(&options)
+        .ends_with("\n\n1. e4! e5?? { White has a decisive advantage } 2. Nf3!? $146 *\n"));
+    let options = PgnWriterOptions {
+        symbolic_nags: false,
+        ..options
+    };
+    assert!(game
+        .to_pgn_with(&options)
+        .ends_with("\n\n1. e4 { good move } 1... e5 { very poor move } { White has a decisive advantage } 2. Nf3 { speculative move } $146 *\n"));
+}
+
+#[test]
+fn movetext_only() {
+    use crate::{HeaderSelection, PgnWriterOptions};
+
+    let game = crate::game::Game::default();
+    assert_eq!(game.movetext(), "");
+    let options = PgnWriterOptions {
+        headers: HeaderSelection::None,
+        ..PgnWriterOptions::default()
+    };
+    assert_eq!(game.to_pgn_with(&options), "*\n");
+
+    let game = crate::read_pgn(GAME_0).unwrap();
+    let movetext = game.movetext();
+    assert!(movetext.starts_with("{ Chess, when played perfectly, ends in a draw } 1. d4"));
+    assert!(movetext.ends_with("54. Kh6 Qg6# { Black wins by checkmate. }"));
+    assert!(!movetext.contains('\n'));
+
+    let options = PgnWriterOptions {
+        max_width: Some(80),
+        ..PgnWriterOptions::movetext_only()
+    };
+    assert!(game.to_pgn_with(&options).lines().count() > 1);
+}
+
+#[test]
+fn indented_variations() {
+    use crate::{HeaderSelection, PgnWriterOptions, VariationLayout};
+
+    let game = crate::read_pgn(
+        "1. e4 (1. d4 d5 (1... Nf6 2. c4 (2. Nf3)) 2. c4) (1. c4) 1... e5 $1 { good } 2. Nf3 *",
+    )
+    .unwrap();
+    let options = PgnWriterOptions {
+        variation_layout: VariationLayout::Indented(2),
+        headers: HeaderSelection::None,
+        ..PgnWriterOptions::default()
+    };
+    assert_eq!(
+        game.to_pgn_with(&options),
+        "1. e4\n  ( 1. d4 d5\n    ( 1... Nf6 2. c4\n      ( 2. Nf3 ) )\n  2. c4 )\n  ( 1. c4 )\n\
+         1... e5 $1 { good } 2. Nf3 *\n"
+    );
+
+    // Wrapped lines keep the indentation of their variation
+    let options = PgnWriterOptions {
+        max_width: Some(16),
+        ..options
+    };
+    assert_eq!(
+        game.to_pgn_with(&options),
+        "1. e4\n  ( 1. d4 d5\n    ( 1... Nf6\n    2. c4\n      ( 2. Nf3 )\n    )\n  2. c4 )\n  ( 1. c4 )\n\
+         1... e5 $1\n{ good } 2. Nf3\n*\n"
+    );
+    assert_eq!(
+        crate::read_pgn(&game.to_pgn_with(&options))
+            .unwrap()
+            .to_string(),
+        game.to_string()
+    );
+}
+
+#[test]
+fn comment_placement() {
+    use crate::game::CommentPlacement;
+    use crate::{GameCommentPlacement, HeaderSelection, PgnWriterOptions};
+
+    let game = crate::read_pgn(
+        "{ Game } 1. e4 { King's pawn } ( { Also } 1. d4 { Queen's pawn } ) 1... e5 *",
+    )
+    .unwrap();
+    let options = PgnWriterOptions {
+        headers: HeaderSelection::None,
+        ..PgnWriterOptions::default()
+    };
+    assert_eq!(
+        game.to_pgn_with(&options),
+        "{ Game } 1. e4 { King's pawn } ( { Also } 1. d4 { Queen's pawn } ) 1... e5 *\n"
+    );
+
+    let options = PgnWriterOptions {
+        starting_comments: Some(CommentPlacement::AfterMove),
+        move_comments: Some(CommentPlacement::BeforeMove),
+        game_comments: GameCommentPlacement::AfterResult,
+        ..options
+    };
+    assert_eq!(
+        game.to_pgn_with(&options),
+        "{ King's pawn } 1. e4 ( { Queen's pawn } 1. d4 { Also } ) 1... e5 * { Game }\n"
+    );
+
+    let options = PgnWriterOptions {
+        starting_comments: None,
+        game_comments: GameCommentPlacement::Omit,
+        ..options
+    };
+    assert_eq!(
+        game.to_pgn_with(&options),
+        "{ King's pawn } 1. e4 ( { Queen's pawn } 1. d4 ) 1... e5 *\n"
+    );
+}
+
+#[test]
+fn line_wrapping() {
+    use crate::PgnWriterOptions;
+
+    let game = crate::read_pgn(GAME_0).unwrap();
+    for max_width in [20, 40, 80] {
+        let options = PgnWriterOptions {
+            max_width: Some(max_width),
+            symbolic_nags: true,
+            ..PgnWriterOptions::default()
+        };
+        let pgn = game.to_pgn_with(&options);
+        let movetext = pgn.split_once("\n\n").unwrap().1;
+        assert!(movetext
+            .lines()
+            .all(|l| l.chars().count() <= max_width as usize));
+        assert!(movetext
+            .lines()
+            .all(|l| !l.ends_with("1.") && !l.starts_with('!')));
+
+        let round_trip = crate::read_pgn(&pgn).unwrap();
+        assert_eq!(round_trip.movetext(), game.movetext());
+    }
+
+    let options = PgnWriterOptions {
+        max_width: Some(30),
+        ..PgnWriterOptions::default()
+    };
+    let pgn = game.to_pgn_with(&options);
+    assert!(pgn.contains("\n\n{ Chess, when played\nperfectly, ends in a draw }\n1. d4 { The best opening\nmove } ( 1. e4"));
+}
+
+#[test]
+fn database_writer() {
+    use crate::{LineEnding, PgnDatabaseWriter, PgnWriterOptions};
+
+    let options = PgnWriterOptions {
+        line_ending: LineEnding::CrLf,
+        ..PgnWriterOptions::default()
+    };
+    let mut writer = PgnDatabaseWriter::new(Vec::new(), &options);
+    for pgn in [GAME_0, "1. e4 *", "[Result \"1-0\"]\n\n1. d4 d5 1-0"] {
+        writer.write_game(&crate::read_pgn(pgn).unwrap()).unwrap();
+    }
+    assert_eq!(writer.game_count(), 3);
+
+    let pgn = String::from_utf8(writer.into_inner()).unwrap();
+    assert_eq!(pgn.matches("[Event ").count(), 3);
+    assert_eq!(pgn.matches("\r\n\r\n[Event ").count(), 2);
+    assert!(pgn.contains("0-1\r\n\r\n[Event \"?\"]"));
+    assert!(pgn.ends_with("1. d4 d5 1-0\r\n"));
+    assert!(!pgn.replace("\r\n", "").contains('\n'));
+}
+
+#[test]
+fn seven_tag_roster() {
+    use crate::PgnWriterOptions;
+
+    let mut game = crate::read_pgn(
+        "[ECO \"C20\"]\n[White \"Kasparov\"]\n\n1. e4 e5 2. Qh5 Nc6 3. Bc4 Nf6 4. Qxf7# *",
+    )
+    .unwrap();
+    game.opt_headers
+        .insert("Event".to_string(), "Duplicate".to_string());
+    game.opt_headers
+        .insert("Annotator".to_string(), "Steinitz".to_string());
+    game.opt_headers
+        .insert("FEN".to_string(), crate::Fen::default().to_string());
+    game.opt_headers
+        .insert("SetUp".to_string(), "1".to_string());
+    let pgn = game.to_string();
+    let tag_vec: Vec<&str> = pgn
+        .lines()
+        .take_while(|l| l.starts_with('['))
+        .map(|l| l[1..].split_once(' ').unwrap().0)
+        .collect();
+    assert_eq!(
+        tag_vec,
+        [
+            "Event",
+            "Site",
+            "Date",
+            "Round",
+            "White",
+            "Black",
+            "Result",
+            "SetUp",
+            "FEN",
+            "Annotator",
+            "ECO"
+        ]
+    );
+    assert!(pgn.contains("[Result \"*\"]"));
+
+    let options = PgnWriterOptions {
+        fill_date: true,
+        fill_result: true,
+        ..PgnWriterOptions::default()
+    };
+    let pgn = game.to_pgn_with(&options);
+    assert!(pgn.contains("[Result \"1-0\"]"));
+    assert!(pgn.ends_with("4. Qxf7# 1-0\n"));
+    let date = pgn.lines().nth(2).unwrap();
+    assert_eq!(date.len(), "[Date \"2026.01.01\"]".len());
+    assert!(!date.contains('?'));
+
+    game.opt_headers
+        .insert("PlyCount".to_string(), "3".to_string());
+    let options = PgnWriterOptions {
+        fill_ply_count: true,
+        ..PgnWriterOptions::default()
+    };
+    let pgn = game.to_pgn_with(&options);
+    assert!(pgn.contains("[Annotator \"Steinitz\"]\n[ECO \"C20\"]\n[PlyCount \"7\"]\n"));
+    assert_eq!(
+        game.opt_headers.get("PlyCount").map(String::as_str),
+        Some("3")
+    );
+
+    let options = PgnWriterOptions {
+        fill_opening: Some(|node| match node.san()?.to_string().as_str() {
+            "e4" => Some(("B00".to_string(), "King's Pawn Game".to_string())),
+            "Qh5" => Some(("C20".to_string(), "Wayward Queen Attack".to_string())),
+            _ => None,
+        }),
+        ..PgnWriterOptions::default()
+    };
+    let pgn = game.to_pgn_with(&options);
+    assert!(pgn.contains("[ECO \"C20\"]\n[Opening \"Wayward Queen Attack\"]\n"));
+    assert!(!game.opt_headers.contains_key("Opening"));
+    assert!(!crate::read_pgn("1. d4 *")
+        .unwrap()
+        .to_pgn_with(&options)
+        .contains("[ECO"));
+}
+
+#[test]
+fn escaping() {
+    let mut game = crate::read_pgn(
+        r#"[Event "The \"Immortal\" \\ Game"]
+
+1. e4 e5 *"#,
+    )
+    .unwrap();
+    assert_eq!(
+        game.header.event.as_deref(),
+        Some(r#"The "Immortal" \ Game"#)
+    );
+
+    game.header.white = Some("Line\nbreak".to_string());
+    game.root()
+        .mainline()
+        .unwrap()
+        .set_comment(Some("A {nested} comment }".to_string()));
+    let pgn = game.to_string();
+    assert!(pgn.contains(r#"[Event "The \"Immortal\" \\ Game"]"#));
+    assert!(pgn.contains("[White \"Line break\"]"));
+    assert!(pgn.contains("1. e4 { A {nested comment } 1... e5"));
+
+    let round_trip = crate::read_pgn(&pgn).unwrap();
+    assert_eq!(round_trip.header.event, game.header.event);
+    assert_eq!(round_trip.movetext(), "1. e4 { A {nested comment } 1... e5");
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn json() {
+    use crate::game::Game;
+
+    let game = crate::read_pgn(GAME_0).unwrap();
+    let json = game.to_json();
+    let round_trip = Game::from_json(&json).unwrap();
+    assert_eq!(round_trip.movetext(), game.movetext());
+    assert_eq!(round_trip.opt_headers, game.opt_headers);
+    assert_eq!(round_trip.to_json(), json);
+
+    let game = crate::read_pgn("1. e4 { [%eval 0.3] [%clk 0:01:00] } *").unwrap();
+    let subtree = game.root().mainline().unwrap().to_game();
+    let json = subtree.to_json();
+    assert!(json.contains(r#""fen":"rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1""#));
+    let round_trip = Game::from_json(&json).unwrap();
+    assert_eq!(round_trip.initial_position(), subtree.initial_position());
+
+    let json = game.to_json();
+    assert!(json.contains(r#""clock":60.0,"eval":{"cp":30}"#));
+    let node = Game::from_json(&json).unwrap().root().mainline().unwrap();
+    assert_eq!(node.clock(), Some(std::time::Duration::from_secs(60)));
+    for clock in ["-1", "1e300"] {
+        let json = format!(r#"{{"moves":[{{"san":"e4","clock":{}}}]}}"#, clock);
+        assert!(matches!(
+            Game::from_json(&json),
+            Err(crate::game::JsonError::InvalidClock(_))
+        ));
+    }
+
+    // Chess960 castling, as in Node::uci
+    let game =
+        crate::read_pgn("[SetUp \"1\"]\n[FEN \"rk5r/8/8/8/8/8/8/RK5R w KQkq - 0 1\"]\n\n1. O-O *")
+            .unwrap();
+    assert!(game.to_json().contains(r#""san":"O-O","uci":"b1h1""#));
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn serde_round_trip() {
+    use crate::game::{Eval, EvalInfo, Game, Shape, ShapeColor};
+
+    let game = crate::read_pgn(GAME_0).unwrap();
+    let json = serde_json::to_string(&game).unwrap();
+    let stored: Game = serde_json::from_str(&json).unwrap();
+    assert_eq!(stored.to_string(), game.to_string());
+    assert_eq!(serde_json::to_string(&stored).unwrap(), json);
+
+    // Ids survive removed nodes and ids out of creation order
+    let game =
+        crate::read_pgn("1. e4 (1. d4) 1... e5 { [%clk 0:01:00] [%eval 0.3,20] } *").unwrap();
+    game.root().other_variations()[0].clone().remove_node();
+    let mut root = game.root();
+    let c4_node = root.play_san("c4").unwrap();
+    root.promote_variation(c4_node.clone());
+    let mut e5_node = game.node_at(&["e4", "e5"]).unwrap();
+    e5_node.set_shapes(vec![Shape::arrow(
+        ShapeColor::Red,
+        crate::Square::G1,
+        crate::Square::F3,
+    )]);
+    e5_node.set_meta("seen", Some("yes".to_string()));
+
+    let stored: Game = serde_json::from_str(&serde_json::to_string(&game).unwrap()).unwrap();
+    assert_eq!(stored.to_string(), game.to_string());
+    let stored_e5 = stored.node_by_id(e5_node.id()).unwrap();
+    assert_eq!(stored_e5.moves(), e5_node.moves());
+    assert_eq!(stored_e5.eval_info(), e5_node.eval_info());
+    assert_eq!(stored_e5.shapes(), e5_node.shapes());
+    assert_eq!(stored_e5.meta("seen"), Some("yes".to_string()));
+    assert_eq!(stored.node_by_id(c4_node.id()), stored.root().mainline());
+    assert!(stored.node_by_id(2).is_none()); // 1. d4
+
+    // Chess960 castling
+    let game = crate::read_pgn(
+        "[SetUp \"1\"]\n[FEN \"nrkbbqrn/pppppppp/8/8/8/8/PPPPPPPP/NRKBBQRN w KQkq - 0 1\"]\n\n*",
+    )
+    .unwrap();
+    let stored: Game = serde_json::from_str(&serde_json::to_string(&game).unwrap()).unwrap();
+    assert_eq!(stored.initial_position(), game.initial_position());
+
+    // A stored line that castles with the king away from the e-file
+    let game =
+        crate::read_pgn("[SetUp \"1\"]\n[FEN \"rk5r/8/8/8/8/8/8/RK5R w KQkq - 0 1\"]\n\n1. Kc1 *")
+            .unwrap();
+    let mut root = game.root();
+    root.set_eval_info(Some(EvalInfo {
+        pv: vec![crate::Move::Castle {
+            king: crate::Square::B1,
+            rook: crate::Square::H1,
+        }],
+        ..Eval::Centipawns(10).into()
+    }));
+    let json = serde_json::to_string(&game).unwrap();
+    assert!(json.contains(r#""pv":["b1h1"]"#));
+    let stored: Game = serde_json::from_str(&json).unwrap();
+    assert_eq!(stored.root().eval_info(), root.eval_info());
+
+    assert!(serde_json::from_str::<Game>(
+        r#"{"headers":{},"tree":{"fen":"8/8/8/8/8/8/8/8 w - - 0 1","nodes":[]}}"#
+    )
+    .is_err());
+}
+
+#[test]
+fn epd_export() {
+    use crate::epd::{read_epd, EpdSelection};
+
+    let game = crate::read_pgn(GAME_0).unwrap();
+    let mainline = game.to_epd(EpdSelection::Mainline);
+    assert_eq!(mainline.lines().count(), 109);
+    let all = game.to_epd(EpdSelection::All);
+    assert_eq!(all.lines().count(), 109 + 2 + 9 + 3);
+
+    for line in all.lines() {
+        read_epd(line).unwrap();
+    }
+    let line = mainline.lines().nth(16).unwrap(); // 8... Bg4 $2
+    assert!(line.ends_with("hmvc 3; fmvn 9; nag $2;"));
+    let mut node = game.root();
+    for _ in 0..16 {
+        node = node.mainline().unwrap();
+    }
+    assert_eq!(read_epd(line).unwrap().position(), node.position());
+
+    let game = crate::read_pgn("1. f3 e5 2. g4 { \"Fool's\" \\ mate [%eval #-1] } *").unwrap();
+    let line = game
+        .to_epd(EpdSelection::Mainline)
+        .lines()
+        .last()
+        .unwrap()
+        .to_string();
+    let epd = read_epd(&line).unwrap();
+    assert_eq!(epd.direct_mate, Some(1));
+    assert_eq!(epd.operands("c0").unwrap()[0], "\"Fool's\" \\ mate");
+}
+
+#[cfg(feature = "render")]
+#[test]
+fn svg_render() {
+    use crate::render::{Arrow, ShapeColor, SvgOptions};
+    use crate::Square;
+
+    let game = crate::read_pgn("1. e4 e5 2. Qh5 Nc6 3. Bc4 Nf6 4. Qxf7# *").unwrap();
+    let mut node = game.root();
+    while let Some(next) = node.mainline() {
+        node = next;
+    }
+
+    let options = SvgOptions {
+        size: 400,
+        flipped: true,
+        coordinates: false,
+        arrows: vec![Arrow {
+            from: Square::C4,
+            to: Square::F7,
+            color: ShapeColor::Red,
+        }],
+        ..SvgOptions::default()
+    };
+    let svg = node.to_svg(&options);
+    assert!(svg.starts_with(r#"<svg xmlns="http://www.w3.org/2000/svg" width="400""#));
+    assert!(svg.ends_with("</svg>"));
+    assert_eq!(svg.matches("<rect").count(), 64);
+    assert_eq!(svg.matches("<text").count(), 31);
+    assert_eq!(svg.matches("#882020").count(), 2);
+    // Flipped, h5 is on the left edge and f7 near the bottom
+    assert!(svg.contains(r##"<rect x="0" y="200" width="50" height="50" fill="#cdd26a"/>"##));
+    assert!(svg.contains(r##"<rect x="100" y="300" width="50" height="50" fill="#cdd26a"/>"##));
+}
+
+#[cfg(feature = "animation")]
+#[test]
+fn animation() {
+    use crate::animation::AnimationOptions;
+    use std::time::Duration;
+
+    let game = crate::read_pgn("1. e4 e5 2. Qh5 Nc6 3. Bc4 Nf6 4. Qxf7# *").unwrap();
+    let options = AnimationOptions {
+        square_size: 24,
+        flipped: true,
+        delay: Duration::from_millis(500),
+        ..AnimationOptions::default()
+    };
+
+    let gif = game.to_gif(&options);
+    let mut decoder = gif::DecodeOptions::new().read_info(gif.as_slice()).unwrap();
+    assert_eq!((decoder.width(), decoder.height()), (192, 192));
+    let mut delay_vec = Vec::new();
+    while let Some(frame) = decoder.read_next_frame().unwrap() {
+        delay_vec.push(frame.delay);
+    }
+    assert_eq!(delay_vec, [50, 50, 50, 50, 50, 50, 50, 300]);
+
+    let apng = game.to_apng(&options);
+    let reader = png::Decoder::new(apng.as_slice()).read_info().unwrap();
+    let info = reader.info();
+    assert_eq!((info.width, info.height), (192, 192));
+    assert_eq!(info.animation_control.unwrap().num_frames, 8);
+
+    // Flipped, the white king sits on the top row and the first frame
+    // shows no highlight
+    let gif_vec = |options: &AnimationOptions| {
+        let gif = crate::read_pgn("*").unwrap().to_gif(options);
+        let mut decoder = gif::DecodeOptions::new();
+        decoder.set_color_output(gif::ColorOutput::Indexed);
+        let mut decoder = decoder.read_info(gif.as_slice()).unwrap();
+        decoder.read_next_frame().unwrap().unwrap().buffer.to_vec()
+    };
+    let flipped = gif_vec(&options);
+    let upright = gif_vec(&AnimationOptions {
+        flipped: false,
+        ..options.clone()
+    });
+    assert_ne!(flipped, upright);
+    assert!(!flipped.contains(&2));
+    assert!(flipped[..192 * 24].contains(&3)); // white pieces
+    assert!(upright[..192 * 24].contains(&4)); // black pieces
+}
+
+#[test]
+fn board_diagram() {
+    let game = crate::read_pgn("1. e4 e5 2. Nf3 *").unwrap();
+    let node = game
+        .root()
+        .mainline()
+        .unwrap()
+        .mainline()
+        .unwrap()
+        .mainline()
+        .unwrap();
+
+    let diagram = crate::game::BoardDiagram {
+        flipped: true,
+        coordinates: false,
+        ..node.diagram()
+    };
+    assert_eq!(
+        diagram.to_string(),
+        "R . B K Q B N R\n\
+         P P P . P P P P\n\
+         . . N . . . . .\n\
+         . . . P . . . .\n\
+         . . . p . . . .\n\
+         . . . . . . . .\n\
+         p p p . p p p p\n\
+         r n b k q b n r\n"
+    );
+
+    let diagram = crate::game::BoardDiagram {
+        unicode: true,
+        ..node.diagram()
+    };
+    let text = diagram.to_string();
+    assert_eq!(text.lines().nth(5).unwrap(), "3 . . . . . ♘ . .");
+    assert_eq!(text.lines().count(), 9);
+}
+
+#[test]
+fn html_export() {
+    let game = crate::read_pgn(GAME_0).unwrap();
+    let html = game.to_html();
+    assert!(html.starts_with("<div class=\"game\">\n<table class=\"headers\">\n<tr><th>Event</th>"));
+    assert!(html.ends_with("<span class=\"result\">0-1</span>\n</div>\n</div>\n"));
+    assert_eq!(
+        html.matches("<span class=\"move\"").count(),
+        108 + 2 + 9 + 3
+    );
+    assert_eq!(html.matches("<ul class=\"variations\">").count(), 3);
+    assert_eq!(html.matches("<li>").count(), html.matches("</li>").count());
+    assert!(html.contains("<span class=\"nag\" data-nag=\"3\">!!</span>"));
+    assert!(html.contains("<th>Opening</th><td>Queen's Pawn Game"));
+}
+
+#[test]
+fn latex_export() {
+    let game = crate::read_pgn(GAME_0).unwrap();
+    let latex = game.to_latex();
+    assert!(latex.starts_with(
+        "\\newchessgame[event={Casual Rapid game},site={https://lichess.org/5uSupub7},date={2023.03.06},white={maia1},"
+    ));
+    assert!(latex.contains("8.Nf3 Bg4?} (Apparently this is best \\variation{8...Nxe5"));
+    assert!(latex.contains("(\\variation{12...Qa5+ 13.Nd2 Qxb5}) \\mainline{13.f4"));
+    assert_eq!(latex.matches('{').count(), latex.matches('}').count());
+
+    let game = crate::read_pgn("1. e4 { 100% [#] } 1... e5 $221 2. Nf3 *").unwrap();
+    assert!(game.to_latex().ends_with(
+        "\\mainline{1.e4} 100\\%\n\n\\chessboard\n\n\\mainline{1...e5}\n\n\\chessboard[inverse]\n\n\\mainline{2.Nf3} *\n"
+    ));
+}
+
+#[test]
+fn markdown_export() {
+    let game = crate::read_pgn(GAME_0).unwrap();
+    let markdown = game.to_markdown();
+    assert!(markdown.starts_with("---\nEvent: \"Casual Rapid game\"\n"));
+    assert!(markdown.contains(
+        "1. d4 *The best opening move*\n- 1. e4 *This blunder allows the Sicilian Defense* 1... c5\n\n1... d5 2. Bf4"
+    ));
+    assert!(markdown.contains("8. Nf3 Bg4?\n- *Apparently this is best* 8... Nxe5"));
+    assert!(markdown.ends_with("54. Kh6 Qg6# *Black wins by checkmate.* **0-1**\n"));
+}
+
+#[test]
+fn odds_games() {
+    use crate::game::{Game, GameBuilder};
+    use crate::pgn::reader::Severity;
+
+    // Queen's rook odds, with castling rights the FEN cannot have
+    let pgn = "[SetUp \"1\"]\n[FEN \"rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/1NBQKBNR w KQkq - 0 1\"]\n\n1. e4 e5 2. Nf3 Nc6 *";
+    let (game, diagnostics) = crate::read_pgn_with(pgn, &Default::default()).unwrap();
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].severity, Severity::Warning);
+    assert!(diagnostics[0]
+        .message
+        .ends_with("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/1NBQKBNR w Kkq - 0 1"));
+    assert_eq!(game.movetext(), "1. e4 e5 2. Nf3 Nc6");
+    // The tag is kept as it was written
+    assert_eq!(
+        game.tag("FEN").as_deref(),
+        Some("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/1NBQKBNR w KQkq - 0 1")
+    );
+    assert!(game.validate().is_empty());
+    let round_trip = crate::read_pgn(&game.to_string()).unwrap();
+    assert_eq!(round_trip.initial_position(), game.initial_position());
+
+    // Built from the same FEN, the tag is kept the same way
+    let fen = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/1NBQKBNR w KQkq - 0 1";
+    let built = GameBuilder::new()
+        .fen(fen)
+        .moves(["e4", "e5", "Nf3", "Nc6"])
+        .build()
+        .unwrap();
+    assert_eq!(built.tag("FEN").as_deref(), Some(fen));
+    assert_eq!(built.initial_position(), game.initial_position());
+    assert!(built.validate().is_empty());
+
+    // Queen odds, black to move, numbered from the FEN's counters
+    let fen = "rnb1kbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 7";
+    let game = GameBuilder::new()
+        .fen(fen)
+        .moves(["e5", "Nf3", "Nc6"])
+        .build()
+        .unwrap();
+    assert_eq!(game.movetext(), "7... e5 8. Nf3 Nc6");
+    assert_eq!(game.tag("FEN").as_deref(), Some(fen));
+
+    // Without a queen, the black king can step to d8
+    let game = Game::from_fen(fen).unwrap();
+    assert_eq!(game.root().legal_moves_san().len(), 21);
+    assert!(Game::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQ1BNR w KQkq - 0 1").is_err());
+}
+
+#[test]
+fn chess960_fen() {
+    let fen = "nrkbqrbn/pppppppp/8/8/8/8/PPPPPPPP/NRKBQRBN w KQkq - 0 1";
+    let pgn = format!(
+        "[Variant \"Chess960\"]\n[SetUp \"1\"]\n[FEN \"{}\"]\n\n1. e4 e5 *",
+        fen
+    );
+    let (game, diagnostics) = crate::validate_pgn(&pgn).unwrap();
+    assert!(diagnostics.is_empty());
+    assert_eq!(game.tag("FEN").as_deref(), Some(fen));
+    assert_eq!(
+        crate::Position::castles(&game.initial_position())
+            .castling_rights()
+            .count(),
+        4
+    );
+
+    // Both sides castle short, the king to g1 and the rook to f1
+    let fen = "nrk2r2/pppppppp/8/8/8/8/PPPPPPPP/NRK2R2 w KQkq - 0 1";
+    let pgn = format!("[SetUp \"1\"]\n[FEN \"{}\"]\n\n1. O-O O-O *", fen);
+    let (game, diagnostics) = crate::validate_pgn(&pgn).unwrap();
+    assert!(diagnostics.is_empty());
+    assert_eq!(game.movetext(), "1. O-O O-O");
+    let round_trip = crate::read_pgn(&game.to_string()).unwrap();
+    assert_eq!(round_trip.tag("FEN").as_deref(), Some(fen));
+    assert_eq!(round_trip.movetext(), "1. O-O O-O");
+}
+
+/// A UCI engine that always gives the same lines, for the start position
+/// and for the position after 1. e4.
+#[cfg(all(feature = "engine", unix))]
+const FAKE_ENGINE: &str = r#"
+while read -r line; do
+  case "$line" in
+    uci)
+      echo "id name Fake 1.0"
+      echo "id author Nobody"
+      echo "option name Hash type spin default 16 min 1 max 1024"
+      echo "option name UCI_Variant type combo default chess var chess var atomic"
+      echo "uciok" ;;
+    isready) echo "readyok" ;;
+    "position startpos moves e2e4") side=black ;;
+    position*) side=white ;;
+    "go infinite") echo "info depth 1 score cp 20 pv e2e4" ;;
+    stop) echo "bestmove e2e4" ;;
+    go*)
+      if [ "$side" = black ]; then
+        echo "info depth 1 score cp -20 pv c7c5"
+        echo "info depth 2 seldepth 4 score cp -30 lowerbound pv e7e5"
+        echo "info depth 2 seldepth 4 score cp -25 nodes 300 time 7 pv c7c5 g1f3"
+        echo "bestmove c7c5 ponder g1f3"
+      else
+        echo "info string using fake evaluation"
+        echo "info depth 1 score cp 20 pv e2e4"
+        echo "info depth 2 seldepth 3 multipv 1 score cp 35 nodes 120 time 5 pv e2e4 e7e5"
+        echo "info depth 2 seldepth 3 multipv 2 score cp 20 nodes 120 time 5 pv d2d4 d7d5"
+        echo "bestmove e2e4 ponder e7e5"
+      fi ;;
+    quit) exit 0 ;;
+  esac
+done
+"#;
+
+#[cfg(all(feature = "engine", unix))]
+fn fake_engine() -> crate::engine::Engine {
+    let mut command = std::process::Command::new("sh");
+    command.arg("-c").arg(FAKE_ENGINE);
+    crate::engine::Engine::from_command(command).unwrap()
+}
+
+#[cfg(all(feature = "engine", unix))]
+#[test]
+fn engine() {
+    use crate::engine::{EngineError, Limits, OptionKind};
+    use crate::game::Eval;
+    use crate::Square;
+
+    let mut engine = fake_engine();
+    assert_eq!(engine.name(), Some("Fake 1.0"));
+    assert_eq!(engine.author(), Some("Nobody"));
+    assert_eq!(engine.options().len(), 2);
+    assert_eq!(engine.options()[0].max, Some(1024));
+    assert_eq!(engine.options()[1].kind, OptionKind::Combo);
+    assert_eq!(engine.options()[1].vars, vec!["chess", "atomic"]);
+    engine.set_option("hash", "64").unwrap();
+    assert!(matches!(
+        engine.set_option("Threads", "2"),
+        Err(EngineError::UnknownOption(_))
+    ));
+    engine.new_game().unwrap();
+
+    let game = crate::read_pgn("1. e4 *").unwrap();
+    let analysis = engine.analyze(&game.root(), &Limits::depth(2)).unwrap();
+    assert_eq!(analysis.best_move.unwrap().to(), Square::E4);
+    assert_eq!(analysis.ponder.unwrap().to(), Square::E5);
+    assert_eq!(analysis.depth, 2);
+    assert_eq!(analysis.seldepth, Some(3));
+    assert_eq!(analysis.eval, Some(Eval::Centipawns(35)));
+    assert_eq!(analysis.pv.len(), 2);
+    assert_eq!(analysis.nodes, Some(120));
+    assert_eq!(analysis.lines.len(), 2);
+    assert_eq!(analysis.lines[1].eval, Some(Eval::Centipawns(20)));
+
+    // Scores are turned to White's point of view, and bounds are ignored
+    let e4_node = game.root().mainline().unwrap();
+    let analysis = engine.analyze(&e4_node, &Limits::depth(2)).unwrap();
+    assert_eq!(analysis.best_move.unwrap().to(), Square::C5);
+    assert_eq!(analysis.eval, Some(Eval::Centipawns(25)));
+    assert_eq!(analysis.pv.len(), 2);
+    assert_eq!(analysis.time, Some(std::time::Duration::from_millis(7)));
+
+    let position = game.initial_position();
+    let analysis = engine
+        .analyze_position(&position, &Limits::nodes(100))
+        .unwrap();
+    assert_eq!(analysis.best_move.unwrap().to(), Square::E4);
+}
+
+#[cfg(all(feature = "engine", unix))]
+#[test]
+fn analyze_all() {
+    use crate::engine::Limits;
+    use crate::game::Eval;
+
+    let mut engine = fake_engine();
+    let game = crate::read_pgn("1. e4 (1. d4) 1... c5 *").unwrap();
+    game.analyze_all(&mut engine, &Limits::depth(2), false)
+        .unwrap();
+
+    let root_info = game.root().eval_info().unwrap();
+    assert_eq!(root_info.eval, Eval::Centipawns(35));
+    assert_eq!(root_info.depth, Some(2));
+    assert_eq!(root_info.engine.as_deref(), Some("Fake 1.0"));
+    assert_eq!(root_info.pv.len(), 2);
+    let e4_node = game.root().mainline().unwrap();
+    assert_eq!(e4_node.eval(), Some(Eval::Centipawns(25)));
+    assert!(game.root().variation_vec()[1].eval().is_none());
+    assert!(game.to_string().contains("1. e4 { [%eval 0.25,2] }"));
+
+    game.analyze_all(&mut engine, &Limits::depth(2), true)
+        .unwrap();
+    assert!(game.root().variation_vec()[1].eval().is_some());
+}
+
+#[test]
+fn polyglot_book() {
+    use crate::book::{BookBuilder, BookOptions};
+
+    let game = |pgn: &str| crate::read_pgn(pgn).unwrap();
+    let options = BookOptions {
+        max_plies: 7,
+        min_rating: Some(2000),
+        ..BookOptions::default()
+    };
+    let mut builder = BookBuilder::new(options);
+    let rated = "[WhiteElo \"2100\"]\n[BlackElo \"2050\"]\n";
+    assert!(builder.add_game(&game(&format!(
+        "{}[Result \"1-0\"]\n\n1. e4 e5 2. Nf3 Nc6 3. Bc4 Nf6 4. O-O Be7 1-0",
+        rated
+    ))));
+    assert!(!builder.add_game(&game("[Result \"1-0\"]\n\n1. d4 1-0")));
+    assert!(!builder.add_game(&game(&format!("{}\n1. d4 *", rated))));
+    assert_eq!(builder.game_count(), 1);
+
+    let mut bin = Vec::new();
+    builder.write(&mut bin).unwrap();
+    // Only White's four moves scored, and the castling is the seventh ply
+    assert_eq!(bin.len(), 4 * 16);
+    let entry_vec: Vec<(u64, u16, u16)> = bin
+        .chunks(16)
+        .map(|chunk| {
+            (
+                u64::from_be_bytes(chunk[..8].try_into().unwrap()),
+                u16::from_be_bytes(chunk[8..10].try_into().unwrap()),
+                u16::from_be_bytes(chunk[10..12].try_into().unwrap()),
+            )
+        })
+        .collect();
+    assert!(entry_vec.windows(2).all(|pair| pair[0].0 <= pair[1].0));
+    assert!(entry_vec.contains(&(0x463b96181691fc9c, 0x031c, 2)));
+    // e1h1, the king taking its own rook
+    assert!(entry_vec.iter().any(|entry| entry.1 == 0x0107));
+}
+
+#[test]
+fn explorer() {
+    use crate::explorer::Explorer;
+
+    let game = |pgn: &str| crate::read_pgn(pgn).unwrap();
+    let mut explorer = Explorer::new(2);
+    assert!(explorer.add_game(&game(
+        "[WhiteElo \"2000\"]\n[BlackElo \"2200\"]\n[Result \"1-0\"]\n\n1. d4 d5 2. c4 1-0"
+    )));
+    assert!(explorer.add_game(&game(
+        "[WhiteElo \"1800\"]\n[BlackElo \"1800\"]\n[Result \"0-1\"]\n\n1. d4 Nf6 0-1"
+    )));
+    assert!(explorer.add_game(&game("[Result \"1/2-1/2\"]\n\n1. e4 1/2-1/2")));
+    assert!(!explorer.add_game(&game("1. c4 *")));
+    assert_eq!(explorer.game_count(), 3);
+
+    let root = game("*").root();
+    let stats = explorer.position_stats(&root);
+    assert_eq!(stats.games, 3);
+    assert_eq!(stats.average_rating(), Some(1950));
+    let moves = explorer.moves(&root);
+    assert_eq!(moves.len(), 2);
+    assert_eq!(moves[0].san, "d4");
+    assert_eq!(
+        (moves[0].stats.white_wins, moves[0].stats.black_wins),
+        (1, 1)
+    );
+    assert_eq!(moves[1].stats.draws, 1);
+    assert_eq!(moves[1].stats.average_rating(), None);
+
+    // Moves after the second ply are not counted
+    let d5_node = game("1. d4 d5 *").root().mainline_iter().last().unwrap();
+    assert_eq!(explorer.position_stats(&d5_node).games, 1);
+    assert!(explorer.moves(&d5_node).is_empty());
+    let c4_node = game("1. c4 *").root().mainline().unwrap();
+    assert_eq!(explorer.position_stats(&c4_node).games, 0);
+}
+
+#[cfg(all(feature = "engine", unix))]
+#[test]
+fn selfplay() {
+    use crate::engine::{Limits, SelfPlayOptions};
+    use crate::game::GameResult;
+
+    let mut engine = fake_engine();
+    let mut options = SelfPlayOptions::new(Limits::depth(2));
+    options.max_plies = Some(1);
+
+    let mut game = crate::read_pgn("[Result \"1-0\"]\n\n1-0").unwrap();
+    let root = game.root();
+    let node = game.selfplay(&root, &mut engine, None, &options).unwrap();
+    assert_eq!(game.movetext(), "1. e4 { [%eval 0.35,2] }");
+    assert_eq!(game.header.white.as_deref(), Some("Fake 1.0"));
+    assert_eq!(game.header.black.as_deref(), Some("Fake 1.0"));
+    assert_eq!(game.header.result, GameResult::Ongoing);
+
+    // The fake engine has no move after 1... c5, which ends the game
+    options.max_plies = None;
+    let mut other = fake_engine();
+    let last_node = game
+        .selfplay(&node, &mut engine, Some(&mut other), &options)
+        .unwrap();
+    assert_eq!(last_node.ply(), 2);
+    assert!(game.movetext().ends_with("1... c5 { [%eval 0.25,2] }"));
+
+    let mut game = crate::read_pgn("[Result \"1-0\"]\n\n1. d4 d5 1-0").unwrap();
+    let root = game.root();
+    game.selfplay(&root, &mut engine, None, &options).unwrap();
+    assert_eq!(game.root().variation_vec().len(), 2);
+    assert_eq!(game.header.result, GameResult::WhiteWins);
+}
+
+#[test]
+fn annotate_errors() {
+    use crate::game::{ClassifyOptions, LossMetric, MoveError};
+
+    let pgn = "1. e4 { [%eval 0.3] } 1... e5 $6 { [%eval 0.4] } \
+        2. Qh5 { [%eval -0.8] } 2... Ke7 { [%eval 3.0] } 3. Qxe5# *";
+    let game = crate::read_pgn(pgn).unwrap();
+    let node_vec: Vec<_> = game.root().mainline_iter().collect();
+    assert_eq!(node_vec[2].eval_loss(LossMetric::Centipawns), Some(120.0));
+    assert_eq!(node_vec[4].eval_loss(LossMetric::Centipawns), None);
+
+    let error_vec = game.annotate_errors(&ClassifyOptions::centipawns());
+    let errors: Vec<MoveError> = error_vec.iter().map(|(_, error)| *error).collect();
+    assert_eq!(errors, [MoveError::Mistake, MoveError::Blunder]);
+    assert_eq!(error_vec[0].0, node_vec[2]);
+    // The old inaccuracy mark of 1... e5 is gone
+    assert!(node_vec[1].nags().unwrap().is_empty());
+    assert_eq!(
+        node_vec[3].move_error(&ClassifyOptions::default()),
+        Some(MoveError::Blunder)
+    );
+    assert_eq!(MoveError::Blunder.to_string(), "blunder");
+}
+
+#[test]
+fn annotate_best_lines() {
+    use crate::game::{ClassifyOptions, MoveError};
+    use crate::{Position, San};
+
+    let game =
+        crate::read_pgn("1. e4 { [%eval 0.3] } 1... e5 { [%eval 0.3] } 2. Qh5 { [%eval -2.1] } *")
+            .unwrap();
+    let mut e5_node = game.root().mainline().unwrap().mainline().unwrap();
+    let mut position = e5_node.position();
+    let mut pv = Vec::new();
+    for san_str in ["Nf3", "Nc6", "Bc4"] {
+        let m = san_str.parse::<San>().unwrap().to_move(&position).unwrap();
+        position.play_unchecked(&m);
+        pv.push(m);
+    }
+    let mut info = e5_node.eval_info().unwrap();
+    info.pv = pv;
+    e5_node.set_eval_info(Some(info));
+
+    for _ in 0..2 {
+        let error_vec = game.annotate_best_lines(&ClassifyOptions::default(), 2);
+        assert_eq!(error_vec.len(), 1);
+        assert_eq!(error_vec[0].1, MoveError::Blunder);
+    }
+    let qh5_node = e5_node.mainline().unwrap();
+    assert_eq!(
+        qh5_node.comment().as_deref(),
+        Some("Blunder. Better was 2.Nf3 \u{2a72} +0.3")
+    );
+    assert_eq!(e5_node.variation_vec().len(), 2);
+    assert!(game.movetext().ends_with("( 2. Nf3 Nc6 )"));
+}
+
+#[cfg(all(feature = "engine", unix))]
+#[test]
+fn add_engine_lines() {
+    use crate::engine::Limits;
+    use crate::game::Eval;
+    use crate::Square;
+
+    let mut engine = fake_engine();
+    let game = crate::read_pgn("1. e4 { [%eval 0.4] } *").unwrap();
+    let root = game.root();
+    let analysis = engine.analyze(&root, &Limits::depth(2)).unwrap();
+    let first_vec = root.add_lines(&analysis, engine.name(), 2);
+
+    // 1. e4 was already there, and keeps its evaluation
+    assert_eq!(first_vec.len(), 2);
+    assert_eq!(first_vec[0], root.mainline().unwrap());
+    assert_eq!(first_vec[0].eval(), Some(Eval::Centipawns(40)));
+    let d4_info = first_vec[1].eval_info().unwrap();
+    assert_eq!(d4_info.eval, Eval::Centipawns(20));
+    assert_eq!(d4_info.engine.as_deref(), Some("Fake 1.0"));
+    assert_eq!(d4_info.pv[0].to(), Square::D5);
+    assert_eq!(
+        game.movetext(),
+        "1. e4 { [%eval 0.40] } ( 1. d4 { [%eval 0.20,2] } 1... d5 ) 1... e5"
+    );
+
+    // Adding the lines again changes nothing
+    root.add_lines(&analysis, engine.name(), 2);
+    assert_eq!(root.variation_vec().len(), 2);
+}
+
+#[test]
+fn accuracy() {
+    use crate::game::Eval;
+    use crate::Color;
+
+    let game = crate::read_pgn(
+        "1. f3 { [%eval -0.5] } 1... e5 { [%eval -0.5] } 2. g4 { [%eval #-1] } 2... Qh4# 0-1",
+    )
+    .unwrap();
+    assert!(game.accuracy().white.is_some());
+    game.root().set_eval(Some(Eval::Centipawns(20)));
+
+    let report = game.accuracy();
+    let white = report.get(Color::White).unwrap();
+    assert_eq!(white.moves, 2);
+    assert_eq!(white.acpl, 510.0);
+    assert_eq!(white.blunders, 1);
+    assert!(white.accuracy < 50.0);
+    // The mate has no evaluation, but is judged as one
+    let black = report.get(Color::Black).unwrap();
+    assert_eq!(black.moves, 2);
+    assert_eq!(black.acpl, 0.0);
+    assert!((black.accuracy - 100.0).abs() < 1e-9);
+
+    let game = crate::read_pgn("1. e4 e5 *").unwrap();
+    assert_eq!(game.accuracy().white, None);
+    assert_eq!(game.accuracy().black, None);
+}
+
+#[test]
+fn eval_series() {
+    use crate::game::{Eval, EvalPoint};
+
+    let game = crate::read_pgn(
+        "[SetUp \"1\"]\n[FEN \"4k3/8/8/8/8/8/4P3/4K3 b - - 0 12\"]\n\n12... Kd7 { [%eval 25.0] } 13. e4 { [%eval #7] } (13. e3 { [%eval 3.0] }) *",
+    )
+    .unwrap();
+    assert_eq!(game.eval_series().len(), 2);
+    game.root().set_eval(Some(Eval::Centipawns(-30)));
+
+    let series = game.eval_series();
+    assert_eq!(
+        series,
+        vec![
+            EvalPoint {
+                ply: 23,
+                centipawns: -30,
+                mate: None,
+            },
+            EvalPoint {
+                ply: 24,
+                centipawns: 1000,
+                mate: None,
+            },
+            EvalPoint {
+                ply: 25,
+                centipawns: 1000,
+                mate: Some(7),
+            },
+        ]
+    );
+    assert!(series[2].winning_chances() > 0.9);
+    assert!(crate::read_pgn("1. e4 e5 *")
+        .unwrap()
+        .eval_series()
+        .is_empty());
+}
+
+#[cfg(all(feature = "engine", feature = "tokio", unix))]
+#[tokio::test]
+async fn async_engine() {
+    use crate::engine::{AsyncEngine, Limits};
+    use crate::game::Eval;
+    use crate::Square;
+    use futures_util::future;
+
+    let mut command = tokio::process::Command::new("sh");
+    command.arg("-c").arg(FAKE_ENGINE);
+    let mut engine = AsyncEngine::from_command(command).await.unwrap();
+    assert_eq!(engine.name(), Some("Fake 1.0"));
+    engine.set_option("Hash", "32").await.unwrap();
+    engine.new_game().await.unwrap();
+
+    let game = crate::read_pgn("1. e4 *").unwrap();
+    let mut depth_vec = Vec::new();
+    let analysis = engine
+        .analyze_with(&game.root(), &Limits::depth(2), future::pending(), |info| {
+            depth_vec.push(info.depth)
+        })
+        .await
+        .unwrap();
+    assert_eq!(depth_vec, [None, Some(1), Some(2), Some(2)]);
+    assert_eq!(analysis.eval, Some(Eval::Centipawns(35)));
+
+    let e4_node = game.root().mainline().unwrap();
+    let analysis = engine.analyze(&e4_node, &Limits::depth(2)).await.unwrap();
+    assert_eq!(analysis.best_move.unwrap().to(), Square::C5);
+
+    // A search without limits runs until it is cancelled
+    let analysis = engine
+        .analyze_position_with(
+            &game.initial_position(),
+            &Limits::default(),
+            future::ready(()),
+            |_| {},
+        )
+        .await
+        .unwrap();
+    assert_eq!(analysis.best_move.unwrap().to(), Square::E4);
+    assert_eq!(analysis.eval, Some(Eval::Centipawns(20)));
+
+    engine.quit().await.unwrap();
+}